@@ -1,31 +1,63 @@
 // crates/elf2ohlink/src/main.rs
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Parser, ValueEnum};
+use ohlink_elf::{convert_elf_to_ohlink, ConvertOptions};
 use ohlink_format::*;
-use object::{Object, ObjectSection, ObjectSymbol};
-use object::RelocationKind;
-use object::elf;
-use std::fs;
-use std::path::PathBuf;
+use object::Object;
+use rayon::prelude::*;
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    after_help = "Arguments of the form @FILE are replaced by the whitespace-separated arguments in FILE \
+                  (quotes and backslash escapes are honoured, nesting is allowed).\n\
+                  -MD and -MF FILE are accepted as spellings of --MD and --MF."
+)]
 struct Args {
-    /// Input ELF file
-    input: PathBuf,
-    
-    /// Output Ohlink file
-    #[arg(short, long)]
+    /// Input ELF files
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output Ohlink file (single input only)
+    #[arg(short, long, conflicts_with = "out_dir")]
     output: Option<PathBuf>,
-    
-    /// Output file type
+
+    /// Directory for outputs, named after each input with an .ohlink extension
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Number of parallel conversions (default: number of CPUs, 1 with --verbose)
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// Output file type (relocatable inputs only; linked executables and
+    /// shared objects are detected from the ELF header)
     #[arg(long, value_enum, default_value_t = FileType::Object)]
     file_type: FileType,
-    
-    /// Verbose output
-    #[arg(short, long)]
+
+    /// Write a Make dependency rule for every output to OUTPUT.d
+    #[arg(long = "MD")]
+    md: bool,
+
+    /// Write all dependency rules to FILE instead
+    #[arg(long = "MF", value_name = "FILE")]
+    mf: Option<PathBuf>,
+
+    /// Print a detailed report for every converted file
+    #[arg(short, long, conflicts_with = "quiet")]
     verbose: bool,
+
+    /// Do not print the summary line
+    #[arg(short, long)]
+    quiet: bool,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -45,288 +77,280 @@ impl From<FileType> for u32 {
     }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    
+/// 展开 @FILE 响应文件，并把 GCC 风格的 -MD/-MF 改写为 clap 能识别的长选项
+fn expand_args(args: impl IntoIterator<Item = String>, depth: usize) -> Result<Vec<String>> {
+    if depth > 16 {
+        bail!("response files nested too deeply");
+    }
+    let mut out = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-MD" => out.push("--MD".to_string()),
+            "-MF" => out.push("--MF".to_string()),
+            _ => match arg.strip_prefix('@') {
+                Some(path) if !path.is_empty() => {
+                    let text = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read response file: {}", path))?;
+                    out.extend(expand_args(split_response_file(&text), depth + 1)?);
+                }
+                _ => out.push(arg),
+            },
+        }
+    }
+    Ok(out)
+}
+
+/// 按空白切分响应文件内容；单/双引号内的空白保留，反斜杠转义下一个字符
+fn split_response_file(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut cur = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                if let Some(next) = chars.next() {
+                    cur.push(next);
+                }
+                in_word = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => cur.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut cur));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                cur.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(cur);
+    }
+    words
+}
+
+/// 输入对应的输出路径：-o 优先，其次 --out-dir/<文件名>.ohlink，否则与输入同目录
+fn output_path(args: &Args, input: &Path) -> PathBuf {
+    if let Some(out) = &args.output {
+        return out.clone();
+    }
+    let mut path = match &args.out_dir {
+        Some(dir) => dir.join(input.file_name().unwrap_or(input.as_os_str())),
+        None => input.to_path_buf(),
+    };
+    path.set_extension("ohlink");
+    path
+}
+
+/// Make 规则中的路径：转义空格、`#` 与 `$`
+fn make_escape(path: &Path) -> String {
+    let mut s = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            ' ' | '#' => {
+                s.push('\\');
+                s.push(c);
+            }
+            '$' => s.push_str("$$"),
+            _ => s.push(c),
+        }
+    }
+    s
+}
+
+/// 转换一个文件，返回输出大小；`verbose` 时附带详细报告
+fn convert_one(input: &Path, output: &Path, opts: &ConvertOptions) -> Result<(usize, Option<String>)> {
     // 1. 读取输入文件
-    let data = fs::read(&args.input)
-        .with_context(|| format!("Failed to read input file: {:?}", args.input))?;
-    
+    let data = fs::read(input)
+        .with_context(|| format!("Failed to read input file: {:?}", input))?;
+
     // 2. 解析 ELF 文件
     let obj = object::File::parse(&*data)
         .with_context(|| "Failed to parse ELF file")?;
-    
-    println!("=== ELF to Ohlink Converter ===");
-    println!("Input: {:?}", args.input);
-    println!("ELF type: {:?}", obj.kind());
-    println!("Sections: {}", obj.sections().count());
-    println!("Symbols: {}", obj.symbols().count());
-    
+
     // 3. 转换为 Ohlink
-    let ohlink_data = convert_elf_to_ohlink(&obj, args.file_type.into(), args.verbose)
+    let ohlink_data = convert_elf_to_ohlink(&obj, opts)
         .with_context(|| "Conversion failed")?;
-    
+
     // 4. 写入输出文件
-    let output_path = args.output.unwrap_or_else(|| {
-        let mut path = args.input.clone();
-        path.set_extension("ohlink");
-        path
-    });
-    
-    fs::write(&output_path, &ohlink_data)
-        .with_context(|| format!("Failed to write output: {:?}", output_path))?;
-    
-    // 5. 解析并显示结果
-    let ohlink_file = OhlinkFile::parse(&ohlink_data)
+    fs::write(output, &ohlink_data)
+        .with_context(|| format!("Failed to write output: {:?}", output))?;
+
+    if !opts.verbose {
+        return Ok((ohlink_data.len(), None));
+    }
+    let report = report(input, output, &obj, &ohlink_data)?;
+    Ok((ohlink_data.len(), Some(report)))
+}
+
+/// 详细报告：输入 ELF 概况与生成的段、节、符号表
+fn report(input: &Path, output: &Path, obj: &object::File, ohlink_data: &[u8]) -> Result<String> {
+    let ohlink_file = OhlinkFile::parse(ohlink_data)
         .with_context(|| "Failed to parse generated Ohlink file")?;
-    
-    println!("\n=== Conversion Results ===");
-    println!("Output: {:?}", output_path);
-    println!("Size: {} bytes", ohlink_data.len());
+
+    let mut r = String::new();
+    writeln!(r, "=== ELF to Ohlink Converter ===")?;
+    writeln!(r, "Input: {:?}", input)?;
+    writeln!(r, "ELF type: {:?}", obj.kind())?;
+    writeln!(r, "Sections: {}", obj.sections().count())?;
+    writeln!(r, "Symbols: {}", obj.symbols().count())?;
+
+    writeln!(r, "\n=== Conversion Results ===")?;
+    writeln!(r, "Output: {:?}", output)?;
+    writeln!(r, "Size: {} bytes", ohlink_data.len())?;
     let magic_le = u32::from_le_bytes(ohlink_file.header.magic);
-    println!("Magic: {:#010x}", magic_le);
-    println!("CPU: ARM64");
-    println!("File type: {:#x}", ohlink_file.header.file_type);
-    println!("Load commands: {}", ohlink_file.header.ncmds);
-    
+    writeln!(r, "Magic: {:#010x}", magic_le)?;
+    writeln!(r, "CPU: ARM64")?;
+    writeln!(r, "File type: {:#x}", ohlink_file.header.file_type)?;
+    writeln!(r, "Load commands: {}", ohlink_file.header.ncmds)?;
+
     // 显示段信息
     for (i, cmd) in ohlink_file.commands.iter().enumerate() {
         match cmd {
             LoadCommand::Segment64(segment, sections) => {
-                let segname_cow = String::from_utf8_lossy(&segment.segname);
-                let segname = segname_cow.trim_end_matches('\0');
-                println!("\nSegment {}: {}", i, segname);
-                println!("  VM range: {:#x} - {:#x}", 
-                         segment.vmaddr, segment.vmaddr + segment.vmsize);
-                println!("  File range: {:#x} - {:#x}", 
-                         segment.fileoff, segment.fileoff + segment.filesize);
-                println!("  Sections: {}", sections.len());
-                
+                let segname = ohlink_file.segment_name(segment);
+                writeln!(r, "\nSegment {}: {}", i, segname)?;
+                writeln!(r, "  VM range: {:#x} - {:#x}",
+                         segment.vmaddr, segment.vmaddr + segment.vmsize)?;
+                writeln!(r, "  File range: {:#x} - {:#x}",
+                         segment.fileoff, segment.fileoff + segment.filesize)?;
+                writeln!(r, "  Sections: {}", sections.len())?;
+
                 for (j, section) in sections.iter().enumerate() {
-                    let sectname_cow = String::from_utf8_lossy(&section.sectname);
-                    let sectname = sectname_cow.trim_end_matches('\0');
-                    println!("    [{:2}] {:16} addr:{:#010x} size:{:#6x} offset:{:#x}", 
-                             j, sectname, section.addr, section.size, section.offset);
+                    let sectname = ohlink_file.section_name(section);
+                    writeln!(r, "    [{:2}] {:16} addr:{:#010x} size:{:#6x} offset:{:#x}",
+                             j, sectname, section.addr, section.size, section.offset)?;
                 }
             }
             LoadCommand::Symtab(symtab) => {
-                println!("\nSymbol Table:");
-                println!("  Symbols: {}", symtab.nsyms);
-                println!("  Symbol offset: {:#x}", symtab.symoff);
-                println!("  String table offset: {:#x}", symtab.stroff);
+                writeln!(r, "\nSymbol Table:")?;
+                writeln!(r, "  Symbols: {}", symtab.nsyms)?;
+                writeln!(r, "  Symbol offset: {:#x}", symtab.symoff)?;
+                writeln!(r, "  String table offset: {:#x}", symtab.stroff)?;
             }
             LoadCommand::Unknown { cmd, cmdsize, .. } => {
-                println!("\nUnknown command: {:#x} (size: {})", cmd, cmdsize);
+                writeln!(r, "\nUnknown command: {:#x} (size: {})", cmd, cmdsize)?;
             }
             _ => { }
         }
     }
-    
-    println!("\n✅ Conversion successful!");
-    Ok(())
+
+    writeln!(r, "\n✅ Conversion successful!")?;
+    Ok(r)
 }
 
-fn convert_elf_to_ohlink(elf: &object::File, file_type: u32, verbose: bool) -> Result<Vec<u8>> {
-    let mut builder = OhlinkBuilder::new(file_type);
-    
-    let mut text_additions: Vec<(&'static str, Vec<u8>, u64, usize)> = Vec::new();
-    let mut data_additions: Vec<(&'static str, Vec<u8>, u64, usize)> = Vec::new();
-    let mut symbol_mapping = Vec::new();
-    
-    for (elf_section_idx, section) in elf.sections().enumerate() {
-        if let Ok(name) = section.name() {
-            if verbose {
-                println!("Processing section {}: '{}'", elf_section_idx, name);
-            }
-            
-            match name {
-                ".text" => {
-                    if let Ok(data) = section.data() {
-                        if !data.is_empty() {
-                            let align = section.align() as u32;
-                            let size = section.size();
-                            text_additions.push(("__text", data.to_vec(), section.address(), elf_section_idx));
-                            if verbose {
-                                println!("  -> __TEXT,__text ({} bytes)", data.len());
-                            }
-                        }
-                    }
-                }
-                ".data" => {
-                    if let Ok(data) = section.data() {
-                        if !data.is_empty() {
-                            let align = section.align() as u32;
-                            let size = section.size();
-                            data_additions.push(("__data", data.to_vec(), section.address(), elf_section_idx));
-                            if verbose {
-                                println!("  -> __DATA,__data ({} bytes)", data.len());
-                            }
-                        }
-                    }
-                }
-                ".rodata" => {
-                    if let Ok(data) = section.data() {
-                        if !data.is_empty() {
-                            let align = section.align() as u32;
-                            let size = section.size();
-                            text_additions.push(("__cstring", data.to_vec(), section.address(), elf_section_idx));
-                            if verbose {
-                                println!("  -> __TEXT,__cstring ({} bytes)", data.len());
-                            }
-                        }
-                    }
-                }
-                name if name.starts_with(".rodata.str") => {
-                    if let Ok(data) = section.data() {
-                        if !data.is_empty() {
-                            let align = section.align() as u32;
-                            let size = section.size();
-                            text_additions.push(("__cstring", data.to_vec(), section.address(), elf_section_idx));
-                            if verbose {
-                                println!("  -> __TEXT,__cstring ({} bytes)", data.len());
-                            }
-                        }
-                    }
-                }
-                ".bss" => {
-                    if section.size() > 0 {
-                        let align = section.align() as u32;
-                        let size = section.size();
-                        data_additions.push(("__bss", Vec::new(), section.address(), elf_section_idx));
-                        if verbose {
-                            println!("  -> __DATA,__bss ({} bytes, zero-filled)", section.size());
-                        }
-                    }
-                }
-                _ => {
-                    if verbose && !name.starts_with('.') {
-                        println!("  Skipping non-standard section: {}", name);
-                    }
-                }
-            }
-        }
+fn main() -> Result<()> {
+    let argv = expand_args(std::env::args(), 0)?;
+    let args = Args::parse_from(argv);
+    let started = Instant::now();
+
+    if args.output.is_some() && args.inputs.len() > 1 {
+        bail!("-o can only be used with a single input; use --out-dir for several");
     }
-    
-    // 先构建节与段，建立 ELF->Ohlink 节索引映射后再转换符号
-    
-    let mut section_map: HashMap<usize, u8> = HashMap::new();
-    let mut section_ord: u8 = 0;
-    {
-        let text_segment = builder.add_segment("__TEXT", 0x4000_0000);
-        for (name, data, addr, elf_idx) in text_additions.drain(..) {
-            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
-            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
-            text_segment.add_section_with(name, &data, addr, align, size);
-            section_map.insert(elf_idx, section_ord);
-            section_ord = section_ord.wrapping_add(1);
-        }
+    if let Some(dir) = &args.out_dir {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create output directory: {:?}", dir))?;
     }
-    {
-        let data_segment = builder.add_segment("__DATA", 0x4000_8000);
-        for (name, data, addr, elf_idx) in data_additions.drain(..) {
-            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
-            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
-            data_segment.add_section_with(name, &data, addr, align, size);
-            section_map.insert(elf_idx, section_ord);
-            section_ord = section_ord.wrapping_add(1);
+
+    // 输入与输出一一对应，不同输入不能写到同一个输出
+    let jobs: Vec<(&PathBuf, PathBuf)> = args.inputs.iter().map(|i| (i, output_path(&args, i))).collect();
+    let mut seen: HashMap<&Path, &Path> = HashMap::new();
+    for (input, output) in &jobs {
+        if let Some(prev) = seen.insert(output, input) {
+            bail!("{:?} and {:?} would both be written to {:?}", prev, input, output);
         }
     }
 
-    // 转换符号（现在已有节索引映射）
-    let mut elf_to_oh_sym: HashMap<usize, u32> = HashMap::new();
-    for symbol in elf.symbols() {
-        if let Ok(name) = symbol.name() {
-            if name.is_empty() {
-                continue;
-            }
-
-            let symbol_section = match symbol.section() {
-                object::SymbolSection::Section(idx) => *section_map.get(&idx.0).unwrap_or(&0u8),
-                _ => 0u8,
-            };
+    let opts = ConvertOptions { file_type: args.file_type.clone().into(), verbose: args.verbose };
+    // 详细模式下转换过程本身也会打印，未指定 -j 时串行执行以免输出交错
+    let threads = args.jobs.unwrap_or(if args.verbose { 1 } else { 0 });
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .context("Failed to start worker threads")?;
+    let results: Vec<Result<(usize, Option<String>)>> = pool.install(|| {
+        jobs.par_iter().map(|(input, output)| convert_one(input, output, &opts)).collect()
+    });
 
-            if verbose && symbol.kind() == object::SymbolKind::Text {
-                println!(
-                    "Adding symbol: {} at {:#x} (section: {})",
-                    name, symbol.address(), symbol_section
-                );
+    // 按输入顺序汇报，失败的文件逐个列出
+    let mut converted = 0usize;
+    let mut total_bytes = 0usize;
+    let mut failed = 0usize;
+    let mut deps = String::new();
+    for ((input, output), result) in jobs.iter().zip(results) {
+        match result {
+            Ok((size, report)) => {
+                converted += 1;
+                total_bytes += size;
+                if let Some(report) = report {
+                    println!("{}", report);
+                }
+                let rule = format!("{}: {}\n", make_escape(output), make_escape(input));
+                if args.md && args.mf.is_none() {
+                    let mut dep = output.clone().into_os_string();
+                    dep.push(".d");
+                    fs::write(&dep, &rule)
+                        .with_context(|| format!("Failed to write dependency file: {:?}", dep))?;
+                }
+                deps.push_str(&rule);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("error: {}: {:#}", input.display(), e);
             }
-
-            let n_type = if matches!(symbol.section(), object::SymbolSection::Undefined)
-                { 0x00 } else if symbol.is_global() { 0x0f } else { 0x0e };
-            let symbol_idx = builder.add_symbol_with(name, symbol.address(), symbol_section, n_type, 0);
-            // 建立 ELF 符号索引到 Ohlink 符号索引的映射
-            let elf_sym_idx = symbol.index().0;
-            elf_to_oh_sym.insert(elf_sym_idx, symbol_idx);
-            symbol_mapping.push((name.to_string(), symbol_idx));
         }
     }
-
-    if verbose {
-        println!("\nSymbol mapping:");
-        for (name, idx) in &symbol_mapping {
-            println!("  {} -> symbol index {}", name, idx);
-        }
+    if let Some(mf) = &args.mf {
+        fs::write(mf, &deps)
+            .with_context(|| format!("Failed to write dependency file: {:?}", mf))?;
     }
 
-    // 收集并写入重定位信息
-    let mut reloc_map: HashMap<usize, Vec<Relocation64>> = HashMap::new();
-    for (elf_section_idx, section) in elf.sections().enumerate() {
-        for (offset, reloc) in section.relocations() {
-            let r_addr = section.address().wrapping_add(offset);
-            let r_symbol_elf = match reloc.target() {
-                object::RelocationTarget::Symbol(si) => si.0,
-                _ => 0,
-            };
-            let r_symbol = elf_to_oh_sym.get(&r_symbol_elf).copied().unwrap_or(0);
-            let r_type = map_relocation_type(&reloc);
-            let r_addend = reloc.addend();
-            let r = Relocation64 { r_addr, r_symbol, r_type, r_addend };
-            reloc_map.entry(elf_section_idx).or_default().push(r);
-        }
+    if !args.quiet {
+        println!(
+            "Converted {} of {} file(s), {} bytes, in {:.2?}",
+            converted, jobs.len(), total_bytes, started.elapsed()
+        );
     }
-    // 写入到相应的 Ohlink 节（按 ord）
-    for (elf_idx, relocs) in reloc_map.iter() {
-        if let Some(ord) = section_map.get(elf_idx) {
-            builder.add_relocations_by_ord(*ord, relocs);
-        }
+    if failed > 0 {
+        bail!("{} of {} conversion(s) failed", failed, jobs.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_file_words() {
+        assert_eq!(split_response_file("a.o  'b c.o'\n\"d e\".o f\\ g.o"), ["a.o", "b c.o", "d e.o", "f g.o"]);
+        assert_eq!(split_response_file("'' x\\\\y \"it's\""), ["", "x\\y", "it's"]);
+        assert!(split_response_file(" \n\t").is_empty());
     }
 
-    let segments_count = builder.segment_count();
-    let symbols_count = builder.symbol_count();
-    let ohlink_data = builder.build();
-    
-    if verbose {
-        println!("\nGenerated Ohlink file:");
-        println!("  Total size: {} bytes", ohlink_data.len());
-        println!("  Segments: {}", segments_count);
-        println!("  Symbols: {}", symbols_count);
+    #[test]
+    fn expands_response_files_and_dep_flags() {
+        let rsp = std::env::temp_dir().join(format!("elf2ohlink-test-{}.rsp", std::process::id()));
+        fs::write(&rsp, "-MD -MF 'out dir/a.d'\nb.o").unwrap();
+        let args = ["-o", "x.ohlink", &format!("@{}", rsp.display()), "@", "-MF"].map(String::from);
+        let expanded = expand_args(args, 0);
+        fs::remove_file(&rsp).unwrap();
+        assert_eq!(expanded.unwrap(), ["-o", "x.ohlink", "--MD", "--MF", "out dir/a.d", "b.o", "@", "--MF"]);
+        assert!(expand_args(["@/nonexistent/elf2ohlink.rsp".to_string()], 0).is_err());
     }
-    
-    Ok(ohlink_data)
-}
 
-fn map_relocation_type(reloc: &object::Relocation) -> u32 {
-    match reloc.kind() {
-        RelocationKind::Absolute => match reloc.size() {
-            64 => RELOC_ABS64,
-            32 => RELOC_ABS32,
-            _ => RELOC_NONE,
-        },
-        RelocationKind::Relative => match reloc.size() {
-            64 => RELOC_REL64,
-            32 => RELOC_REL32,
-            _ => RELOC_NONE,
-        },
-        RelocationKind::Got | RelocationKind::GotRelative | RelocationKind::GotBaseRelative | RelocationKind::GotBaseOffset => RELOC_GOT,
-        RelocationKind::PltRelative => RELOC_PLT,
-        RelocationKind::Elf(t) => match t {
-            elf::R_AARCH64_CALL26 | elf::R_AARCH64_JUMP26 => RELOC_BRANCH26,
-            elf::R_AARCH64_ADR_PREL_PG_HI21 => RELOC_AARCH64_ADR_PREL_PG_HI21,
-            elf::R_AARCH64_ADD_ABS_LO12_NC => RELOC_AARCH64_ADD_ABS_LO12_NC,
-            elf::R_AARCH64_LD_PREL_LO19 => RELOC_AARCH64_LD_PREL_LO19,
-            _ => RELOC_NONE,
-        },
-        _ => RELOC_NONE,
+    #[test]
+    fn make_rule_escaping() {
+        assert_eq!(make_escape(Path::new("out dir/a#1$x.o")), "out\\ dir/a\\#1$$x.o");
     }
 }
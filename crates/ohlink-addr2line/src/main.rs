@@ -0,0 +1,233 @@
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use gimli::{EndianSlice, LittleEndian};
+use ohlink_format::*;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Map addresses in Ohlink files to symbols and source lines",
+    long_about = None,
+    after_help = "Addresses are hexadecimal, with or without 0x. The outermost function of every address is shown as\n\
+                  symbol+offset from the symbol table; file:line and inlined frames come from the __DWARF segment\n\
+                  (??:0 without debug info). Without ADDR or with --batch, every 0x... word read from stdin is looked up."
+)]
+struct Args {
+    /// Addresses to look up
+    #[arg(value_name = "ADDR")]
+    addrs: Vec<String>,
+    /// Ohlink executable to use
+    #[arg(short = 'e', long = "exe", value_name = "FILE", default_value = "a.out")]
+    exe: PathBuf,
+    /// Print the address before the results
+    #[arg(short = 'a', long)]
+    addresses: bool,
+    /// Print function names
+    #[arg(short = 'f', long)]
+    functions: bool,
+    /// Also print the callers of inlined functions
+    #[arg(short = 'i', long)]
+    inlines: bool,
+    /// Print the results of one address on a single line (inlined callers on their own lines)
+    #[arg(short = 'p', long)]
+    pretty_print: bool,
+    /// Strip directories from file names
+    #[arg(short = 's', long)]
+    basenames: bool,
+    /// Demangle Rust and C++ symbol names
+    #[arg(short = 'C', long)]
+    demangle: bool,
+    /// Read addresses from stdin, one backtrace line at a time, flushing the output after every line
+    #[arg(long)]
+    batch: bool,
+}
+
+/// 一层调用帧：函数名与源码位置
+struct Frame {
+    function: Option<String>,
+    file: Option<String>,
+    line: u32,
+}
+
+/// 符号表与调试信息；符号按地址排序，只保留节内的有名符号（不含映射符号与 .L 标号）
+struct Symbolizer<'a> {
+    symbols: Vec<(u64, u64, String, bool)>,
+    dwarf: Option<addr2line::Context<Reader<'a>>>,
+}
+
+impl<'a> Symbolizer<'a> {
+    fn new(file: &'a OhlinkFile) -> Result<Self> {
+        let extents = file.symbol_extents();
+        let mut symbols: Vec<(u64, u64, String, bool)> = file
+            .symbols()
+            .into_iter()
+            .zip(extents)
+            .filter(|((name, n), _)| {
+                n.n_type & N_TYPE == N_SECT && !name.is_empty() && !is_local_label(name) && !is_mapping_symbol(name)
+            })
+            .map(|((name, n), extent)| (n.n_value, extent.unwrap_or(0), name, n.is_external()))
+            .collect();
+        symbols.sort_by_key(|s| s.0);
+
+        let has_debug = file.sections().iter().any(|(seg, sec)| is_debug_section(&file.segment_name(seg), &file.section_name(sec)));
+        let dwarf = if has_debug {
+            let dwarf = gimli::Dwarf::load(|id| -> Result<Reader<'a>, gimli::Error> {
+                Ok(EndianSlice::new(debug_section(file, id.name()), LittleEndian))
+            })?;
+            Some(addr2line::Context::from_dwarf(dwarf).context("Failed to read debug info")?)
+        } else {
+            None
+        };
+        Ok(Symbolizer { symbols, dwarf })
+    }
+
+    /// 包含该地址的符号及偏移：取起始地址最高的那个，同一地址优先外部符号
+    fn symbol(&self, addr: u64) -> Option<(&str, u64)> {
+        let end = self.symbols.partition_point(|s| s.0 <= addr);
+        self.symbols[..end]
+            .iter()
+            .rev()
+            .filter(|(start, size, _, _)| addr < start + (*size).max(1))
+            .max_by_key(|(start, _, _, external)| (*start, *external))
+            .map(|(start, _, name, _)| (name.as_str(), addr - start))
+    }
+
+    /// 由内向外的调用帧；没有调试信息（或不在任何编译单元内）时只有一帧
+    fn frames(&self, addr: u64, demangle: bool) -> Result<Vec<Frame>> {
+        let name = |raw: &str| demangle::display_name(raw, demangle).into_owned();
+        let mut frames = Vec::new();
+        if let Some(ctx) = &self.dwarf {
+            let mut iter = ctx.find_frames(addr).skip_all_loads().context("Failed to read debug info")?;
+            while let Some(frame) = iter.next().context("Failed to read debug info")? {
+                let function = frame.function.as_ref().and_then(|f| f.raw_name().ok()).map(|raw| name(&raw));
+                let (file, line) = match &frame.location {
+                    Some(loc) => (loc.file.map(str::to_string), loc.line.unwrap_or(0)),
+                    None => (None, 0),
+                };
+                frames.push(Frame { function, file, line });
+            }
+        }
+        if frames.is_empty() {
+            frames.push(Frame { function: None, file: None, line: 0 });
+        }
+        // 最外层是实际执行的函数，按符号表显示为 symbol+offset
+        if let Some((sym, offset)) = self.symbol(addr) {
+            let sym = name(sym);
+            frames.last_mut().unwrap().function = Some(if offset == 0 { sym } else { format!("{}+{:#x}", sym, offset) });
+        }
+        Ok(frames)
+    }
+}
+
+/// ELF 名字对应的调试节内容（`.debug_info` -> `__debug_info`）；没有时为空
+fn debug_section<'a>(file: &'a OhlinkFile, elf_name: &str) -> &'a [u8] {
+    let Some(name) = dwarf_section_name(elf_name) else { return &[] };
+    file.sections()
+        .into_iter()
+        .find(|(seg, sec)| file.section_name(sec) == name && is_debug_section(&file.segment_name(seg), &name))
+        .map(|(_, sec)| file.section_data(sec))
+        .unwrap_or(&[])
+}
+
+fn parse_address(s: &str) -> Option<u64> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// 回溯中的一行：取出所有 0x 开头的词（以标点分隔，如 `lr=0x...`），紧跟在 `+`/`-` 后的偏移
+/// （如 `_start+0x8`）不算地址；整行只有一个数时也接受不带 0x 的十六进制数
+fn addresses_in_line(line: &str) -> Vec<u64> {
+    if let Some(addr) = parse_address(line.trim()) {
+        return vec![addr];
+    }
+    line.split(|c: char| !c.is_ascii_alphanumeric() && c != '+' && c != '-')
+        .filter(|w| w.starts_with("0x") || w.starts_with("0X"))
+        .filter_map(|w| w.split(['+', '-']).next())
+        .filter_map(parse_address)
+        .collect()
+}
+
+fn print_address(out: &mut impl Write, sym: &Symbolizer, addr: u64, args: &Args) -> Result<()> {
+    let mut frames = sym.frames(addr, args.demangle)?;
+    if !args.inlines {
+        frames.truncate(1);
+    }
+    let location = |f: &Frame| {
+        let file = match &f.file {
+            Some(file) if args.basenames => file.rsplit('/').next().unwrap_or(file).to_string(),
+            Some(file) => file.clone(),
+            None => return "??:0".to_string(),
+        };
+        format!("{}:{}", file, f.line)
+    };
+    let function = |f: &Frame| f.function.clone().unwrap_or_else(|| "??".to_string());
+    if args.pretty_print {
+        if args.addresses {
+            write!(out, "{:#018x}: ", addr)?;
+        }
+        for (i, f) in frames.iter().enumerate() {
+            let prefix = if i == 0 { "" } else { " (inlined by) " };
+            if args.functions {
+                writeln!(out, "{}{} at {}", prefix, function(f), location(f))?;
+            } else {
+                writeln!(out, "{}{}", prefix, location(f))?;
+            }
+        }
+    } else {
+        if args.addresses {
+            writeln!(out, "{:#018x}", addr)?;
+        }
+        for f in &frames {
+            if args.functions {
+                writeln!(out, "{}", function(f))?;
+            }
+            writeln!(out, "{}", location(f))?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let data = fs::read(&args.exe).with_context(|| format!("Failed to read file: {:?}", args.exe))?;
+    if data.get(0..4) == Some(&OHLIB_MAGIC[..]) {
+        bail!("{:?}: archives are not supported", args.exe);
+    }
+    let file = OhlinkFile::parse(&data).with_context(|| format!("Failed to parse Ohlink file: {:?}", args.exe))?;
+    let sym = Symbolizer::new(&file)?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if args.batch || args.addrs.is_empty() {
+        for line in io::stdin().lock().lines() {
+            for addr in addresses_in_line(&line?) {
+                print_address(&mut out, &sym, addr, &args)?;
+            }
+            out.flush()?;
+        }
+    }
+    for a in &args.addrs {
+        let addr = parse_address(a).with_context(|| format!("bad address: {}", a))?;
+        print_address(&mut out, &sym, addr, &args)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backtrace_line_addresses() {
+        assert_eq!(addresses_in_line("#1 pc 0x40000008 lr=0x4000001c (_start+0x1c)"), [0x4000_0008, 0x4000_001c]);
+        assert_eq!(addresses_in_line("sp-0x10 0x40000000+0x8"), [0x4000_0000]);
+        assert_eq!(addresses_in_line("  40000028 "), [0x4000_0028]);
+        assert!(addresses_in_line("panicked at src/main.rs:3").is_empty());
+    }
+}
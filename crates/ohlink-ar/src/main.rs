@@ -1,28 +1,416 @@
-use anyhow::Result;
-use ohlink_format::OhlibBuilder;
+use anyhow::{bail, Context, Result};
+use ohlink_format::{OhlibArchive, OhlibBuilder};
+use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use clap::Parser;
 
 #[derive(Parser)]
-#[command(about = "Create Ohlib static library")]
+#[command(
+    about = "Create and maintain Ohlib static libraries",
+    override_usage = "ohlink-ar -o <OUTPUT> [INPUTS]...\n       \
+                      ohlink-ar <t|x|p|r|q|d|m|s>[modifiers] [RELPOS] <ARCHIVE> [MEMBERS]...\n       \
+                      ohlink-ar -M < script.mri\n       \
+                      ohlink-ar --from-ar <LIB.a> -o <OUTPUT>",
+    after_help = "Modifiers: v verbose, c no create message, s write index (default), S no index,\n\
+                  a/b/i insert after/before RELPOS (r, m), D/U accepted for compatibility."
+)]
 struct Args {
-    /// Output .ohlib
+    /// Output .ohlib (create a new archive from INPUTS)
     #[arg(short, long)]
-    output: PathBuf,
-    /// Input .o files
-    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    /// Regenerate the symbol index of existing archives (same as `ohlink-ar s lib.ohlib`)
+    #[arg(long)]
+    index: bool,
+    /// Run an MRI librarian script read from stdin
+    #[arg(short = 'M')]
+    mri: bool,
+    /// Convert a GNU/BSD `!<arch>` archive of ELF objects into an .ohlib (use with -o)
+    #[arg(long, value_name = "LIB.a")]
+    from_ar: Option<PathBuf>,
+    /// Deterministic output; always in effect since ohlib stores no timestamps, uids or modes
+    #[arg(long)]
+    deterministic: bool,
+    /// Operation and its operands, or input files with -o
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    inputs: Vec<OsString>,
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();   // ← 就是这行缺失
-    let mut b = OhlibBuilder::new();
-    for p in &args.inputs {
-        let bytes = fs::read(p)?;
-        let name = p.file_name().unwrap().to_str().unwrap();
-        b.add_member(name, &bytes);
-    }
-    fs::write(&args.output, &b.build())?;
-    println!("ar: {} members -> {}", args.inputs.len(), args.output.display());
+    let args = Args::parse();
+    if args.mri {
+        return run_mri(io::stdin().lock(), Path::new("."), &mut io::stdout().lock());
+    }
+    if let Some(input) = &args.from_ar {
+        let output = args.output.as_ref().context("--from-ar needs -o <output>")?;
+        let data = fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+        let bytes = ohlink_elf::convert_ar_to_ohlib(&data, &Default::default())
+            .with_context(|| format!("Failed to convert {}", input.display()))?;
+        fs::write(output, &bytes)?;
+        let members = OhlibArchive::parse(&bytes)?.len();
+        println!("ar: {} members {} -> {}", members, input.display(), output.display());
+        return Ok(());
+    }
+    if let Some(output) = &args.output {
+        // 兼容旧用法：ohlink-ar -o out.ohlib a.ohlink b.ohlink
+        let mut b = OhlibBuilder::new();
+        for p in &args.inputs {
+            let p = Path::new(p);
+            let bytes = fs::read(p).with_context(|| format!("Failed to read {}", p.display()))?;
+            b.add_member(&member_name_of(p), &bytes);
+        }
+        fs::write(output, b.build())?;
+        println!("ar: {} members -> {}", args.inputs.len(), output.display());
+        return Ok(());
+    }
+    if args.index {
+        for p in &args.inputs {
+            let p = Path::new(p);
+            let (arch, _) = load_archive(p, false)?;
+            save_archive(p, &arch, true)?;
+        }
+        return Ok(());
+    }
+    run_operation(&args.inputs, Path::new("."), &mut io::stdout().lock())
+}
+
+/// 执行 `<操作>[修饰符] [RELPOS] <ARCHIVE> [MEMBERS]...`；列表与 p 的内容写到 `out`，x 解包到 `dir`
+fn run_operation(inputs: &[OsString], dir: &Path, out: &mut impl Write) -> Result<()> {
+    let mut operands = inputs.iter().map(Path::new);
+    let key = operands.next().context("missing operation; see --help")?;
+    let key = key.to_string_lossy();
+    let key = key.trim_start_matches('-');
+    // 操作字母：s 既可作为操作也可作为修饰符，只有在没有其它操作时才视为操作
+    let op = key.chars().find(|c| "dmpqrtx".contains(*c))
+        .or_else(|| key.contains('s').then_some('s'))
+        .with_context(|| format!("no operation specified in `{}`", key))?;
+    let mods: String = key.chars().filter(|c| *c != op).collect();
+    let verbose = mods.contains('v');
+    let relpos = if "rm".contains(op) && mods.contains(['a', 'b', 'i']) {
+        Some(operands.next().context("missing RELPOS member")?.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+    let archive_path = operands.next().context("missing archive")?;
+    let files: Vec<&Path> = operands.collect();
+    let (mut arch, existed) = load_archive(archive_path, "rq".contains(op))?;
+    if !existed && !mods.contains('c') {
+        eprintln!("ohlink-ar: creating {}", archive_path.display());
+    }
+    let names: Vec<String> = files.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    let selected = |name: &str| names.is_empty() || names.iter().any(|n| n == name);
+    let mut modified = true;
+
+    match op {
+        't' => {
+            modified = false;
+            for (name, bytes) in arch.members() {
+                if !selected(&name) { continue; }
+                if verbose { writeln!(out, "{:>10} {}", bytes.len(), name)?; } else { writeln!(out, "{}", name)?; }
+            }
+        }
+        'p' => {
+            modified = false;
+            for (name, bytes) in arch.members() {
+                if !selected(&name) { continue; }
+                if verbose { writeln!(out, "\n<{}>\n", name)?; }
+                out.write_all(bytes)?;
+            }
+        }
+        'x' => {
+            modified = false;
+            for (name, bytes) in arch.members() {
+                if !selected(&name) { continue; }
+                if verbose { writeln!(out, "x - {}", name)?; }
+                extract_member(dir, &name, bytes)?;
+            }
+        }
+        'd' => {
+            for name in &names {
+                if arch.remove_member(name) {
+                    if verbose { writeln!(out, "d - {}", name)?; }
+                } else {
+                    eprintln!("ohlink-ar: no entry {} in archive", name);
+                }
+            }
+        }
+        'q' => {
+            for p in &files {
+                let bytes = fs::read(p).with_context(|| format!("Failed to read {}", p.display()))?;
+                if verbose { writeln!(out, "a - {}", p.display())?; }
+                arch.append_member(&member_name_of(p), &bytes);
+            }
+        }
+        'r' => {
+            let mut inputs = Vec::new();
+            for p in &files {
+                let bytes = fs::read(p).with_context(|| format!("Failed to read {}", p.display()))?;
+                inputs.push((member_name_of(p), bytes));
+            }
+            match &relpos {
+                None => {
+                    for (name, bytes) in &inputs {
+                        if arch.replace_member(name, bytes) {
+                            if verbose { writeln!(out, "r - {}", name)?; }
+                        } else {
+                            if verbose { writeln!(out, "a - {}", name)?; }
+                            arch.append_member(name, bytes);
+                        }
+                    }
+                }
+                Some(rel) => {
+                    for (name, _) in &inputs {
+                        if name != rel { arch.remove_member(name); }
+                    }
+                    let pos = insert_position(&arch, rel, &mods)?;
+                    for (i, (name, bytes)) in inputs.iter().enumerate() {
+                        if verbose { writeln!(out, "r - {}", name)?; }
+                        arch.insert_member(pos + i, name, bytes);
+                    }
+                }
+            }
+        }
+        'm' => {
+            match &relpos {
+                None => {
+                    for name in &names {
+                        if !arch.move_member(name, usize::MAX) { bail!("no entry {} in archive", name); }
+                    }
+                }
+                Some(rel) => {
+                    let mut moving = Vec::new();
+                    for name in &names {
+                        let pos = arch.position(name).with_context(|| format!("no entry {} in archive", name))?;
+                        moving.push((name.clone(), arch.member(pos).map(|m| m.1.to_vec()).unwrap_or_default()));
+                        arch.remove_member(name);
+                    }
+                    let pos = insert_position(&arch, rel, &mods)?;
+                    for (i, (name, bytes)) in moving.iter().enumerate() {
+                        arch.insert_member(pos + i, name, bytes);
+                    }
+                }
+            }
+        }
+        's' => {}
+        _ => unreachable!(),
+    }
+
+    if modified {
+        save_archive(archive_path, &arch, !mods.contains('S'))?;
+    }
+    Ok(())
+}
+
+/// a 插在 RELPOS 之后，b/i 插在之前
+fn insert_position(arch: &OhlibArchive, relpos: &str, mods: &str) -> Result<usize> {
+    let pos = arch.position(relpos).with_context(|| format!("no entry {} in archive", relpos))?;
+    Ok(if mods.contains('a') { pos + 1 } else { pos })
+}
+
+/// 成员名取文件名部分；非 UTF-8 字节按 lossy 转换，不会 panic
+fn member_name_of(path: &Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// 读取 .ohlib；`!<arch>` 归档先转换为 ohlib（写回时即变为 ohlib 格式）
+fn load_archive(path: &Path, create: bool) -> Result<(OhlibArchive, bool)> {
+    match fs::read(path) {
+        Ok(data) => {
+            let data = if ohlink_elf::is_ar_archive(&data) {
+                ohlink_elf::convert_ar_to_ohlib(&data, &Default::default()).with_context(|| format!("Failed to convert {}", path.display()))?
+            } else {
+                data
+            };
+            let arch = OhlibArchive::parse(&data).with_context(|| format!("Failed to parse {}", path.display()))?;
+            Ok((arch, true))
+        }
+        Err(e) if create && e.kind() == io::ErrorKind::NotFound => Ok((OhlibArchive::new(), false)),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn save_archive(path: &Path, arch: &OhlibArchive, symbol_index: bool) -> Result<()> {
+    fs::write(path, arch.to_bytes(symbol_index)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// 解包到 `dir`（命令行中为当前目录）；成员名只取最后一级，防止写到目录之外
+fn extract_member(dir: &Path, name: &str, bytes: &[u8]) -> Result<()> {
+    let file = Path::new(name).file_name().with_context(|| format!("invalid member name: {}", name))?;
+    fs::write(dir.join(file), bytes).with_context(|| format!("Failed to write {}", name))
+}
+
+/// MRI 脚本（`ar -M`）：CREATE/OPEN/ADDMOD/ADDLIB/DELETE/REPLACE/EXTRACT/LIST/CLEAR/SAVE/END；
+/// EXTRACT 解包到 `dir`，LIST 写到 `out`
+fn run_mri(script: impl BufRead, dir: &Path, out: &mut impl Write) -> Result<()> {
+    let mut current: Option<(PathBuf, OhlibArchive)> = None;
+    let mut verbose = false;
+    for (lineno, line) in script.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['*', ';']) { continue; }
+        let mut words = line.split([' ', '\t', ',']).filter(|w| !w.is_empty());
+        let command = words.next().unwrap_or_default().to_ascii_uppercase();
+        let operands: Vec<&str> = words.collect();
+        let result = (|| -> Result<bool> {
+            match command.as_str() {
+                "CREATE" | "CREATETHIN" => {
+                    let path = operands.first().context("CREATE needs an archive name")?;
+                    current = Some((PathBuf::from(path), OhlibArchive::new()));
+                }
+                "OPEN" => {
+                    let path = PathBuf::from(operands.first().context("OPEN needs an archive name")?);
+                    let (arch, _) = load_archive(&path, false)?;
+                    current = Some((path, arch));
+                }
+                "VERBOSE" => verbose = !verbose,
+                "END" => return Ok(true),
+                _ => {
+                    let (path, arch) = current.as_mut().context("no open archive")?;
+                    match command.as_str() {
+                        "ADDMOD" => {
+                            for f in &operands {
+                                let bytes = fs::read(f).with_context(|| format!("Failed to read {}", f))?;
+                                arch.append_member(&member_name_of(Path::new(f)), &bytes);
+                            }
+                        }
+                        "ADDLIB" => {
+                            let lib = operands.first().context("ADDLIB needs a library")?;
+                            let (other, _) = load_archive(Path::new(lib), false)?;
+                            for (name, bytes) in other.members() {
+                                arch.append_member(&name, bytes);
+                            }
+                        }
+                        "DELETE" => {
+                            for name in &operands {
+                                if !arch.remove_member(name) { bail!("no entry {} in archive", name); }
+                            }
+                        }
+                        "REPLACE" => {
+                            for f in &operands {
+                                let bytes = fs::read(f).with_context(|| format!("Failed to read {}", f))?;
+                                if !arch.replace_member(&member_name_of(Path::new(f)), &bytes) {
+                                    bail!("no entry {} in archive", f);
+                                }
+                            }
+                        }
+                        "EXTRACT" => {
+                            for name in &operands {
+                                let pos = arch.position(name).with_context(|| format!("no entry {} in archive", name))?;
+                                extract_member(dir, name, arch.member(pos).map(|m| m.1).unwrap_or_default())?;
+                            }
+                        }
+                        "LIST" | "DIRECTORY" => {
+                            for (name, bytes) in arch.members() {
+                                if verbose { writeln!(out, "{:>10} {}", bytes.len(), name)?; } else { writeln!(out, "{}", name)?; }
+                            }
+                        }
+                        // 丢弃当前内容（不重新读取磁盘上的归档）
+                        "CLEAR" => *arch = OhlibArchive::new(),
+                        "SAVE" => save_archive(path, arch, true)?,
+                        other => bail!("unknown MRI command: {}", other),
+                    }
+                }
+            }
+            Ok(false)
+        })();
+        match result {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(e) => return Err(e.context(format!("MRI script line {}", lineno + 1))),
+        }
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ohlink_format::{OhlinkBuilder, MH_OBJECT};
+
+    /// 每个测试独立的临时目录
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ohlink-ar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 在 `dir` 中写一个定义 `symbol` 的目标文件，返回其路径
+    fn object(dir: &Path, file: &str, symbol: &str) -> String {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0xc0, 0x03, 0x5f, 0xd6], 0);
+        b.add_symbol(symbol, 0, 0);
+        let path = dir.join(file);
+        fs::write(&path, b.build()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn ar(dir: &Path, args: &[&str]) -> String {
+        let inputs: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let mut out = Vec::new();
+        run_operation(&inputs, dir, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn operations_and_positioning() {
+        let dir = scratch("ops");
+        let [a, b, c, d] = [("a.o", "fa"), ("b.o", "fb"), ("c.o", "fc"), ("d.o", "fd")].map(|(f, s)| object(&dir, f, s));
+        let lib = dir.join("lib.ohlib").to_string_lossy().into_owned();
+
+        ar(&dir, &["qc", &lib, &a, &b]);
+        assert_eq!(ar(&dir, &["t", &lib]), "a.o\nb.o\n");
+        ar(&dir, &["r", &lib, &c]);
+        ar(&dir, &["rb", "a.o", &lib, &d]);
+        assert_eq!(ar(&dir, &["t", &lib]), "d.o\na.o\nb.o\nc.o\n");
+        ar(&dir, &["ma", "c.o", &lib, "d.o"]);
+        assert_eq!(ar(&dir, &["t", &lib]), "a.o\nb.o\nc.o\nd.o\n");
+        ar(&dir, &["m", &lib, "a.o"]);
+        ar(&dir, &["d", &lib, "b.o"]);
+        assert_eq!(ar(&dir, &["t", &lib, "c.o", "a.o"]), "c.o\na.o\n");
+        assert_eq!(ar(&dir, &["t", &lib]), "c.o\nd.o\na.o\n");
+
+        // r 原位替换同名成员，x 解包出新内容
+        let new_a = object(&dir, "a.o", "fa2");
+        ar(&dir, &["r", &lib, &new_a]);
+        assert_eq!(ar(&dir, &["t", &lib]), "c.o\nd.o\na.o\n");
+        let out = scratch("ops-x");
+        assert_eq!(ar(&out, &["xv", &lib, "a.o"]), "x - a.o\n");
+        assert_eq!(fs::read(out.join("a.o")).unwrap(), fs::read(&new_a).unwrap());
+        assert!(!out.join("c.o").exists());
+
+        // 默认写索引，S 不写
+        let index = |lib: &str| OhlibArchive::parse(&fs::read(lib).unwrap()).unwrap().index;
+        let names: Vec<String> = index(&lib).unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["fc", "fd", "fa2"]);
+        ar(&dir, &["dS", &lib, "d.o"]);
+        assert!(index(&lib).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn mri_script() {
+        let dir = scratch("mri");
+        let [a, b, c, d] = [("a.o", "fa"), ("b.o", "fb"), ("c.o", "fc"), ("d.o", "fd")].map(|(f, s)| object(&dir, f, s));
+        let lib = dir.join("lib.ohlib").to_string_lossy().into_owned();
+        let script = format!(
+            "* comment\nCREATE {lib}\nADDMOD {a}, {b}\nSAVE\nOPEN {lib}\nDELETE a.o\nADDMOD {c}\nLIST\nEXTRACT b.o\n\
+             CLEAR\nADDMOD {d}\nSAVE\nEND\nADDMOD {a}\n"
+        );
+        let out_dir = scratch("mri-x");
+        let mut out = Vec::new();
+        run_mri(script.as_bytes(), &out_dir, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "b.o\nc.o\n");
+        assert_eq!(fs::read(out_dir.join("b.o")).unwrap(), fs::read(&b).unwrap());
+        // CLEAR 丢弃了之前的成员，SAVE 只写入之后添加的；END 之后的命令不执行
+        assert_eq!(ar(&dir, &["t", &lib]), "d.o\n");
+
+        let err = run_mri("ADDMOD x.o\n".as_bytes(), &out_dir, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "MRI script line 1");
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
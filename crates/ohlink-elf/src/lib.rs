@@ -0,0 +1,779 @@
+// crates/ohlink-elf/src/lib.rs
+//! ELF -> Ohlink 转换（elf2ohlink、ohlink-ld 与 ohlink-ar 共用）
+//!
+//! 所有工具走同一条转换路径，因此 ohlink-ld 直接链接 ELF 目标文件与先用 elf2ohlink 转换再链接的结果相同。
+//! 反向转换（Ohlink -> ELF，供 ohlink2elf 使用）见 `to_elf`。
+use anyhow::{bail, Context, Result};
+use ohlink_format::*;
+use object::{Object, ObjectKind, ObjectSection, ObjectSegment, ObjectSymbol};
+use object::RelocationKind;
+use object::elf;
+use object::read::archive::ArchiveFile;
+use std::collections::HashMap;
+
+mod to_elf;
+pub use to_elf::convert_ohlink_to_elf;
+
+/// `!<arch>` 归档（GNU/BSD 变体）的魔数
+pub const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+
+/// 转换选项
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// 输出文件类型（MH_OBJECT/MH_EXECUTE/MH_DYLIB）
+    pub file_type: u32,
+    /// 打印转换过程
+    pub verbose: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self { file_type: MH_OBJECT, verbose: false }
+    }
+}
+
+pub fn is_ar_archive(data: &[u8]) -> bool {
+    data.starts_with(AR_MAGIC)
+}
+
+/// 把 `.a` 归档转换为 `.ohlib`：ELF 成员逐个转换，已是 Ohlink 的成员原样保留，
+/// 符号表与长名字表由 object 解析后丢弃，新的索引由 OhlibBuilder 重新生成；成员总是转换为 MH_OBJECT
+pub fn convert_ar_to_ohlib(data: &[u8], opts: &ConvertOptions) -> Result<Vec<u8>> {
+    let member_opts = ConvertOptions { file_type: MH_OBJECT, ..opts.clone() };
+    let archive = ArchiveFile::parse(data).context("Failed to parse ar archive")?;
+    let mut lib = OhlibBuilder::new();
+    for member in archive.members() {
+        let member = member.context("Malformed ar member")?;
+        let name = String::from_utf8_lossy(member.name()).into_owned();
+        let bytes = member.data(data).with_context(|| format!("Malformed ar member {}", name))?;
+        if bytes.len() >= 4 && (bytes[0..4] == OHLINK_MAGIC || bytes[0..4] == OHLINK_MAGIC_64) {
+            lib.add_member(&name, bytes);
+            continue;
+        }
+        let elf = object::File::parse(bytes).with_context(|| format!("Unsupported ar member: {}", name))?;
+        if opts.verbose {
+            println!("Converting member {}", name);
+        }
+        let converted = convert_elf_to_ohlink(&elf, &member_opts)
+            .with_context(|| format!("Failed to convert member {}", name))?;
+        lib.add_member(&name, &converted);
+    }
+    Ok(lib.build())
+}
+
+/// ELF -> Ohlink：可重定位文件逐节转换；已链接的可执行文件/共享对象按 PT_LOAD 建段（见 `convert_linked_elf`）
+pub fn convert_elf_to_ohlink(elf: &object::File, opts: &ConvertOptions) -> Result<Vec<u8>> {
+    if matches!(elf.kind(), ObjectKind::Executable | ObjectKind::Dynamic) {
+        return convert_linked_elf(elf, opts);
+    }
+    let verbose = opts.verbose;
+    let mut builder = OhlinkBuilder::new(opts.file_type);
+    
+    let mut text_additions: Vec<(String, Vec<u8>, u64, usize)> = Vec::new();
+    let mut data_additions: Vec<(String, Vec<u8>, u64, usize)> = Vec::new();
+    let mut section_attrs: HashMap<usize, (u32, u32)> = HashMap::new();
+    let mut symbol_mapping = Vec::new();
+    
+    for (elf_section_idx, section) in elf.sections().enumerate() {
+        let Ok(name) = section.name() else { continue };
+        if verbose {
+            println!("Processing section {}: '{}'", elf_section_idx, name);
+        }
+        let Some((is_data, oh_name, flags, reserved1)) = classify_section(&section) else {
+            if verbose {
+                println!("  Skipping non-allocatable section: {}", name);
+            }
+            continue;
+        };
+        if section.size() == 0 {
+            continue;
+        }
+        let mut data = if flags == S_ZEROFILL { Vec::new() } else { section.data()?.to_vec() };
+        if is_legacy_ctors(name) {
+            reverse_ctors(name, &mut data)?;
+        }
+        if verbose {
+            let seg = if is_data { "__DATA" } else { "__TEXT" };
+            println!("  -> {},{} ({} bytes{})", seg, oh_name, section.size(), if flags == S_ZEROFILL { ", zero-filled" } else { "" });
+        }
+        if flags != S_REGULAR || reserved1 != 0 {
+            section_attrs.insert(elf_section_idx, (flags, reserved1));
+        }
+        let additions = if is_data { &mut data_additions } else { &mut text_additions };
+        additions.push((oh_name, data, section.address(), elf_section_idx));
+    }
+    
+    // 先构建节与段，建立 ELF->Ohlink 节索引映射后再转换符号
+    
+    let mut section_map: HashMap<usize, u8> = HashMap::new();
+    {
+        // 节地址沿用 ELF 的 sh_addr（目标文件中为 0），符号值也相对于它，段基址因此取 0
+        let text_segment = builder.add_segment("__TEXT", 0);
+        for (name, data, addr, elf_idx) in text_additions.drain(..) {
+            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
+            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
+            text_segment.add_section_with(&name, &data, addr, align, size);
+            if let Some(&(flags, reserved1)) = section_attrs.get(&elf_idx) {
+                text_segment.set_section_flags(flags, reserved1);
+            }
+            section_map.insert(elf_idx, section_ord(section_map.len())?);
+        }
+    }
+    {
+        let data_segment = builder.add_segment("__DATA", 0);
+        for (name, data, addr, elf_idx) in data_additions.drain(..) {
+            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
+            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
+            data_segment.add_section_with(&name, &data, addr, align, size);
+            if let Some(&(flags, reserved1)) = section_attrs.get(&elf_idx) {
+                data_segment.set_section_flags(flags, reserved1);
+            }
+            section_map.insert(elf_idx, section_ord(section_map.len())?);
+        }
+    }
+
+    // 转换符号（现在已有节索引映射）。节符号（STT_SECTION，如 `.L.str` 引用的 .rodata）
+    // 转为无名局部符号，值为节起始地址；位于未转换节（调试信息等）中的符号与 STT_FILE 不保留
+    let mut elf_to_oh_sym: HashMap<usize, u32> = HashMap::new();
+    for symbol in elf.symbols() {
+        if let Ok(name) = symbol.name() {
+            let is_section_symbol = symbol.kind() == object::SymbolKind::Section;
+            if (name.is_empty() && !is_section_symbol) || symbol.kind() == object::SymbolKind::File {
+                continue;
+            }
+
+            let symbol_section = match symbol.section() {
+                object::SymbolSection::Section(idx) => match section_map.get(&idx.0) {
+                    Some(&ord) => ord,
+                    None => continue,
+                },
+                _ => 0u8,
+            };
+
+            if is_section_symbol {
+                // 不属于普通节的节符号（绝对、公共）没有可对应的 Ohlink 节
+                let Some(idx) = symbol.section_index() else { continue };
+                let addr = elf.section_by_index(idx)?.address();
+                let symbol_idx = builder.add_symbol_with("", addr, symbol_section, N_SECT, 0);
+                elf_to_oh_sym.insert(symbol.index().0, symbol_idx);
+                continue;
+            }
+
+            if verbose && symbol.kind() == object::SymbolKind::Text {
+                println!(
+                    "Adding symbol: {} at {:#x} (section: {})",
+                    name, symbol.address(), symbol_section
+                );
+            }
+
+            let symbol_idx = if matches!(symbol.section(), object::SymbolSection::Undefined) {
+                let n_desc = if symbol.is_weak() { N_WEAK_REF } else { 0 };
+                builder.add_undefined_symbol(name, n_desc)
+            } else if matches!(symbol.section(), object::SymbolSection::Common) {
+                // SHN_COMMON 符号的 st_value 存放的是对齐
+                builder.add_common_symbol(name, symbol.size(), symbol.address())
+            } else if matches!(symbol.section(), object::SymbolSection::Absolute) {
+                if !symbol.is_global() {
+                    continue;
+                }
+                builder.add_absolute_symbol(name, symbol.address())
+            } else {
+                let n_type = if symbol.is_global() { N_SECT | N_EXT } else { N_SECT };
+                let n_desc = if symbol.is_weak() { N_WEAK_DEF } else { 0 };
+                let idx = builder.add_symbol_with(name, symbol.address(), symbol_section, n_type, n_desc);
+                builder.set_symbol_size(idx, symbol.size());
+                idx
+            };
+            // 建立 ELF 符号索引到 Ohlink 符号索引的映射
+            let elf_sym_idx = symbol.index().0;
+            elf_to_oh_sym.insert(elf_sym_idx, symbol_idx);
+            symbol_mapping.push((name.to_string(), symbol_idx));
+        }
+    }
+
+    if verbose {
+        println!("\nSymbol mapping:");
+        for (name, idx) in &symbol_mapping {
+            println!("  {} -> symbol index {}", name, idx);
+        }
+    }
+
+    // 收集并写入重定位信息（只处理已转换的节）；目标符号必须能映射，否则报错而不是指向任意符号
+    let mut reloc_map: HashMap<usize, Vec<Relocation64>> = HashMap::new();
+    for (elf_section_idx, section) in elf.sections().enumerate() {
+        if !section_map.contains_key(&elf_section_idx) {
+            continue;
+        }
+        let legacy_ctors = section.name().is_ok_and(is_legacy_ctors);
+        for (offset, reloc) in section.relocations() {
+            // 反转后的旧式构造表中，表项的重定位随之镜像
+            let offset = if legacy_ctors {
+                section.size().checked_sub(offset + 8).filter(|_| offset.is_multiple_of(8)).with_context(|| {
+                    format!("relocation at {}+{:#x} is not on a table entry", section.name().unwrap_or("?"), offset)
+                })?
+            } else {
+                offset
+            };
+            let r_addr = section.address().wrapping_add(offset);
+            let r_type = map_relocation_type(&reloc);
+            let r_symbol = match reloc.target() {
+                object::RelocationTarget::Symbol(si) => match elf_to_oh_sym.get(&si.0) {
+                    Some(&idx) => idx,
+                    None => {
+                        let name = elf.symbol_by_index(si).ok().and_then(|s| s.name().ok().map(str::to_string)).unwrap_or_default();
+                        bail!(
+                            "relocation at {}+{:#x} refers to symbol #{} `{}' which cannot be converted",
+                            section.name().unwrap_or("?"), offset, si.0, name
+                        );
+                    }
+                },
+                _ if r_type == RELOC_NONE => 0,
+                _ => bail!("relocation at {}+{:#x} has no target symbol", section.name().unwrap_or("?"), offset),
+            };
+            let r_addend = reloc.addend();
+            let r = Relocation64 { r_addr, r_symbol, r_type, r_addend };
+            reloc_map.entry(elf_section_idx).or_default().push(r);
+        }
+    }
+    // 写入到相应的 Ohlink 节（按 ord）
+    for (elf_idx, relocs) in reloc_map.iter() {
+        if let Some(ord) = section_map.get(elf_idx) {
+            builder.add_relocations_by_ord(*ord, relocs);
+        }
+    }
+
+    let segments_count = builder.segment_count();
+    let symbols_count = builder.symbol_count();
+    let ohlink_data = builder.build();
+    
+    if verbose {
+        println!("\nGenerated Ohlink file:");
+        println!("  Total size: {} bytes", ohlink_data.len());
+        println!("  Segments: {}", segments_count);
+        println!("  Symbols: {}", symbols_count);
+    }
+    
+    Ok(ohlink_data)
+}
+
+/// 已链接的 ELF（ET_EXEC/ET_DYN，如 ld.lld 的输出）：每个 PT_LOAD 生成一个段，保留 vmaddr、大小与权限，
+/// 入口取 e_entry。段内按节头切分为节，每个节的内容延伸到下一个节的起点，使段的文件内容与内存映像一致。
+/// 输出类型默认 ET_EXEC -> MH_EXECUTE、ET_DYN -> MH_DYLIB，`opts.file_type` 为这两者之一时以它为准。
+/// 动态重定位只接受 R_AARCH64_RELATIVE（按链接地址就地应用），其余拒绝
+fn convert_linked_elf(elf: &object::File, opts: &ConvertOptions) -> Result<Vec<u8>> {
+    let file_type = match opts.file_type {
+        MH_EXECUTE | MH_DYLIB => opts.file_type,
+        _ if elf.kind() == ObjectKind::Executable => MH_EXECUTE,
+        _ => MH_DYLIB,
+    };
+    let mut builder = OhlinkBuilder::new(file_type);
+    builder.set_entry_point(elf.entry());
+
+    // 动态重定位：RELATIVE 在加载地址等于链接地址时就是 addend
+    let mut relative: Vec<(u64, u64)> = Vec::new();
+    if let Some(relocs) = elf.dynamic_relocations() {
+        for (vaddr, reloc) in relocs {
+            match reloc.kind() {
+                RelocationKind::Elf(elf::R_AARCH64_NONE) => {}
+                RelocationKind::Elf(elf::R_AARCH64_RELATIVE) => relative.push((vaddr, reloc.addend() as u64)),
+                kind => bail!(
+                    "unsupported dynamic relocation {:?} at {:#x}; link statically (-static, --no-pie)",
+                    kind, vaddr
+                ),
+            }
+        }
+    }
+
+    let alloc_sections: Vec<object::Section> = elf
+        .sections()
+        .filter(|s| matches!(s.flags(), object::SectionFlags::Elf { sh_flags } if sh_flags & elf::SHF_ALLOC as u64 != 0))
+        // .tbss 只是 TLS 模板的大小，不占段内地址
+        .filter(|s| s.kind() != object::SectionKind::UninitializedTls && s.size() != 0)
+        .collect();
+
+    // (地址, 大小, 节序号)，用于定位符号所在的节
+    let mut placed: Vec<(u64, u64, u8)> = Vec::new();
+    let mut ord = 0;
+    for segment in elf.segments() {
+        let vaddr = segment.address();
+        let memsz = segment.size();
+        let mut image = segment.data().context("Malformed PT_LOAD")?.to_vec();
+        let filesz = image.len() as u64;
+        for &(addr, value) in &relative {
+            if addr >= vaddr && addr + 8 <= vaddr + filesz {
+                let off = (addr - vaddr) as usize;
+                image[off..off + 8].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        let p_flags = match segment.flags() {
+            object::SegmentFlags::Elf { p_flags } => p_flags,
+            _ => elf::PF_R,
+        };
+        let mut prot = 0;
+        if p_flags & elf::PF_R != 0 { prot |= VM_PROT_READ; }
+        if p_flags & elf::PF_W != 0 { prot |= VM_PROT_WRITE; }
+        if p_flags & elf::PF_X != 0 { prot |= VM_PROT_EXECUTE; }
+        let segname = if prot & VM_PROT_EXECUTE != 0 {
+            "__TEXT"
+        } else if prot & VM_PROT_WRITE != 0 {
+            "__DATA"
+        } else {
+            "__RODATA"
+        };
+
+        let mut secs: Vec<&object::Section> = alloc_sections
+            .iter()
+            .filter(|s| s.address() >= vaddr && s.address() < vaddr + memsz)
+            .collect();
+        secs.sort_by_key(|s| s.address());
+        // 段首的 ELF 头/程序头不属于任何节，运行时不需要，段从第一个节开始
+        let base = secs.first().map(|s| s.address()).unwrap_or(vaddr);
+        if opts.verbose {
+            println!("PT_LOAD {:#x} filesz {:#x} memsz {:#x} -> {} at {:#x}", vaddr, filesz, memsz, segname, base);
+        }
+        let seg = builder.add_segment(segname, base);
+        seg.set_protection(prot, prot);
+
+        let file_end = vaddr + filesz;
+        let mut covered = base;
+        for (i, s) in secs.iter().enumerate() {
+            let name = s.name().unwrap_or("");
+            let sh_flags = match s.flags() { object::SectionFlags::Elf { sh_flags } => sh_flags, _ => 0 };
+            let nobits = s.kind() == object::SectionKind::UninitializedData;
+            if is_legacy_ctors(name) && !nobits {
+                let start = (s.address() - vaddr) as usize;
+                let table = image.get_mut(start..start + s.size() as usize).context("Malformed legacy constructor table")?;
+                reverse_ctors(name, table)?;
+            }
+            let (oh_name, flags, reserved1) = match init_array_kind(name) {
+                Some((n, f, p)) => (n.to_string(), f, p),
+                None => (ohlink_section_name(name, sh_flags), if nobits { S_ZEROFILL } else { S_REGULAR }, 0),
+            };
+            let rel = s.address() - base;
+            if nobits || s.address() >= file_end {
+                seg.add_section_with(&oh_name, &[], rel, 1, s.size());
+                seg.set_section_flags(S_ZEROFILL, 0);
+            } else {
+                // 内容延伸到下一个节（或段的文件部分末尾），对齐取 1，不再插入填充
+                let next = secs[i + 1..].iter().map(|n| n.address()).next().unwrap_or(file_end).min(file_end);
+                let data = &image[(s.address() - vaddr) as usize..(next.max(s.address()) - vaddr) as usize];
+                seg.add_section_with(&oh_name, data, rel, 1, s.size());
+                seg.set_section_flags(flags, reserved1);
+            }
+            placed.push((s.address(), s.size(), section_ord(ord)?));
+            ord += 1;
+            covered = covered.max(s.address() + s.size());
+        }
+        if secs.is_empty() {
+            // 没有节头（strip 过）：整个段作为一个节
+            let name = match segname { "__TEXT" => "__text", "__DATA" => "__data", _ => "__const" };
+            seg.add_section_with(name, &image, 0, 1, filesz);
+            ord += 1;
+            covered = file_end;
+        }
+        if covered < vaddr + memsz && memsz > filesz {
+            // 没有被节覆盖的 .bss 尾部
+            seg.add_section_with("__bss", &[], covered.max(file_end) - base, 1, vaddr + memsz - covered.max(file_end));
+            seg.set_section_flags(S_ZEROFILL, 0);
+            ord += 1;
+        }
+    }
+
+    // 调试节（.debug_*，压缩的先解压）依次放入末尾不可访问的 __DWARF 段，加载器不映射，供 ohlink-addr2line 使用
+    let mut debug: Vec<(String, Vec<u8>)> = Vec::new();
+    for s in elf.sections() {
+        let Some(name) = s.name().ok().and_then(dwarf_section_name) else { continue };
+        let data = s.uncompressed_data().with_context(|| format!("Malformed debug section {}", name))?;
+        debug.push((name, data.into_owned()));
+    }
+    if !debug.is_empty() {
+        let base = builder.vm_end().next_multiple_of(0x1000);
+        let seg = builder.add_segment("__DWARF", base);
+        seg.set_protection(0, 0);
+        let mut rel = 0;
+        for (name, data) in &debug {
+            seg.add_section_with(name, data, rel, 1, data.len() as u64);
+            rel += data.len() as u64;
+        }
+    }
+
+    // 符号值已是绝对地址；不在任何节内的（如 __ehdr_start）作为绝对符号
+    for symbol in elf.symbols() {
+        let Ok(name) = symbol.name() else { continue };
+        if name.is_empty() || matches!(symbol.kind(), object::SymbolKind::Section | object::SymbolKind::File) {
+            continue;
+        }
+        match symbol.section() {
+            object::SymbolSection::Undefined => {
+                builder.add_undefined_symbol(name, if symbol.is_weak() { N_WEAK_REF } else { 0 });
+            }
+            object::SymbolSection::Section(_) => {
+                let addr = symbol.address();
+                let sect = placed
+                    .iter()
+                    .find(|(a, sz, _)| addr >= *a && addr < a + sz)
+                    .or_else(|| placed.iter().find(|(a, sz, _)| addr == a + sz))
+                    .map(|p| p.2);
+                match sect {
+                    Some(sect) => {
+                        let n_type = if symbol.is_global() { N_SECT | N_EXT } else { N_SECT };
+                        let n_desc = if symbol.is_weak() { N_WEAK_DEF } else { 0 };
+                        let idx = builder.add_symbol_with(name, addr, sect, n_type, n_desc);
+                        builder.set_symbol_size(idx, symbol.size());
+                    }
+                    None => {
+                        builder.add_absolute_symbol(name, addr);
+                    }
+                }
+            }
+            _ => {
+                builder.add_absolute_symbol(name, symbol.address());
+            }
+        }
+    }
+
+    Ok(builder.build())
+}
+
+/// 按 ELF 节标志归类可分配节：返回 (是否放入 __DATA, Ohlink 节名, 节类型, reserved1)
+/// 非 SHF_ALLOC 节（调试信息、符号表、重定位等）不转换
+fn classify_section(section: &object::Section) -> Option<(bool, String, u32, u32)> {
+    let object::SectionFlags::Elf { sh_flags } = section.flags() else { return None };
+    if sh_flags & elf::SHF_ALLOC as u64 == 0 {
+        return None;
+    }
+    let name = section.name().ok()?;
+    if let Some((oh_name, flags, priority)) = init_array_kind(name) {
+        return Some((true, oh_name.to_string(), flags, priority));
+    }
+    let writable = sh_flags & elf::SHF_WRITE as u64 != 0;
+    let nobits = matches!(section.kind(), object::SectionKind::UninitializedData | object::SectionKind::UninitializedTls);
+    let flags = if nobits { S_ZEROFILL } else { S_REGULAR };
+    Some((writable || nobits, ohlink_section_name(name, sh_flags), flags, 0))
+}
+
+/// ELF 节名 -> Ohlink 节名：常规前缀换成 Mach-O 风格的名字并保留后缀（`.text.foo` -> `__text.foo`），
+/// 自定义节（`#[link_section]`）原样保留
+fn ohlink_section_name(name: &str, sh_flags: u64) -> String {
+    const PREFIXES: &[(&str, &str)] = &[
+        (".text", "__text"),
+        (".rodata", "__const"),
+        (".data", "__data"),
+        (".bss", "__bss"),
+        (".tdata", "__thread_data"),
+        (".tbss", "__thread_bss"),
+    ];
+    for (elf_prefix, oh_prefix) in PREFIXES {
+        let Some(rest) = name.strip_prefix(elf_prefix) else { continue };
+        if !rest.is_empty() && !rest.starts_with('.') {
+            continue;
+        }
+        let oh_prefix = if *elf_prefix == ".rodata" && sh_flags & elf::SHF_STRINGS as u64 != 0 { "__cstring" } else { oh_prefix };
+        return format!("{}{}", oh_prefix, rest);
+    }
+    name.to_string()
+}
+
+/// 构造/析构数组节：ELF 节名 -> (Ohlink 节名, 节类型, 优先级)
+/// `.ctors.NNNNN`/`.dtors.NNNNN` 的优先级按 GNU ld 的约定换算为 65535 - NNNNN
+fn init_array_kind(name: &str) -> Option<(&'static str, u32, u32)> {
+    let rest = name.strip_prefix('.')?;
+    let (base, priority) = match rest.find('.') {
+        Some(dot) => (&rest[..dot], Some(rest[dot + 1..].parse::<u32>().ok()?)),
+        None => (rest, None),
+    };
+    let (oh_name, flags, legacy) = match base {
+        "preinit_array" => ("__preinit_array", S_PREINIT_FUNC_POINTERS, false),
+        "init_array" => ("__init_array", S_INIT_FUNC_POINTERS, false),
+        "fini_array" => ("__fini_array", S_TERM_FUNC_POINTERS, false),
+        "ctors" => ("__init_array", S_INIT_FUNC_POINTERS, true),
+        "dtors" => ("__fini_array", S_TERM_FUNC_POINTERS, true),
+        _ => return None,
+    };
+    let priority = match priority {
+        None => INIT_PRIORITY_DEFAULT,
+        Some(p) if legacy => INIT_PRIORITY_DEFAULT.saturating_sub(p),
+        Some(p) => p,
+    };
+    Some((oh_name, flags, priority))
+}
+
+/// 旧式 `.ctors`/`.dtors`（含 `.NNNNN` 后缀）
+fn is_legacy_ctors(name: &str) -> bool {
+    name.strip_prefix('.')
+        .and_then(|rest| rest.split('.').next())
+        .is_some_and(|base| base == "ctors" || base == "dtors")
+}
+
+/// 旧式构造/析构表从后往前执行，并入 init/fini 数组（从前往后执行）时与 GNU ld 一样反转 8 字节表项
+fn reverse_ctors(name: &str, table: &mut [u8]) -> Result<()> {
+    if !table.len().is_multiple_of(8) {
+        bail!("{}: size {:#x} is not a multiple of the entry size", name, table.len());
+    }
+    let reversed: Vec<u8> = table.chunks_exact(8).rev().flatten().copied().collect();
+    table.copy_from_slice(&reversed);
+    Ok(())
+}
+
+fn map_relocation_type(reloc: &object::Relocation) -> u32 {
+    // object 把 CALL26/JUMP26 归为 PltRelative/Relative，按编码识别为 B/BL
+    if reloc.encoding() == object::RelocationEncoding::AArch64Call {
+        return RELOC_BRANCH26;
+    }
+    match reloc.kind() {
+        RelocationKind::Absolute => match reloc.size() {
+            64 => RELOC_ABS64,
+            32 => RELOC_ABS32,
+            _ => RELOC_NONE,
+        },
+        RelocationKind::Relative => match reloc.size() {
+            64 => RELOC_REL64,
+            32 => RELOC_REL32,
+            _ => RELOC_NONE,
+        },
+        RelocationKind::Got | RelocationKind::GotRelative | RelocationKind::GotBaseRelative | RelocationKind::GotBaseOffset => RELOC_GOT,
+        RelocationKind::PltRelative => RELOC_PLT,
+        RelocationKind::Elf(t) => match t {
+            elf::R_AARCH64_CALL26 | elf::R_AARCH64_JUMP26 => RELOC_BRANCH26,
+            elf::R_AARCH64_ADR_PREL_PG_HI21 => RELOC_AARCH64_ADR_PREL_PG_HI21,
+            elf::R_AARCH64_ADD_ABS_LO12_NC => RELOC_AARCH64_ADD_ABS_LO12_NC,
+            elf::R_AARCH64_LD_PREL_LO19 => RELOC_AARCH64_LD_PREL_LO19,
+            _ => RELOC_NONE,
+        },
+        _ => RELOC_NONE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::write;
+    use object::{Architecture, BinaryFormat, Endianness, RelocationEncoding, SectionKind, SymbolFlags, SymbolKind, SymbolScope};
+    use std::collections::BTreeMap;
+
+    /// 可分配节：名字 -> (类型, 大小, 内容)
+    fn alloc_sections(elf: &object::File) -> BTreeMap<String, (object::SectionKind, u64, Vec<u8>)> {
+        elf.sections()
+            .filter(|s| matches!(s.flags(), object::SectionFlags::Elf { sh_flags } if sh_flags & elf::SHF_ALLOC as u64 != 0))
+            .map(|s| (s.name().unwrap().to_string(), (s.kind(), s.size(), s.data().unwrap().to_vec())))
+            .collect()
+    }
+
+    /// 具名符号：名字 -> (所在节名, 值, 全局, 弱, 未定义)
+    fn named_symbols(elf: &object::File) -> BTreeMap<String, (Option<String>, u64, bool, bool, bool)> {
+        elf.symbols()
+            .filter(|s| !s.name().unwrap_or("").is_empty() && s.kind() != object::SymbolKind::Section)
+            .map(|s| {
+                let sect = s.section_index().map(|i| elf.section_by_index(i).unwrap().name().unwrap().to_string());
+                (s.name().unwrap().to_string(), (sect, s.address(), s.is_global(), s.is_weak(), s.is_undefined()))
+            })
+            .collect()
+    }
+
+    /// 重定位：(节名, 偏移, 类型, 目标符号名, addend)
+    fn relocations(elf: &object::File) -> Vec<(String, u64, RelocationKind, String, i64)> {
+        let mut out = Vec::new();
+        for s in elf.sections() {
+            for (off, r) in s.relocations() {
+                let object::RelocationTarget::Symbol(si) = r.target() else { continue };
+                let target = elf.symbol_by_index(si).unwrap().name().unwrap().to_string();
+                out.push((s.name().unwrap().to_string(), off, r.kind(), target, r.addend()));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn elf_ohlink_elf_round_trip() {
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+        let text = obj.add_section(Vec::new(), b".text".to_vec(), SectionKind::Text);
+        // bl ext; ret; ret
+        obj.append_section_data(text, &[0x00, 0x00, 0x00, 0x94, 0xc0, 0x03, 0x5f, 0xd6, 0xc0, 0x03, 0x5f, 0xd6], 4);
+        let rodata = obj.add_section(Vec::new(), b".rodata.str1.1".to_vec(), SectionKind::ReadOnlyString);
+        obj.append_section_data(rodata, b"hi\0", 1);
+        let data = obj.add_section(Vec::new(), b".data".to_vec(), SectionKind::Data);
+        obj.append_section_data(data, &[0; 8], 8);
+        let bss = obj.add_section(Vec::new(), b".bss".to_vec(), SectionKind::UninitializedData);
+        obj.append_section_bss(bss, 32, 16);
+        let init = obj.add_section(Vec::new(), b".init_array.101".to_vec(), SectionKind::Elf(elf::SHT_INIT_ARRAY));
+        obj.section_mut(init).flags = object::SectionFlags::Elf { sh_flags: (elf::SHF_ALLOC | elf::SHF_WRITE) as u64 };
+        obj.append_section_data(init, &[0; 8], 8);
+
+        let mut sym = |name: &str, section, value, kind, scope, weak| {
+            obj.add_symbol(write::Symbol {
+                name: name.as_bytes().to_vec(),
+                value,
+                size: 0,
+                kind,
+                scope,
+                weak,
+                section,
+                flags: SymbolFlags::None,
+            })
+        };
+        let start = sym("_start", write::SymbolSection::Section(text), 0, SymbolKind::Text, SymbolScope::Dynamic, false);
+        sym("helper", write::SymbolSection::Section(text), 8, SymbolKind::Text, SymbolScope::Dynamic, true);
+        let msg = sym("msg", write::SymbolSection::Section(rodata), 0, SymbolKind::Data, SymbolScope::Compilation, false);
+        sym("buf", write::SymbolSection::Section(bss), 0, SymbolKind::Data, SymbolScope::Dynamic, false);
+        let ext = sym("ext", write::SymbolSection::Undefined, 0, SymbolKind::Unknown, SymbolScope::Dynamic, false);
+        sym("opt", write::SymbolSection::Undefined, 0, SymbolKind::Unknown, SymbolScope::Dynamic, true);
+        obj.symbol_mut(start).size = 8;
+
+        let reloc = |symbol, offset, r_type, addend| write::Relocation {
+            offset,
+            size: 0,
+            kind: RelocationKind::Elf(r_type),
+            encoding: RelocationEncoding::Generic,
+            symbol,
+            addend,
+        };
+        obj.add_relocation(text, reloc(ext, 0, elf::R_AARCH64_CALL26, 0)).unwrap();
+        obj.add_relocation(data, reloc(msg, 0, elf::R_AARCH64_ABS64, 1)).unwrap();
+        obj.add_relocation(init, reloc(start, 0, elf::R_AARCH64_ABS64, 0)).unwrap();
+        let original_bytes = obj.write().unwrap();
+
+        let original = object::File::parse(&*original_bytes).unwrap();
+        let ohlink_bytes = convert_elf_to_ohlink(&original, &ConvertOptions::default()).unwrap();
+        let ohlink = OhlinkFile::parse(&ohlink_bytes).unwrap();
+        let back_bytes = convert_ohlink_to_elf(&ohlink, None).unwrap();
+        let back = object::File::parse(&*back_bytes).unwrap();
+
+        assert_eq!(back.kind(), object::ObjectKind::Relocatable);
+        assert_eq!(alloc_sections(&back), alloc_sections(&original));
+        assert_eq!(named_symbols(&back), named_symbols(&original));
+        assert_eq!(relocations(&back), relocations(&original));
+        // 符号大小经 LC_SYMBOL_SIZES 往返
+        assert_eq!(ohlink.symbol_size(ohlink.symbols().iter().position(|(n, _)| n == "_start").unwrap()), Some(8));
+        assert_eq!(back.symbol_by_name("_start").unwrap().size(), 8);
+    }
+
+    #[test]
+    fn section_symbol_relocations() {
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+        let text = obj.add_section(Vec::new(), b".text".to_vec(), SectionKind::Text);
+        obj.append_section_data(text, &[0; 8], 4);
+        let rodata = obj.add_section(Vec::new(), b".rodata.str1.1".to_vec(), SectionKind::ReadOnlyString);
+        obj.append_section_data(rodata, b"hello\0world\0", 1);
+        let section_sym = obj.section_symbol(rodata);
+        let reloc = |offset, r_type| write::Relocation {
+            offset,
+            size: 0,
+            kind: RelocationKind::Elf(r_type),
+            encoding: RelocationEncoding::Generic,
+            symbol: section_sym,
+            addend: 6,
+        };
+        obj.add_relocation(text, reloc(0, elf::R_AARCH64_ADR_PREL_PG_HI21)).unwrap();
+        obj.add_relocation(text, reloc(4, elf::R_AARCH64_ADD_ABS_LO12_NC)).unwrap();
+        let original_bytes = obj.write().unwrap();
+
+        let original = object::File::parse(&*original_bytes).unwrap();
+        let ohlink_bytes = convert_elf_to_ohlink(&original, &ConvertOptions::default()).unwrap();
+        let ohlink = OhlinkFile::parse(&ohlink_bytes).unwrap();
+        let symbols = ohlink.symbols();
+        let (text_seg, text_sec) = ohlink.sections()[0];
+        assert_eq!(ohlink.segment_name(text_seg), "__TEXT");
+        for r in ohlink.relocations(text_sec) {
+            let (name, n) = &symbols[r.r_symbol as usize];
+            assert!(name.is_empty() && !n.is_external());
+            assert_eq!(ohlink.section_name(ohlink.sections()[n.n_sect as usize - 1].1), "__cstring.str1.1");
+            assert_eq!(r.r_addend, 6);
+        }
+
+        let back_bytes = convert_ohlink_to_elf(&ohlink, None).unwrap();
+        let back = object::File::parse(&*back_bytes).unwrap();
+        let text = back.section_by_name(".text").unwrap();
+        let relocs: Vec<_> = text.relocations().collect();
+        assert_eq!(relocs.len(), 2);
+        for (_, r) in relocs {
+            let object::RelocationTarget::Symbol(si) = r.target() else { panic!("expected a symbol target") };
+            let target = back.symbol_by_index(si).unwrap();
+            assert_eq!(target.kind(), object::SymbolKind::Section);
+            assert_eq!(back.section_by_index(target.section_index().unwrap()).unwrap().name().unwrap(), ".rodata.str1.1");
+            assert_eq!(r.addend(), 6);
+        }
+    }
+
+    #[test]
+    fn legacy_ctors_fold_reversed() {
+        let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+        let text = obj.add_section(Vec::new(), b".text".to_vec(), SectionKind::Text);
+        obj.append_section_data(text, &[0xc0, 0x03, 0x5f, 0xd6].repeat(3), 4);
+        let ctors = obj.add_section(Vec::new(), b".ctors".to_vec(), SectionKind::Data);
+        obj.append_section_data(ctors, &[0; 24], 8);
+        // 无重定位的表项：内容本身随之反转
+        let dtors = obj.add_section(Vec::new(), b".dtors.00100".to_vec(), SectionKind::Data);
+        obj.append_section_data(dtors, &[1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0], 8);
+        for (i, name) in ["a", "b", "c"].into_iter().enumerate() {
+            let sym = obj.add_symbol(write::Symbol {
+                name: name.as_bytes().to_vec(),
+                value: i as u64 * 4,
+                size: 4,
+                kind: SymbolKind::Text,
+                scope: SymbolScope::Dynamic,
+                weak: false,
+                section: write::SymbolSection::Section(text),
+                flags: SymbolFlags::None,
+            });
+            let reloc = write::Relocation {
+                offset: i as u64 * 8,
+                size: 0,
+                kind: RelocationKind::Elf(elf::R_AARCH64_ABS64),
+                encoding: RelocationEncoding::Generic,
+                symbol: sym,
+                addend: 0,
+            };
+            obj.add_relocation(ctors, reloc).unwrap();
+        }
+        let bytes = obj.write().unwrap();
+        let elf = object::File::parse(&*bytes).unwrap();
+        let ohlink_bytes = convert_elf_to_ohlink(&elf, &ConvertOptions::default()).expect("convert");
+        let ohlink = OhlinkFile::parse(&ohlink_bytes).unwrap();
+        let symbols = ohlink.symbols();
+        let section = |name: &str| ohlink.sections().into_iter().find(|(_, sec)| ohlink.section_name(sec) == name).unwrap().1;
+
+        // .ctors 从后往前执行：并入 __init_array 后 c、b、a 依次调用
+        let init = section("__init_array");
+        assert_eq!((init.flags & SECTION_TYPE, init.reserved1), (S_INIT_FUNC_POINTERS, INIT_PRIORITY_DEFAULT));
+        let mut order: Vec<(u64, &str)> = ohlink.relocations(init).iter().map(|r| (r.r_addr, symbols[r.r_symbol as usize].0.as_str())).collect();
+        order.sort();
+        assert_eq!(order, [(0, "c"), (8, "b"), (16, "a")]);
+
+        let fini = section("__fini_array");
+        assert_eq!((fini.flags & SECTION_TYPE, fini.reserved1), (S_TERM_FUNC_POINTERS, INIT_PRIORITY_DEFAULT - 100));
+        assert_eq!(ohlink.section_data(fini), &[2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn section_ordinal_limit() {
+        // `count` 个 -ffunction-sections 风格的 .text.fN，每个节一个函数
+        let convert = |count: usize| {
+            let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+            for i in 0..count {
+                let sec = obj.add_section(Vec::new(), format!(".text.f{}", i).into_bytes(), SectionKind::Text);
+                obj.append_section_data(sec, &[0xc0, 0x03, 0x5f, 0xd6], 4);
+                obj.add_symbol(write::Symbol {
+                    name: format!("f{}", i).into_bytes(),
+                    value: 0,
+                    size: 4,
+                    kind: SymbolKind::Text,
+                    scope: SymbolScope::Dynamic,
+                    weak: false,
+                    section: write::SymbolSection::Section(sec),
+                    flags: SymbolFlags::None,
+                });
+            }
+            let bytes = obj.write().unwrap();
+            convert_elf_to_ohlink(&object::File::parse(&*bytes).unwrap(), &ConvertOptions::default())
+        };
+
+        let ohlink_bytes = convert(MAX_SECTIONS).expect("convert");
+        let ohlink = OhlinkFile::parse(&ohlink_bytes).unwrap();
+        let (_, last) = ohlink.symbols().into_iter().find(|(name, _)| name == "f254").unwrap();
+        assert_eq!(last.n_sect, 255);
+        assert_eq!(ohlink.section_name(ohlink.sections()[254].1), "__text.f254");
+
+        let err = convert(MAX_SECTIONS + 1).unwrap_err();
+        assert!(err.to_string().contains("Too many sections"), "{}", err);
+    }
+}
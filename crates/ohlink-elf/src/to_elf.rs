@@ -0,0 +1,449 @@
+// crates/ohlink-elf/src/to_elf.rs
+//! Ohlink -> ELF 反向转换：目标文件生成 ELF 可重定位文件，可执行文件生成带程序头的 ELF 可执行文件，
+//! 便于用 gdb、llvm-objdump、readelf 及 QEMU 的 ELF 加载器检查 Ohlink 产物
+use anyhow::{bail, Context, Result};
+use object::elf;
+use object::write::elf::{FileHeader, ProgramHeader, SectionHeader, Sym, Writer};
+use object::write::{self, SymbolSection};
+use object::{Architecture, BinaryFormat, Endianness, RelocationEncoding, RelocationKind, SectionFlags, SectionKind, SymbolFlags, SymbolKind, SymbolScope};
+use ohlink_format::*;
+use std::collections::HashMap;
+
+/// 可执行文件中 PT_LOAD 的对齐
+const PAGE_SIZE: u64 = 0x1000;
+
+/// 段的实际权限：VM_PROT_ALL 是构建器的默认值（ohlink-ld 的输出），此时按段名推断
+fn segment_prot(seg: &SegmentCommand64, segname: &str) -> i32 {
+    match (seg.initprot, segname) {
+        (VM_PROT_ALL, "__TEXT") => VM_PROT_READ | VM_PROT_EXECUTE,
+        (VM_PROT_ALL, _) => VM_PROT_READ | VM_PROT_WRITE,
+        (prot, _) => prot,
+    }
+}
+
+/// 一个 Ohlink 节及其在 ELF 中的名字、类型与标志
+struct ElfSection {
+    seg: SegmentCommand64,
+    segname: String,
+    sec: Section64,
+    name: String,
+    sh_type: u32,
+    sh_flags: u64,
+}
+
+impl ElfSection {
+    fn is_nobits(&self) -> bool {
+        self.sh_type == elf::SHT_NOBITS
+    }
+
+    /// 调试节（来自 __DWARF 段）不占地址，只在文件中保存内容
+    fn is_debug(&self) -> bool {
+        self.sh_flags & elf::SHF_ALLOC as u64 == 0
+    }
+}
+
+/// 按文件类型把 Ohlink 文件转换为 ELF：MH_OBJECT 生成 ET_REL，MH_EXECUTE/MH_DYLIB 生成 ET_EXEC/ET_DYN；
+/// `entry` 指定入口符号，缺省时与内核加载器一致（LC_ENTRY_POINT，其次 `_start`，否则 `__TEXT` 段基址）
+pub fn convert_ohlink_to_elf(file: &OhlinkFile, entry: Option<&str>) -> Result<Vec<u8>> {
+    match file.header.file_type {
+        MH_OBJECT => convert_object(file),
+        MH_EXECUTE => convert_image(file, elf::ET_EXEC, entry),
+        MH_DYLIB => convert_image(file, elf::ET_DYN, entry),
+        t => bail!("unsupported Ohlink file type {:#x}", t),
+    }
+}
+
+/// 节顺序与 `OhlinkFile::sections()` 一致，n_sect - 1 即下标
+fn elf_sections(file: &OhlinkFile) -> Vec<ElfSection> {
+    file.sections()
+        .into_iter()
+        .map(|(seg, sec)| {
+            let segname = file.segment_name(seg);
+            let (name, sh_type, sh_flags) = elf_section_attrs(segment_prot(seg, &segname), &file.section_name(sec), sec);
+            ElfSection { seg: *seg, segname, sec: *sec, name, sh_type, sh_flags }
+        })
+        .collect()
+}
+
+/// Ohlink 节 -> (ELF 节名, sh_type, sh_flags)，是 `ohlink_section_name`/`init_array_kind` 的逆映射
+fn elf_section_attrs(prot: i32, name: &str, sec: &Section64) -> (String, u32, u64) {
+    if let Some(rest) = name.strip_prefix("__debug_") {
+        return (format!(".debug_{}", rest), elf::SHT_PROGBITS, 0);
+    }
+    let alloc_write = (elf::SHF_ALLOC | elf::SHF_WRITE) as u64;
+    let array = match sec.flags & SECTION_TYPE {
+        S_PREINIT_FUNC_POINTERS => Some((".preinit_array", elf::SHT_PREINIT_ARRAY)),
+        S_INIT_FUNC_POINTERS => Some((".init_array", elf::SHT_INIT_ARRAY)),
+        S_TERM_FUNC_POINTERS => Some((".fini_array", elf::SHT_FINI_ARRAY)),
+        _ => None,
+    };
+    if let Some((base, sh_type)) = array {
+        let name = match sec.reserved1 {
+            0 | INIT_PRIORITY_DEFAULT => base.to_string(),
+            p => format!("{}.{}", base, p),
+        };
+        return (name, sh_type, alloc_write);
+    }
+
+    const PREFIXES: &[(&str, &str)] = &[
+        ("__text", ".text"),
+        ("__const", ".rodata"),
+        ("__cstring", ".rodata"),
+        ("__data", ".data"),
+        ("__bss", ".bss"),
+        ("__thread_data", ".tdata"),
+        ("__thread_bss", ".tbss"),
+    ];
+    let mut elf_name = name.to_string();
+    let mut sh_flags = elf::SHF_ALLOC as u64;
+    for (oh_prefix, elf_prefix) in PREFIXES {
+        let Some(rest) = name.strip_prefix(oh_prefix) else { continue };
+        if !rest.is_empty() && !rest.starts_with('.') {
+            continue;
+        }
+        elf_name = format!("{}{}", elf_prefix, rest);
+        if *oh_prefix == "__cstring" {
+            sh_flags |= (elf::SHF_MERGE | elf::SHF_STRINGS) as u64;
+        }
+        if oh_prefix.starts_with("__thread") {
+            sh_flags |= elf::SHF_TLS as u64;
+        }
+        break;
+    }
+    // 可执行段里除只读数据外都按代码处理
+    if prot & VM_PROT_EXECUTE != 0 && !elf_name.starts_with(".rodata") {
+        sh_flags |= elf::SHF_EXECINSTR as u64;
+    }
+    if prot & VM_PROT_WRITE != 0 {
+        sh_flags |= elf::SHF_WRITE as u64;
+    }
+    let sh_type = if sec.flags & SECTION_TYPE == S_ZEROFILL { elf::SHT_NOBITS } else { elf::SHT_PROGBITS };
+    (elf_name, sh_type, sh_flags)
+}
+
+/// Ohlink 重定位类型 -> R_AARCH64_*
+fn elf_relocation_type(r_type: u32) -> Option<u32> {
+    Some(match r_type {
+        RELOC_NONE => elf::R_AARCH64_NONE,
+        RELOC_ABS64 => elf::R_AARCH64_ABS64,
+        RELOC_ABS32 => elf::R_AARCH64_ABS32,
+        RELOC_REL64 => elf::R_AARCH64_PREL64,
+        RELOC_REL32 => elf::R_AARCH64_PREL32,
+        RELOC_BRANCH26 => elf::R_AARCH64_CALL26,
+        RELOC_AARCH64_ADR_PREL_PG_HI21 => elf::R_AARCH64_ADR_PREL_PG_HI21,
+        RELOC_AARCH64_ADD_ABS_LO12_NC => elf::R_AARCH64_ADD_ABS_LO12_NC,
+        RELOC_AARCH64_LD_PREL_LO19 => elf::R_AARCH64_LD_PREL_LO19,
+        // GOT/PLT/TLS 在正向转换时已丢失具体的指令形式，无法还原
+        _ => return None,
+    })
+}
+
+fn convert_object(file: &OhlinkFile) -> Result<Vec<u8>> {
+    let mut obj = write::Object::new(BinaryFormat::Elf, Architecture::Aarch64, Endianness::Little);
+    let sections = elf_sections(file);
+
+    let mut section_ids = Vec::with_capacity(sections.len());
+    for s in &sections {
+        let exec = s.sh_flags & elf::SHF_EXECINSTR as u64 != 0;
+        let write = s.sh_flags & elf::SHF_WRITE as u64 != 0;
+        let tls = s.sh_flags & elf::SHF_TLS as u64 != 0;
+        let kind = match s.sh_type {
+            elf::SHT_NOBITS if tls => SectionKind::UninitializedTls,
+            elf::SHT_NOBITS => SectionKind::UninitializedData,
+            elf::SHT_PROGBITS if exec => SectionKind::Text,
+            elf::SHT_PROGBITS if tls => SectionKind::Tls,
+            elf::SHT_PROGBITS if write => SectionKind::Data,
+            elf::SHT_PROGBITS if s.sh_flags & elf::SHF_STRINGS as u64 != 0 => SectionKind::ReadOnlyString,
+            elf::SHT_PROGBITS => SectionKind::ReadOnlyData,
+            t => SectionKind::Elf(t),
+        };
+        let id = obj.add_section(Vec::new(), s.name.as_bytes().to_vec(), kind);
+        let section = obj.section_mut(id);
+        section.flags = SectionFlags::Elf { sh_flags: s.sh_flags };
+        let align = u64::from(s.sec.align.max(1));
+        if s.is_nobits() {
+            section.append_bss(s.sec.size, align);
+        } else {
+            section.set_data(file.section_data(&s.sec).to_vec(), align);
+        }
+        section_ids.push(id);
+    }
+
+    // 符号值在目标文件中相对于所在节。无名局部符号（elf2ohlink 转换的节符号）还原为 ELF 节符号，
+    // 相对节起点的偏移并入重定位的 addend
+    let mut symbol_ids = HashMap::new();
+    for (i, (name, n)) in file.symbols().into_iter().enumerate() {
+        if name.is_empty() {
+            let si = (n.n_sect as usize).wrapping_sub(1);
+            if let (false, Some(s)) = (n.is_undefined() || n.is_absolute() || n.is_external(), sections.get(si)) {
+                let id = obj.section_symbol(section_ids[si]);
+                symbol_ids.insert(i as u32, (id, n.n_value.wrapping_sub(s.sec.addr) as i64));
+            }
+            continue;
+        }
+        let (section, value, size, kind) = if n.is_common() {
+            (SymbolSection::Common, n.common_align(), n.n_value, SymbolKind::Data)
+        } else if n.is_undefined() {
+            (SymbolSection::Undefined, 0, 0, SymbolKind::Unknown)
+        } else if n.is_absolute() {
+            (SymbolSection::Absolute, n.n_value, 0, SymbolKind::Unknown)
+        } else {
+            let si = (n.n_sect as usize).wrapping_sub(1);
+            let Some(s) = sections.get(si) else {
+                bail!("symbol {} refers to missing section {}", name, n.n_sect);
+            };
+            let kind = if s.sh_flags & elf::SHF_EXECINSTR as u64 != 0 { SymbolKind::Text } else { SymbolKind::Data };
+            (SymbolSection::Section(section_ids[si]), n.n_value.wrapping_sub(s.sec.addr), file.symbol_size(i).unwrap_or(0), kind)
+        };
+        let scope = if n.is_external() || n.is_undefined() { SymbolScope::Dynamic } else { SymbolScope::Compilation };
+        let id = obj.add_symbol(write::Symbol {
+            name: name.into_bytes(),
+            value,
+            size,
+            kind,
+            scope,
+            weak: n.is_weak_def() || n.is_weak_ref(),
+            section,
+            flags: SymbolFlags::None,
+        });
+        symbol_ids.insert(i as u32, (id, 0));
+    }
+
+    for (s, &id) in sections.iter().zip(&section_ids) {
+        for r in file.relocations(&s.sec) {
+            let Some(&(symbol, bias)) = symbol_ids.get(&r.r_symbol) else {
+                bail!("relocation at {}+{:#x} refers to missing symbol {}", s.name, r.r_addr.wrapping_sub(s.sec.addr), r.r_symbol);
+            };
+            let r_type = elf_relocation_type(r.r_type)
+                .with_context(|| format!("relocation type {} at {}+{:#x} has no ELF equivalent", r.r_type, s.name, r.r_addr.wrapping_sub(s.sec.addr)))?;
+            obj.add_relocation(id, write::Relocation {
+                offset: r.r_addr.wrapping_sub(s.sec.addr),
+                size: 0,
+                kind: RelocationKind::Elf(r_type),
+                encoding: RelocationEncoding::Generic,
+                symbol,
+                addend: r.r_addend.wrapping_add(bias),
+            })?;
+        }
+    }
+
+    Ok(obj.write()?)
+}
+
+/// 可执行文件：每个 Ohlink 段（`__PAGEZERO` 除外）生成一个 PT_LOAD，节头与符号使用绝对地址
+fn convert_image(file: &OhlinkFile, e_type: u16, entry: Option<&str>) -> Result<Vec<u8>> {
+    // by_ordinal：n_sect - 1 -> sections 下标（__PAGEZERO 的节没有对应的 ELF 节）
+    let mut sections: Vec<ElfSection> = Vec::new();
+    let mut by_ordinal: Vec<Option<usize>> = Vec::new();
+    for s in elf_sections(file) {
+        if s.segname == "__PAGEZERO" {
+            by_ordinal.push(None);
+        } else {
+            by_ordinal.push(Some(sections.len()));
+            sections.push(s);
+        }
+    }
+
+    // 段的内存映像：按节地址摆放节内容，零填充部分只计入 p_memsz
+    struct Load {
+        seg: SegmentCommand64,
+        segname: String,
+        image: Vec<u8>,
+        memsz: u64,
+        offset: u64,
+    }
+    let mut loads: Vec<Load> = Vec::new();
+    for cmd in &file.commands {
+        let LoadCommand::Segment64(seg, _) = cmd else { continue };
+        let segname = file.segment_name(seg);
+        if segname == "__PAGEZERO" || segname == "__DWARF" {
+            continue;
+        }
+        let mut image = Vec::new();
+        let mut memsz = seg.vmsize;
+        for s in sections.iter().filter(|s| s.seg.vmaddr == seg.vmaddr && s.segname == segname && !s.is_debug()) {
+            let start = s.sec.addr.checked_sub(seg.vmaddr).with_context(|| format!("section {} lies below segment {}", s.name, segname))?;
+            memsz = memsz.max(start + s.sec.size);
+            if s.is_nobits() {
+                continue;
+            }
+            let data = file.section_data(&s.sec);
+            let end = start as usize + data.len();
+            if image.len() < end {
+                image.resize(end, 0);
+            }
+            image[start as usize..end].copy_from_slice(data);
+        }
+        if memsz == 0 {
+            continue;
+        }
+        loads.push(Load { seg: *seg, segname, image, memsz, offset: 0 });
+    }
+
+    // (符号, 大小)；ELF 要求局部符号排在全局符号之前
+    let (symbols, symbol_sizes): (Vec<(String, Nlist64)>, Vec<u64>) = {
+        let mut syms: Vec<_> = file
+            .symbols()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (name, _))| !name.is_empty())
+            .map(|(i, sym)| (sym, file.symbol_size(i).unwrap_or(0)))
+            .collect();
+        syms.sort_by_key(|((_, n), _)| n.is_external() || n.is_undefined());
+        syms.into_iter().unzip()
+    };
+    let entry_addr = match entry {
+        Some(name) => symbols
+            .iter()
+            .find(|(n, it)| n == name && !it.is_undefined())
+            .map(|(_, it)| it.n_value)
+            .with_context(|| format!("entry symbol {} not found", name))?,
+        None => file
+            .entry_point()
+            .or_else(|| symbols.iter().find(|(n, it)| n == "_start" && !it.is_undefined()).map(|(_, it)| it.n_value))
+            .or_else(|| loads.iter().find(|l| l.segname == "__TEXT").map(|l| l.seg.vmaddr))
+            .unwrap_or(0),
+    };
+
+    let mut buffer = Vec::new();
+    let mut w = Writer::new(Endianness::Little, true, &mut buffer);
+    w.reserve_file_header();
+    w.reserve_program_headers(loads.len() as u32);
+    for l in &mut loads {
+        // 文件偏移与虚拟地址模页大小同余，加载器才能直接映射
+        let cur = w.reserved_len() as u64;
+        l.offset = cur + (l.seg.vmaddr.wrapping_sub(cur) & (PAGE_SIZE - 1));
+        w.reserve_until(l.offset as usize);
+        w.reserve(l.image.len(), 1);
+    }
+    let debug_offsets: Vec<u64> = sections
+        .iter()
+        .map(|s| if s.is_debug() { w.reserve(file.section_data(&s.sec).len(), 1) as u64 } else { 0 })
+        .collect();
+
+    w.reserve_null_section_index();
+    let mut section_indices = Vec::with_capacity(sections.len());
+    for s in &sections {
+        let name = w.add_section_name(s.name.as_bytes());
+        section_indices.push((name, w.reserve_section_index()));
+    }
+    let mut symbol_names = Vec::with_capacity(symbols.len());
+    for (name, _) in &symbols {
+        symbol_names.push(w.add_string(name.as_bytes()));
+        w.reserve_symbol_index(None);
+    }
+    w.reserve_symtab_section_index();
+    w.reserve_strtab_section_index();
+    w.reserve_shstrtab_section_index();
+    w.reserve_symtab();
+    w.reserve_strtab();
+    w.reserve_shstrtab();
+    w.reserve_section_headers();
+
+    w.write_file_header(&FileHeader {
+        os_abi: elf::ELFOSABI_NONE,
+        abi_version: 0,
+        e_type,
+        e_machine: elf::EM_AARCH64,
+        e_entry: entry_addr,
+        e_flags: 0,
+    })?;
+    w.write_align_program_headers();
+    for l in &loads {
+        let prot = segment_prot(&l.seg, &l.segname);
+        let p_flags = [(VM_PROT_READ, elf::PF_R), (VM_PROT_WRITE, elf::PF_W), (VM_PROT_EXECUTE, elf::PF_X)]
+            .iter()
+            .filter(|(vm, _)| prot & vm != 0)
+            .fold(0, |acc, (_, pf)| acc | pf);
+        w.write_program_header(&ProgramHeader {
+            p_type: elf::PT_LOAD,
+            p_flags,
+            p_offset: l.offset,
+            p_vaddr: l.seg.vmaddr,
+            p_paddr: l.seg.vmaddr,
+            p_filesz: l.image.len() as u64,
+            p_memsz: l.memsz,
+            p_align: PAGE_SIZE,
+        });
+    }
+    for l in &loads {
+        w.pad_until(l.offset as usize);
+        w.write(&l.image);
+    }
+    for (s, &offset) in sections.iter().zip(&debug_offsets) {
+        if s.is_debug() {
+            w.pad_until(offset as usize);
+            w.write(file.section_data(&s.sec));
+        }
+    }
+
+    w.write_null_symbol();
+    let mut num_local = 1;
+    for (((sym_name, n), &name), &size) in symbols.iter().zip(&symbol_names).zip(&symbol_sizes) {
+        let section = if n.is_undefined() || n.is_absolute() {
+            None
+        } else {
+            let si = by_ordinal.get((n.n_sect as usize).wrapping_sub(1)).copied().flatten();
+            match si.filter(|&i| !sections[i].is_debug()) {
+                Some(i) => Some(i),
+                None => bail!("symbol {} refers to missing section {}", sym_name, n.n_sect),
+            }
+        };
+        let bind = if !n.is_external() && !n.is_undefined() {
+            num_local += 1;
+            elf::STB_LOCAL
+        } else if n.is_weak_def() || n.is_weak_ref() {
+            elf::STB_WEAK
+        } else {
+            elf::STB_GLOBAL
+        };
+        let st_type = match section {
+            Some(i) if sections[i].sh_flags & elf::SHF_EXECINSTR as u64 != 0 => elf::STT_FUNC,
+            Some(_) => elf::STT_OBJECT,
+            None => elf::STT_NOTYPE,
+        };
+        let st_shndx = match section {
+            Some(_) => 0,
+            None if n.is_undefined() => elf::SHN_UNDEF,
+            None => elf::SHN_ABS,
+        };
+        w.write_symbol(&Sym {
+            name: Some(name),
+            section: section.map(|i| section_indices[i].1),
+            st_info: (bind << 4) | st_type,
+            st_other: elf::STV_DEFAULT,
+            st_shndx,
+            st_value: if n.is_undefined() { 0 } else { n.n_value },
+            st_size: if n.is_undefined() { 0 } else { size },
+        });
+    }
+    w.write_strtab();
+    w.write_shstrtab();
+
+    w.write_null_section_header();
+    for ((s, &(name, _)), &debug_offset) in sections.iter().zip(&section_indices).zip(&debug_offsets) {
+        let load = loads.iter().find(|l| l.seg.vmaddr == s.seg.vmaddr && l.segname == s.segname);
+        let sh_offset = match load {
+            _ if s.is_debug() => debug_offset,
+            Some(l) => l.offset + (s.sec.addr - l.seg.vmaddr),
+            None => 0,
+        };
+        w.write_section_header(&SectionHeader {
+            name: Some(name),
+            sh_type: s.sh_type,
+            sh_flags: s.sh_flags,
+            sh_addr: if s.is_debug() { 0 } else { s.sec.addr },
+            sh_offset,
+            sh_size: s.sec.size,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: u64::from(s.sec.align.max(1)),
+            sh_entsize: if s.sh_flags & elf::SHF_STRINGS as u64 != 0 { 1 } else { 0 },
+        });
+    }
+    w.write_symtab_section_header(num_local);
+    w.write_strtab_section_header();
+    w.write_shstrtab_section_header();
+
+    Ok(buffer)
+}
@@ -0,0 +1,52 @@
+// crates/ohlink-format/src/demangle.rs
+//! 符号名还原（`demangle` feature）：Rust legacy（`_ZN…E`）与 v0（`_R…`）、Itanium C++（`_Z…`）。
+use std::borrow::Cow;
+
+/// 还原修饰名；不是可识别的修饰名时返回 None。Rust 名字不带末尾的哈希
+pub fn demangle(name: &str) -> Option<String> {
+    if let Ok(d) = rustc_demangle::try_demangle(name) {
+        return Some(format!("{:#}", d));
+    }
+    if name.starts_with("_Z") {
+        let sym = cpp_demangle::Symbol::new(name).ok()?;
+        return sym.demangle(&cpp_demangle::DemangleOptions::default()).ok();
+    }
+    None
+}
+
+/// 显示用名字：`enabled` 且能还原时为还原结果，否则原样
+pub fn display_name(name: &str, enabled: bool) -> Cow<'_, str> {
+    if !enabled {
+        return Cow::Borrowed(name);
+    }
+    match demangle(name) {
+        Some(d) => Cow::Owned(d),
+        None => Cow::Borrowed(name),
+    }
+}
+
+/// 诊断信息用：还原后的名字附带括号中的原名，不是修饰名时原样
+pub fn describe(name: &str, enabled: bool) -> String {
+    if !enabled {
+        return name.to_string();
+    }
+    match demangle(name) {
+        Some(d) => format!("{} ({})", d, name),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_rust_and_cpp() {
+        assert_eq!(demangle("_ZN4core9panicking5panic17h0123456789abcdefE").as_deref(), Some("core::panicking::panic"));
+        assert_eq!(demangle("_RNvCs1234_7mycrate3foo").as_deref(), Some("mycrate::foo"));
+        assert_eq!(demangle("_Z3fooi").as_deref(), Some("foo(int)"));
+        assert_eq!(demangle("main"), None);
+        assert_eq!(describe("_Z3fooi", true), "foo(int) (_Z3fooi)");
+        assert_eq!(display_name("_Z3fooi", false), "_Z3fooi");
+    }
+}
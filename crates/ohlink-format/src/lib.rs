@@ -2,11 +2,11 @@
 #![allow(non_camel_case_types)]
 
 use thiserror::Error;
-// ====== 3. 顶部加工具函数 ======
-#[inline]
-fn align_up(val: u64, align: u64) -> u64 {
-    if align == 0 { val } else { ((val + align - 1) / align) * align }
-}
+
+#[cfg(feature = "demangle")]
+pub mod demangle;
+#[cfg(feature = "serde")]
+pub mod report;
 // ==================== 错误类型 ====================
 #[derive(Error, Debug)]
 pub enum OhlinkError {
@@ -18,14 +18,30 @@ pub enum OhlinkError {
     UnsupportedFileType(u32),
     #[error("Parse error at offset {offset:#x}: {message}")]
     ParseError { offset: u64, message: String },
+    #[error("Too many sections: {0} (at most {MAX_SECTIONS})")]
+    TooManySections(usize),
 }
 
 pub type Result<T> = std::result::Result<T, OhlinkError>;
 
+/// `OhlinkBuilder::retain_sections` 失败的原因；失败时构建器不做任何修改
+#[derive(Error, Debug)]
+pub enum RetainError {
+    /// 要删除的符号被保留节的重定位引用
+    #[error("symbol `{0}' is named in a relocation")]
+    Referenced(String),
+    #[error("symbol `{name}' refers to missing section {n_sect}")]
+    MissingSection { name: String, n_sect: u8 },
+    #[error(transparent)]
+    Format(#[from] OhlinkError),
+}
+
 // ==================== 常量定义 ====================
 pub const OHLINK_MAGIC: [u8; 4] = 0x0f112233u32.to_le_bytes();
 pub const OHLINK_MAGIC_64: [u8; 4] = 0x0f112234u32.to_le_bytes();
 pub const OHLIB_MAGIC: [u8; 4] = 0x0f112235u32.to_le_bytes();
+/// ohlib 符号索引成员名（BSD ranlib 约定），位于成员表首位
+pub const OHLIB_SYMDEF: &str = "__.SYMDEF";
 pub const CPU_TYPE_ARM64: u32 = 0x0100_000C;
 pub const MH_OBJECT: u32 = 0x1;
 pub const MH_EXECUTE: u32 = 0x2;
@@ -45,12 +61,48 @@ pub const RELOC_AARCH64_ADR_PREL_PG_HI21: u32 = 9;
 pub const RELOC_AARCH64_ADD_ABS_LO12_NC: u32 = 10;
 pub const RELOC_AARCH64_LD_PREL_LO19: u32 = 11;
 pub const LC_NOTE_ABI: u32 = 0x31;
+pub const LC_INIT_FUNCS: u32 = 0x32;
+pub const LC_NAME_TABLE: u32 = 0x33;
+pub const LC_ENTRY_POINT: u32 = 0x34;
+pub const LC_SYMBOL_SIZES: u32 = 0x35;
 pub const NOTE_NAME_HNX: &[u8; 4] = b"HNX\0";
 pub const NOTE_ABI_VERSION: u32 = 1;
+// Section64.flags 低 8 位为节类型
+pub const SECTION_TYPE: u32 = 0xff;
+pub const S_REGULAR: u32 = 0x0;
+pub const S_ZEROFILL: u32 = 0x1;
+pub const S_INIT_FUNC_POINTERS: u32 = 0x9;
+pub const S_TERM_FUNC_POINTERS: u32 = 0xa;
+pub const S_PREINIT_FUNC_POINTERS: u32 = 0x16;
+// 段权限（maxprot/initprot），与 Mach-O 的 VM_PROT_* 相同
+pub const VM_PROT_READ: i32 = 0x1;
+pub const VM_PROT_WRITE: i32 = 0x2;
+pub const VM_PROT_EXECUTE: i32 = 0x4;
+pub const VM_PROT_ALL: i32 = VM_PROT_READ | VM_PROT_WRITE | VM_PROT_EXECUTE;
+// 构造/析构数组节的 reserved1 存放优先级（.init_array.NNNNN），无编号者为默认值
+pub const INIT_PRIORITY_DEFAULT: u32 = 65535;
+// nlist n_type 位
+pub const N_UNDF: u8 = 0x0;
+pub const N_EXT: u8 = 0x1;
+pub const N_ABS: u8 = 0x2;
+pub const N_SECT: u8 = 0xe;
+pub const N_TYPE: u8 = 0x0e;
+pub const NO_SECT: u8 = 0;
+// n_sect 只有 1 字节且从 1 开始，一个文件最多 255 个节
+pub const MAX_SECTIONS: usize = 255;
+// nlist n_desc 标志
+pub const N_WEAK_REF: u16 = 0x0040; // 弱引用：未解析时取 0
+pub const N_WEAK_DEF: u16 = 0x0080; // 弱定义：可被强定义覆盖
+// 公共符号（tentative definition）：N_UNDF | N_EXT 且 n_value = 大小，
+// 对齐以 log2 形式存放在 n_desc 的 bit 8..11
+pub const COMM_ALIGN_SHIFT: u16 = 8;
+pub const COMM_ALIGN_MASK: u16 = 0x0f00;
 // ==================== 核心结构 ====================
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct OhlinkHeader {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "report::serialize_hex"))]
     pub magic: [u8; 4],
     pub cpu_type: u32,
     pub cpu_subtype: u32,
@@ -126,9 +178,11 @@ impl OhlinkHeader {
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SegmentCommand64 {
     pub cmd: u32,
     pub cmdsize: u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "report::serialize_name"))]
     pub segname: [u8; 16],
     pub vmaddr: u64,
     pub vmsize: u64,
@@ -142,8 +196,11 @@ pub struct SegmentCommand64 {
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Section64 {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "report::serialize_name"))]
     pub sectname: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(serialize_with = "report::serialize_name"))]
     pub segname: [u8; 16],
     pub addr: u64,
     pub size: u64,
@@ -159,6 +216,7 @@ pub struct Section64 {
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SymtabCommand {
     pub cmd: u32,
     pub cmdsize: u32,
@@ -168,8 +226,34 @@ pub struct SymtabCommand {
     pub strsize: u32,
 }
 
+/// 构造/析构函数指针数组的位置（地址 + 字节数），供启动代码与加载器调用
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InitFuncsCommand {
+    pub cmd: u32,
+    pub cmdsize: u32,
+    pub preinit_addr: u64,
+    pub preinit_size: u64,
+    pub init_addr: u64,
+    pub init_size: u64,
+    pub fini_addr: u64,
+    pub fini_size: u64,
+}
+
+/// 入口地址；没有该命令时加载器回退到 `_start` 符号或 `__TEXT` 段基址
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntryPointCommand {
+    pub cmd: u32,
+    pub cmdsize: u32,
+    pub entry: u64,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Relocation64 {
     pub r_addr: u64,
     pub r_symbol: u32,
@@ -179,6 +263,7 @@ pub struct Relocation64 {
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Nlist64 {
     pub n_strx: u32,
     pub n_type: u8,
@@ -187,6 +272,37 @@ pub struct Nlist64 {
     pub n_value: u64,
 }
 
+impl Nlist64 {
+    pub fn is_external(&self) -> bool {
+        self.n_type & N_EXT != 0
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.n_type & N_TYPE == N_UNDF
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.n_type & N_TYPE == N_ABS
+    }
+
+    pub fn is_weak_def(&self) -> bool {
+        !self.is_undefined() && self.n_desc & N_WEAK_DEF != 0
+    }
+
+    pub fn is_weak_ref(&self) -> bool {
+        self.is_undefined() && self.n_desc & N_WEAK_REF != 0
+    }
+
+    pub fn is_common(&self) -> bool {
+        self.is_undefined() && self.is_external() && self.n_value != 0
+    }
+
+    /// 公共符号的对齐（字节）
+    pub fn common_align(&self) -> u64 {
+        1u64 << ((self.n_desc & COMM_ALIGN_MASK) >> COMM_ALIGN_SHIFT)
+    }
+}
+
 // ==================== 文件结构 ====================
 #[derive(Debug, Clone)]
 pub enum LoadCommand {
@@ -198,6 +314,109 @@ pub enum LoadCommand {
         data: Vec<u8>,
     },
     NoteAbi { abi_version: u32, flags: u32 },
+    InitFuncs(InitFuncsCommand),
+    /// 长段名/节名表，定长字段中的 "/NNN" 指向其中偏移 NNN 处的名字
+    NameTable(Vec<u8>),
+    EntryPoint(EntryPointCommand),
+    /// 符号大小表，与 LC_SYMTAB 的符号一一对应（字节数，未知为 0）
+    SymbolSizes(Vec<u64>),
+}
+
+/// 重定位类型名（不含 RELOC_ 前缀），供 objdump 等工具显示
+pub fn relocation_type_name(r_type: u32) -> &'static str {
+    match r_type {
+        RELOC_NONE => "NONE",
+        RELOC_ABS64 => "ABS64",
+        RELOC_ABS32 => "ABS32",
+        RELOC_REL64 => "REL64",
+        RELOC_REL32 => "REL32",
+        RELOC_BRANCH26 => "BRANCH26",
+        RELOC_GOT => "GOT",
+        RELOC_PLT => "PLT",
+        RELOC_TLS => "TLS",
+        RELOC_AARCH64_ADR_PREL_PG_HI21 => "ADR_PREL_PG_HI21",
+        RELOC_AARCH64_ADD_ABS_LO12_NC => "ADD_ABS_LO12_NC",
+        RELOC_AARCH64_LD_PREL_LO19 => "LD_PREL_LO19",
+        _ => "UNKNOWN",
+    }
+}
+
+/// 调试信息节：`__DWARF` 段中的节或 `__debug` 开头的节（strip --strip-debug 与 ld -S 删除）
+pub fn is_debug_section(segname: &str, sectname: &str) -> bool {
+    segname == "__DWARF" || sectname.starts_with("__debug")
+}
+
+/// ELF 调试节名 -> Ohlink 节名（`.debug_info` -> `__debug_info`），其余节返回 None；
+/// elf2ohlink 把已链接 ELF 的调试节放入 `__DWARF` 段，ohlink2elf 与 ohlink-addr2line 按此对应
+pub fn dwarf_section_name(elf_name: &str) -> Option<String> {
+    elf_name.strip_prefix(".debug_").map(|rest| format!("__debug_{}", rest))
+}
+
+/// 第 `index` 个节（从 0 开始）的 ord（n_sect - 1）；超过 MAX_SECTIONS 个节时报错
+pub fn section_ord(index: usize) -> Result<u8> {
+    if index < MAX_SECTIONS {
+        Ok(index as u8)
+    } else {
+        Err(OhlinkError::TooManySections(index + 1))
+    }
+}
+
+/// 编译器生成的临时局部标号（`.L` 开头），ld/strip 的 --discard-locals 删除
+pub fn is_local_label(name: &str) -> bool {
+    name.starts_with(".L")
+}
+
+/// AArch64 映射符号（`$x`/`$d`），只用于区分代码与数据，不是真正的标号
+pub fn is_mapping_symbol(name: &str) -> bool {
+    name == "$x" || name == "$d" || name.starts_with("$x.") || name.starts_with("$d.")
+}
+
+/// 解析定长名字字段：普通名字原样返回，"/NNN" 从名字表偏移 NNN 处读取完整名字
+pub fn resolve_name(raw: &[u8], table: &[u8]) -> String {
+    let end = raw.iter().position(|&c| c == 0).unwrap_or(raw.len());
+    let inline = String::from_utf8_lossy(&raw[..end]);
+    if let Some(off) = inline.strip_prefix('/').and_then(|n| n.parse::<usize>().ok()) {
+        if off < table.len() {
+            let len = table[off..].iter().position(|&c| c == 0).unwrap_or(table.len() - off);
+            return String::from_utf8_lossy(&table[off..off + len]).into_owned();
+        }
+    }
+    inline.into_owned()
+}
+
+/// 名字表构建：放不进 N 字节定长字段（含结尾 NUL）的名字写入表中并以 "/NNN" 引用，
+/// 与 SysV ar 的长成员名约定相同；以 '/' 开头的名字也走名字表以免歧义
+#[derive(Default)]
+struct NameTableBuilder {
+    bytes: Vec<u8>,
+    offsets: std::collections::HashMap<String, usize>,
+}
+
+impl NameTableBuilder {
+    fn encode<const N: usize>(&mut self, name: &str) -> [u8; N] {
+        let mut out = [0u8; N];
+        let bytes = name.as_bytes();
+        if bytes.len() < N && !name.starts_with('/') {
+            out[..bytes.len()].copy_from_slice(bytes);
+            return out;
+        }
+        let off = *self.offsets.entry(name.to_string()).or_insert_with(|| {
+            let off = self.bytes.len();
+            self.bytes.extend_from_slice(bytes);
+            self.bytes.push(0);
+            off
+        });
+        let reference = format!("/{}", off);
+        out[..reference.len()].copy_from_slice(reference.as_bytes());
+        out
+    }
+
+    /// 8 字节对齐后的表内容
+    fn padded(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        bytes.resize(bytes.len().div_ceil(8) * 8, 0);
+        bytes
+    }
 }
 
 #[derive(Debug)]
@@ -209,6 +428,9 @@ pub struct OhlinkFile {
 
 impl OhlinkFile {
     pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 32 {
+            return Err(OhlinkError::ParseError { offset: 0, message: "Data too short for Ohlink header".to_string() });
+        }
         let header = OhlinkHeader::from_bytes(&data[0..32])?;
         header.validate()?;
 
@@ -284,6 +506,52 @@ impl OhlinkFile {
                     let flags = u32::from_le_bytes(data[offset + 12..offset + 16].try_into().unwrap());
                     commands.push(LoadCommand::NoteAbi { abi_version, flags });
                 }
+                LC_INIT_FUNCS => {
+                    let size = std::mem::size_of::<InitFuncsCommand>();
+                    if cmdsize as usize != size || offset + size > data.len() {
+                        return Err(OhlinkError::ParseError {
+                            offset: offset as u64,
+                            message: format!("Invalid InitFuncs size: {}", cmdsize),
+                        });
+                    }
+                    let init_cmd: InitFuncsCommand =
+                        unsafe { std::ptr::read(data[offset..offset + size].as_ptr() as *const _) };
+                    commands.push(LoadCommand::InitFuncs(init_cmd));
+                }
+                LC_ENTRY_POINT => {
+                    let size = std::mem::size_of::<EntryPointCommand>();
+                    if cmdsize as usize != size || offset + size > data.len() {
+                        return Err(OhlinkError::ParseError {
+                            offset: offset as u64,
+                            message: format!("Invalid EntryPoint size: {}", cmdsize),
+                        });
+                    }
+                    let entry_cmd: EntryPointCommand =
+                        unsafe { std::ptr::read(data[offset..offset + size].as_ptr() as *const _) };
+                    commands.push(LoadCommand::EntryPoint(entry_cmd));
+                }
+                LC_NAME_TABLE => {
+                    if cmdsize < 8 || offset + cmdsize as usize > data.len() {
+                        return Err(OhlinkError::ParseError {
+                            offset: offset as u64,
+                            message: format!("Invalid NameTable size: {}", cmdsize),
+                        });
+                    }
+                    commands.push(LoadCommand::NameTable(data[offset + 8..offset + cmdsize as usize].to_vec()));
+                }
+                LC_SYMBOL_SIZES => {
+                    if cmdsize < 8 || (cmdsize - 8) % 8 != 0 || offset + cmdsize as usize > data.len() {
+                        return Err(OhlinkError::ParseError {
+                            offset: offset as u64,
+                            message: format!("Invalid SymbolSizes size: {}", cmdsize),
+                        });
+                    }
+                    let sizes = data[offset + 8..offset + cmdsize as usize]
+                        .chunks_exact(8)
+                        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                        .collect();
+                    commands.push(LoadCommand::SymbolSizes(sizes));
+                }
                 _ => {
                     let end = (offset + cmdsize as usize).min(data.len());
                     let cmd_data = data[offset..end].to_vec();
@@ -304,14 +572,146 @@ impl OhlinkFile {
             data: data.to_vec(),
         })
     }
+
+    /// 符号表中的 (名字, nlist) 列表，没有 LC_SYMTAB 时为空
+    pub fn symbols(&self) -> Vec<(String, Nlist64)> {
+        let Some(sym) = self.commands.iter().find_map(|c| if let LoadCommand::Symtab(s) = c { Some(*s) } else { None }) else {
+            return Vec::new();
+        };
+        let strtab = self.data.get(sym.stroff as usize..).unwrap_or(&[]);
+        let strtab = &strtab[..(sym.strsize as usize).min(strtab.len())];
+        let nsz = std::mem::size_of::<Nlist64>();
+        (0..sym.nsyms as usize)
+            .map_while(|i| {
+                let off = sym.symoff as usize + i * nsz;
+                let bytes = self.data.get(off..off + nsz)?;
+                let e: Nlist64 = unsafe { std::ptr::read(bytes.as_ptr() as *const _) };
+                let name = strtab.get(e.n_strx as usize..).unwrap_or(&[]).split(|&c| c == 0).next().unwrap_or(&[]);
+                Some((String::from_utf8_lossy(name).into_owned(), e))
+            })
+            .collect()
+    }
+
+    /// LC_NAME_TABLE 的内容（没有长名字时为空）
+    pub fn name_table(&self) -> &[u8] {
+        self.commands
+            .iter()
+            .find_map(|c| if let LoadCommand::NameTable(t) = c { Some(t.as_slice()) } else { None })
+            .unwrap_or(&[])
+    }
+
+    /// LC_SYMBOL_SIZES 的内容，下标与 `symbols()` 相同（没有该命令时为空）
+    pub fn symbol_sizes(&self) -> &[u64] {
+        self.commands
+            .iter()
+            .find_map(|c| if let LoadCommand::SymbolSizes(t) = c { Some(t.as_slice()) } else { None })
+            .unwrap_or(&[])
+    }
+
+    /// 第 `index` 个符号记录的大小；文件没有大小表时为 None
+    pub fn symbol_size(&self, index: usize) -> Option<u64> {
+        self.symbol_sizes().get(index).copied()
+    }
+
+    /// 每个符号占用的字节数：优先取 LC_SYMBOL_SIZES 中的非零大小，公共符号为 n_value，
+    /// 其余节内符号估算为到同一节中下一个更高地址的有名符号（或节末）的距离；未定义与绝对符号为 None
+    pub fn symbol_extents(&self) -> Vec<Option<u64>> {
+        let sections = self.sections();
+        let symbols = self.symbols();
+        let sizes = self.symbol_sizes();
+        let mut starts: std::collections::HashMap<u8, Vec<u64>> = std::collections::HashMap::new();
+        for (name, n) in &symbols {
+            if !name.is_empty() && n.n_type & N_TYPE == N_SECT {
+                starts.entry(n.n_sect).or_default().push(n.n_value);
+            }
+        }
+        for v in starts.values_mut() {
+            v.sort_unstable();
+            v.dedup();
+        }
+        symbols
+            .iter()
+            .enumerate()
+            .map(|(i, (_, n))| {
+                if n.is_common() {
+                    return Some(n.n_value);
+                }
+                if n.n_type & N_TYPE != N_SECT {
+                    return None;
+                }
+                if let Some(&size) = sizes.get(i).filter(|&&s| s != 0) {
+                    return Some(size);
+                }
+                let (_, sec) = sections.get((n.n_sect as usize).wrapping_sub(1))?;
+                let next = starts.get(&n.n_sect).and_then(|v| v.get(v.partition_point(|&a| a <= n.n_value)).copied());
+                Some(next.unwrap_or(sec.addr + sec.size).saturating_sub(n.n_value))
+            })
+            .collect()
+    }
+
+    /// LC_ENTRY_POINT 记录的入口地址
+    pub fn entry_point(&self) -> Option<u64> {
+        self.commands.iter().find_map(|c| if let LoadCommand::EntryPoint(e) = c { Some(e.entry) } else { None })
+    }
+
+    /// 段的完整名字
+    pub fn segment_name(&self, seg: &SegmentCommand64) -> String {
+        resolve_name(&seg.segname, self.name_table())
+    }
+
+    /// 段的实际权限；初始权限为默认的 VM_PROT_ALL 时按段名判断（__TEXT 只读可执行，其余可读写）
+    pub fn segment_protection(&self, seg: &SegmentCommand64) -> i32 {
+        match seg.initprot {
+            VM_PROT_ALL if self.segment_name(seg) == "__TEXT" => VM_PROT_READ | VM_PROT_EXECUTE,
+            VM_PROT_ALL => VM_PROT_READ | VM_PROT_WRITE,
+            prot => prot,
+        }
+    }
+
+    /// 节的完整名字
+    pub fn section_name(&self, sec: &Section64) -> String {
+        resolve_name(&sec.sectname, self.name_table())
+    }
+
+    /// 按加载命令顺序列出 (段, 节)；nlist 的 n_sect 从 1 开始，对应下标 n_sect - 1
+    pub fn sections(&self) -> Vec<(&SegmentCommand64, &Section64)> {
+        self.commands
+            .iter()
+            .filter_map(|c| if let LoadCommand::Segment64(seg, secs) = c { Some((seg, secs)) } else { None })
+            .flat_map(|(seg, secs)| secs.iter().map(move |s| (seg, s)))
+            .collect()
+    }
+
+    /// 节在文件中的内容；零填充节（及越界的节）为空
+    pub fn section_data(&self, sec: &Section64) -> &[u8] {
+        if sec.flags & SECTION_TYPE == S_ZEROFILL || sec.offset == 0 {
+            return &[];
+        }
+        let start = sec.offset as usize;
+        self.data.get(start..start + sec.size as usize).unwrap_or(&[])
+    }
+
+    /// 节的重定位项
+    pub fn relocations(&self, sec: &Section64) -> Vec<Relocation64> {
+        let rsz = std::mem::size_of::<Relocation64>();
+        (0..sec.nreloc as usize)
+            .map_while(|i| {
+                let off = sec.reloff as usize + i * rsz;
+                let bytes = self.data.get(off..off + rsz)?;
+                Some(unsafe { std::ptr::read(bytes.as_ptr() as *const Relocation64) })
+            })
+            .collect()
+    }
 }
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct OhlibHeader {
     pub magic: [u8; 4],
+    /// 成员表的项数，包括作为第一项的 __.SYMDEF（`OhlibArchive::len` 不计它）
     pub nentries: u32,
-    pub reserved: u32,
+    /// 紧随成员表之后的长成员名表大小（字节，0 表示没有）
+    pub names_size: u32,
 }
 
 #[repr(C)]
@@ -322,11 +722,12 @@ pub struct OhlibEntry {
     pub size: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct OhlibArchive {
-    pub header: OhlibHeader,
-    pub entries: Vec<OhlibEntry>,
-    pub data: Vec<u8>,
+    /// 普通成员（不含 __.SYMDEF）：(名字, 内容)，按归档顺序
+    members: Vec<(String, Vec<u8>)>,
+    /// 符号索引：(符号名, 成员下标)；旧归档、未生成索引或成员被修改后为 None
+    pub index: Option<Vec<(String, u32)>>,
 }
 
 impl OhlibArchive {
@@ -342,51 +743,218 @@ impl OhlibArchive {
             entries.push(e);
             off += std::mem::size_of::<OhlibEntry>();
         }
-        Ok(Self { header, entries, data: data.to_vec() })
+        let names_end = off + header.names_size as usize;
+        if names_end > data.len() { return Err(OhlinkError::ParseError { offset: off as u64, message: "Incomplete ohlib name table".to_string() }); }
+        let names = &data[off..names_end];
+        let mut index = None;
+        let mut members = Vec::with_capacity(entries.len());
+        for (i, e) in entries.iter().enumerate() {
+            let name = resolve_name(&e.name, names);
+            let body = data
+                .get(e.offset as usize..e.offset.saturating_add(e.size) as usize)
+                .ok_or_else(|| OhlinkError::ParseError { offset: e.offset, message: format!("ohlib member {} out of bounds", name) })?;
+            if i == 0 && name == OHLIB_SYMDEF {
+                index = Some(parse_symdef(body).ok_or_else(|| OhlinkError::ParseError { offset: e.offset, message: "Malformed symbol index".to_string() })?);
+            } else {
+                members.push((name, body.to_vec()));
+            }
+        }
+        Ok(Self { members, index })
+    }
+
+    /// 空归档
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 成员数（不含 __.SYMDEF）
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// 第 i 个成员的 (名字, 内容)
+    pub fn member(&self, i: usize) -> Option<(&str, &[u8])> {
+        self.members.get(i).map(|(name, bytes)| (name.as_str(), bytes.as_slice()))
+    }
+
+    /// 所有成员的 (名字, 内容)，按归档顺序
+    pub fn members(&self) -> Vec<(String, &[u8])> {
+        self.members.iter().map(|(name, bytes)| (name.clone(), bytes.as_slice())).collect()
+    }
+
+    /// 按名字查找成员下标
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.members.iter().position(|(n, _)| n == name)
+    }
+
+    /// 在 pos 处插入成员（pos 超出范围时追加）
+    pub fn insert_member(&mut self, pos: usize, name: &str, bytes: &[u8]) {
+        let pos = pos.min(self.members.len());
+        self.members.insert(pos, (name.to_string(), bytes.to_vec()));
+        self.index = None;
+    }
+
+    /// 追加成员，不检查重名（ar q）
+    pub fn append_member(&mut self, name: &str, bytes: &[u8]) {
+        self.insert_member(usize::MAX, name, bytes);
+    }
+
+    /// 替换同名成员的内容；不存在时返回 false
+    pub fn replace_member(&mut self, name: &str, bytes: &[u8]) -> bool {
+        let Some(pos) = self.position(name) else { return false };
+        self.members[pos].1 = bytes.to_vec();
+        self.index = None;
+        true
+    }
+
+    /// 删除同名成员；不存在时返回 false
+    pub fn remove_member(&mut self, name: &str) -> bool {
+        let Some(pos) = self.position(name) else { return false };
+        self.members.remove(pos);
+        self.index = None;
+        true
+    }
+
+    /// 把成员移动到 pos（按移除后的下标计算，超出范围时移到末尾）；不存在时返回 false
+    pub fn move_member(&mut self, name: &str, pos: usize) -> bool {
+        let Some(from) = self.position(name) else { return false };
+        let member = self.members.remove(from);
+        let pos = pos.min(self.members.len());
+        self.members.insert(pos, member);
+        self.index = None;
+        true
+    }
+
+    /// 序列化归档，symbol_index 决定是否写入 __.SYMDEF（未修改的索引直接沿用，否则扫描成员生成）
+    pub fn to_bytes(&self, symbol_index: bool) -> Vec<u8> {
+        let index = symbol_index.then(|| self.index.clone().unwrap_or_else(|| self.build_symbol_index()));
+        serialize_ohlib(&self.members, index.as_deref())
+    }
+
+    /// 扫描各成员的符号表生成索引（用于没有 __.SYMDEF 的归档）
+    pub fn build_symbol_index(&self) -> Vec<(String, u32)> {
+        symbol_index_of(&self.members)
+    }
+}
+
+fn symbol_index_of(members: &[(String, Vec<u8>)]) -> Vec<(String, u32)> {
+    members
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, bytes))| defined_symbol_names(bytes).into_iter().map(move |name| (name, i as u32)))
+        .collect()
+}
+
+/// 成员中可满足其它文件引用的符号：外部定义与公共符号
+fn defined_symbol_names(bytes: &[u8]) -> Vec<String> {
+    let Ok(file) = OhlinkFile::parse(bytes) else { return Vec::new() };
+    file.symbols()
+        .into_iter()
+        .filter(|(_, e)| e.is_common() || (!e.is_undefined() && e.is_external()))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// __.SYMDEF 布局：nsyms(u32)，nsyms 个 (name_off u32, member u32)，strsize(u32)，字符串表
+fn parse_symdef(body: &[u8]) -> Option<Vec<(String, u32)>> {
+    let u32_at = |off: usize| body.get(off..off + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    let nsyms = u32_at(0)? as usize;
+    let str_start = 4 + nsyms * 8 + 4;
+    let strsize = u32_at(4 + nsyms * 8)? as usize;
+    let strings = body.get(str_start..str_start + strsize)?;
+    (0..nsyms)
+        .map(|i| {
+            let name_off = u32_at(4 + i * 8)? as usize;
+            let member = u32_at(8 + i * 8)?;
+            let name = strings.get(name_off..)?.split(|&c| c == 0).next()?;
+            Some((String::from_utf8_lossy(name).into_owned(), member))
+        })
+        .collect()
+}
+
+fn build_symdef(index: &[(String, u32)]) -> Vec<u8> {
+    let mut table = Vec::new();
+    let mut strings = Vec::new();
+    for (name, member) in index {
+        table.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        table.extend_from_slice(&member.to_le_bytes());
+        strings.extend_from_slice(name.as_bytes());
+        strings.push(0);
     }
+    let mut body = (index.len() as u32).to_le_bytes().to_vec();
+    body.extend_from_slice(&table);
+    body.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+    body.extend_from_slice(&strings);
+    body
 }
 
 pub struct OhlibBuilder {
     entries: Vec<(String, Vec<u8>)>,
+    symbol_index: bool,
+}
+
+impl Default for OhlibBuilder {
+    fn default() -> Self { Self::new() }
 }
 
 impl OhlibBuilder {
-    pub fn new() -> Self { Self { entries: Vec::new() } }
+    pub fn new() -> Self { Self { entries: Vec::new(), symbol_index: true } }
     pub fn add_member(&mut self, name: &str, bytes: &[u8]) { self.entries.push((name.to_string(), bytes.to_vec())); }
+    /// 是否写入 __.SYMDEF 符号索引（默认写入）
+    pub fn set_symbol_index(&mut self, enabled: bool) { self.symbol_index = enabled; }
     pub fn build(self) -> Vec<u8> {
-        let n = self.entries.len();
-        let hsz = std::mem::size_of::<OhlibHeader>();
-        let esz = std::mem::size_of::<OhlibEntry>();
-        let header = OhlibHeader { magic: OHLIB_MAGIC, nentries: n as u32, reserved: 0 };
-        let mut result = Vec::new();
-        result.resize(hsz + n * esz, 0);
-        let mut cursor = hsz;
-        let mut data_off = (hsz + n * esz) as u64;
-        let mut data_blob = Vec::new();
-        for (name, bytes) in self.entries {
-            let mut entry = OhlibEntry { name: [0; 32], offset: data_off, size: bytes.len() as u64 };
-            let nb = name.as_bytes();
-            let nl = nb.len().min(31);
-            entry.name[..nl].copy_from_slice(&nb[..nl]);
-            let ebytes = unsafe { std::slice::from_raw_parts(&entry as *const _ as *const u8, esz) };
-            result[cursor..cursor + esz].copy_from_slice(ebytes);
-            cursor += esz;
-            data_blob.extend_from_slice(&bytes);
-            data_off += bytes.len() as u64;
-        }
-        let hbytes = unsafe { std::slice::from_raw_parts(&header as *const _ as *const u8, hsz) };
-        result[0..hsz].copy_from_slice(hbytes);
-        result.extend_from_slice(&data_blob);
-        result
+        let index = self.symbol_index.then(|| symbol_index_of(&self.entries));
+        serialize_ohlib(&self.entries, index.as_deref())
     }
 }
 
+/// 写出归档：有索引时 __.SYMDEF 作为第一个成员
+fn serialize_ohlib(members: &[(String, Vec<u8>)], index: Option<&[(String, u32)]>) -> Vec<u8> {
+    let symdef = index.map(|index| (OHLIB_SYMDEF, build_symdef(index)));
+    let entries: Vec<(&str, &[u8])> = symdef
+        .iter()
+        .map(|(name, body)| (*name, body.as_slice()))
+        .chain(members.iter().map(|(name, bytes)| (name.as_str(), bytes.as_slice())))
+        .collect();
+    let n = entries.len();
+    let hsz = std::mem::size_of::<OhlibHeader>();
+    let esz = std::mem::size_of::<OhlibEntry>();
+    let mut names = NameTableBuilder::default();
+    let encoded: Vec<[u8; 32]> = entries.iter().map(|(name, _)| names.encode(name)).collect();
+    let names_blob = names.padded();
+    let header = OhlibHeader { magic: OHLIB_MAGIC, nentries: n as u32, names_size: names_blob.len() as u32 };
+    let mut result = vec![0; hsz + n * esz];
+    let mut cursor = hsz;
+    let mut data_off = (hsz + n * esz + names_blob.len()) as u64;
+    for ((_, bytes), name) in entries.iter().zip(encoded) {
+        let entry = OhlibEntry { name, offset: data_off, size: bytes.len() as u64 };
+        let ebytes = unsafe { std::slice::from_raw_parts(&entry as *const _ as *const u8, esz) };
+        result[cursor..cursor + esz].copy_from_slice(ebytes);
+        cursor += esz;
+        data_off += bytes.len() as u64;
+    }
+    let hbytes = unsafe { std::slice::from_raw_parts(&header as *const _ as *const u8, hsz) };
+    result[0..hsz].copy_from_slice(hbytes);
+    result.extend_from_slice(&names_blob);
+    for (_, bytes) in &entries {
+        result.extend_from_slice(bytes);
+    }
+    result
+}
+
 // ==================== 构建器 ====================
 pub struct OhlinkBuilder {
     file_type: u32,
     segments: Vec<SegmentBuilder>,
     symbols: Vec<SymbolEntry>,
     strings: Vec<u8>,
+    init_funcs: Option<InitFuncsCommand>,
+    entry_point: Option<EntryPointCommand>,
+    symbol_sizes: Vec<u64>,
 }
 
 impl OhlinkBuilder {
@@ -396,25 +964,60 @@ impl OhlinkBuilder {
             segments: Vec::new(),
             symbols: Vec::new(),
             strings: vec![0], // 字符串表以空字符开始
+            init_funcs: None,
+            entry_point: None,
+            symbol_sizes: Vec::new(),
+        }
+    }
+
+    /// 记录 preinit/init/fini 数组的 (地址, 字节数)，生成 LC_INIT_FUNCS
+    pub fn set_init_funcs(&mut self, preinit: (u64, u64), init: (u64, u64), fini: (u64, u64)) {
+        self.init_funcs = Some(InitFuncsCommand {
+            cmd: LC_INIT_FUNCS,
+            cmdsize: std::mem::size_of::<InitFuncsCommand>() as u32,
+            preinit_addr: preinit.0,
+            preinit_size: preinit.1,
+            init_addr: init.0,
+            init_size: init.1,
+            fini_addr: fini.0,
+            fini_size: fini.1,
+        });
+    }
+
+    /// 记录入口地址，生成 LC_ENTRY_POINT
+    pub fn set_entry_point(&mut self, entry: u64) {
+        self.entry_point = Some(EntryPointCommand {
+            cmd: LC_ENTRY_POINT,
+            cmdsize: std::mem::size_of::<EntryPointCommand>() as u32,
+            entry,
+        });
+    }
+
+    /// 记录符号大小（`add_*symbol*` 返回的下标），至少有一个非零大小时生成 LC_SYMBOL_SIZES
+    pub fn set_symbol_size(&mut self, index: u32, size: u64) {
+        let index = index as usize;
+        if self.symbol_sizes.len() <= index {
+            self.symbol_sizes.resize(index + 1, 0);
         }
+        self.symbol_sizes[index] = size;
     }
 
     pub fn segment_count(&self) -> usize {
         self.segments.len()
     }
 
+    /// 已添加的节总数；下一个节的 ord（n_sect - 1）即为此值
+    pub fn section_count(&self) -> usize {
+        self.segments.iter().map(|s| s.sections.len()).sum()
+    }
+
     pub fn symbol_count(&self) -> usize {
         self.symbols.len()
     }
 
     pub fn add_segment(&mut self, name: &str, vmaddr: u64) -> &mut SegmentBuilder {
-        let mut segname = [0; 16];
-        let bytes = name.as_bytes();
-        let len = bytes.len().min(15);
-        segname[..len].copy_from_slice(&bytes[..len]);
-
         self.segments.push(SegmentBuilder {
-            segname,
+            segname: name.to_string(),
             vmaddr,
             maxprot: 7, // RWX
             initprot: 7,
@@ -456,6 +1059,61 @@ impl OhlinkBuilder {
         index
     }
 
+    /// 添加未定义符号（n_sect = NO_SECT），n_desc 可携带 N_WEAK_REF
+    pub fn add_undefined_symbol(&mut self, name: &str, n_desc: u16) -> u32 {
+        let n_strx = self.strings.len() as u32;
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+
+        let index = self.symbols.len() as u32;
+        self.symbols.push(SymbolEntry {
+            n_strx,
+            n_type: N_UNDF | N_EXT,
+            n_sect: NO_SECT,
+            n_desc,
+            n_value: 0,
+        });
+
+        index
+    }
+
+    /// 添加绝对符号（不属于任何节，如链接器定义的边界符号）
+    pub fn add_absolute_symbol(&mut self, name: &str, value: u64) -> u32 {
+        let n_strx = self.strings.len() as u32;
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+
+        let index = self.symbols.len() as u32;
+        self.symbols.push(SymbolEntry {
+            n_strx,
+            n_type: N_ABS | N_EXT,
+            n_sect: NO_SECT,
+            n_desc: 0,
+            n_value: value,
+        });
+
+        index
+    }
+
+    /// 添加公共符号：大小记录在 n_value，对齐（2 的幂）记录在 n_desc
+    pub fn add_common_symbol(&mut self, name: &str, size: u64, align: u64) -> u32 {
+        let n_strx = self.strings.len() as u32;
+        self.strings.extend_from_slice(name.as_bytes());
+        self.strings.push(0);
+
+        let log2 = align.max(1).trailing_zeros().min(15) as u16;
+        let index = self.symbols.len() as u32;
+        self.symbols.push(SymbolEntry {
+            n_strx,
+            n_type: N_UNDF | N_EXT,
+            n_sect: NO_SECT,
+            n_desc: log2 << COMM_ALIGN_SHIFT,
+            n_value: size,
+        });
+
+        index
+    }
+
     pub fn add_symbol_with(&mut self, name: &str, value: u64, sect: u8, n_type: u8, n_desc: u16) -> u32 {
         let n_strx = self.strings.len() as u32;
         self.strings.extend_from_slice(name.as_bytes());
@@ -473,6 +1131,253 @@ impl OhlinkBuilder {
         index
     }
 
+    /// 以已解析的文件为起点重建（strip/objcopy 使用）：保留段与权限、节内容、重定位、符号及其大小、
+    /// 入口与初始化数组；偏移与加载命令由 `build` 重新计算，无法识别的加载命令不保留。
+    /// 节内容取到下一个节（或重定位表、段的文件末尾）为止，使节之间的填充保持不变
+    pub fn from_file(file: &OhlinkFile) -> Self {
+        let mut b = OhlinkBuilder::new(file.header.file_type);
+        for cmd in &file.commands {
+            match cmd {
+                LoadCommand::Segment64(seg, secs) => {
+                    let seg_end = seg.fileoff + seg.filesize;
+                    let out = b.add_segment(&file.segment_name(seg), seg.vmaddr);
+                    out.set_protection(seg.maxprot, seg.initprot);
+                    out.flags = seg.flags;
+                    for (i, sec) in secs.iter().enumerate() {
+                        let data = if sec.offset == 0 || sec.flags & SECTION_TYPE == S_ZEROFILL {
+                            &[][..]
+                        } else {
+                            let start = sec.offset as u64;
+                            let end = secs[i + 1..]
+                                .iter()
+                                .map(|n| n.offset as u64)
+                                .find(|&o| o != 0)
+                                .into_iter()
+                                .chain((sec.nreloc > 0).then_some(sec.reloff as u64))
+                                .chain([seg_end])
+                                .min()
+                                .unwrap_or(seg_end)
+                                .max(start + sec.size);
+                            file.data.get(start as usize..end as usize).unwrap_or_else(|| file.section_data(sec))
+                        };
+                        out.add_section_with(&file.section_name(sec), data, sec.addr - seg.vmaddr, sec.align, sec.size);
+                        out.set_section_flags(sec.flags, sec.reserved1);
+                        if let Some(last) = out.sections.last_mut() {
+                            last.relocations = file.relocations(sec);
+                        }
+                    }
+                }
+                LoadCommand::InitFuncs(c) => {
+                    b.set_init_funcs((c.preinit_addr, c.preinit_size), (c.init_addr, c.init_size), (c.fini_addr, c.fini_size));
+                }
+                LoadCommand::EntryPoint(e) => b.set_entry_point(e.entry),
+                _ => {}
+            }
+        }
+        for (name, n) in file.symbols() {
+            b.push_symbol(&name, &n);
+        }
+        b.symbol_sizes = file.symbol_sizes().to_vec();
+        b
+    }
+
+    fn push_symbol(&mut self, name: &str, n: &Nlist64) -> u32 {
+        let n_strx = if name.is_empty() {
+            0
+        } else {
+            let strx = self.strings.len() as u32;
+            self.strings.extend_from_slice(name.as_bytes());
+            self.strings.push(0);
+            strx
+        };
+        self.symbols.push(SymbolEntry { n_strx, n_type: n.n_type, n_sect: n.n_sect, n_desc: n.n_desc, n_value: n.n_value });
+        self.symbols.len() as u32 - 1
+    }
+
+    fn symbol_names(&self) -> Vec<String> {
+        self.symbols
+            .iter()
+            .map(|s| {
+                let name = self.strings.get(s.n_strx as usize..).unwrap_or(&[]).split(|&c| c == 0).next().unwrap_or(&[]);
+                String::from_utf8_lossy(name).into_owned()
+            })
+            .collect()
+    }
+
+    /// 保留的节（按 ord）中引用了被删除符号的第一个重定位，返回该符号名
+    fn relocation_conflict(&self, section_kept: impl Fn(usize) -> bool, symbol_kept: &[bool], names: &[String]) -> Option<String> {
+        let sections = self.segments.iter().flat_map(|seg| &seg.sections);
+        sections
+            .enumerate()
+            .filter(|(ord, _)| section_kept(*ord))
+            .flat_map(|(_, sec)| &sec.relocations)
+            .find(|r| symbol_kept.get(r.r_symbol as usize) == Some(&false))
+            .map(|r| names[r.r_symbol as usize].clone())
+    }
+
+    /// 只保留 `keep(下标, 名字, nlist)` 为真的符号，重新编号重定位的符号下标并压缩字符串表；
+    /// 被重定位引用的符号不能删除，此时不做任何修改并返回该符号名
+    pub fn retain_symbols(&mut self, mut keep: impl FnMut(usize, &str, &Nlist64) -> bool) -> std::result::Result<(), String> {
+        let names = self.symbol_names();
+        let kept: Vec<bool> = self.symbols.iter().enumerate().map(|(i, s)| keep(i, &names[i], &s.to_nlist64())).collect();
+        if let Some(name) = self.relocation_conflict(|_| true, &kept, &names) {
+            return Err(name);
+        }
+        let old = std::mem::take(&mut self.symbols);
+        let old_sizes = std::mem::take(&mut self.symbol_sizes);
+        self.strings = vec![0];
+        let mut remap: Vec<Option<u32>> = Vec::with_capacity(old.len());
+        for (i, sym) in old.iter().enumerate() {
+            if !kept[i] {
+                remap.push(None);
+                continue;
+            }
+            let idx = self.push_symbol(&names[i], &sym.to_nlist64());
+            if let Some(&size) = old_sizes.get(i) {
+                self.set_symbol_size(idx, size);
+            }
+            remap.push(Some(idx));
+        }
+        for seg in &mut self.segments {
+            for sec in &mut seg.sections {
+                for r in &mut sec.relocations {
+                    if let Some(Some(idx)) = remap.get(r.r_symbol as usize) {
+                        r.r_symbol = *idx;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除 `keep(段名, 节名)` 为假的节及其中定义的符号，其余符号的 n_sect 随之重新编号；
+    /// 删除后没有节的段一并删除。保留的节的重定位引用被删除的符号、符号的 n_sect 不指向任何节
+    /// 或保留的节超过 MAX_SECTIONS 个时不做任何修改并返回错误
+    pub fn retain_sections(&mut self, mut keep: impl FnMut(&str, &str) -> bool) -> std::result::Result<(), RetainError> {
+        let section_kept: Vec<bool> = self
+            .segments
+            .iter()
+            .flat_map(|seg| seg.sections.iter().map(|sec| (&seg.segname, &sec.sectname)))
+            .map(|(seg, sect)| keep(seg, sect))
+            .collect();
+        // 旧 n_sect（从 1 开始） -> 新 n_sect，NO_SECT 表示已删除
+        let mut sect_map: Vec<u8> = vec![NO_SECT];
+        let mut kept_count = 0;
+        for &kept in &section_kept {
+            if kept {
+                sect_map.push(section_ord(kept_count)? + 1);
+                kept_count += 1;
+            } else {
+                sect_map.push(NO_SECT);
+            }
+        }
+        let names = self.symbol_names();
+        for (sym, name) in self.symbols.iter().zip(&names) {
+            if sym.n_type & N_TYPE == N_SECT && (sym.n_sect == NO_SECT || sym.n_sect as usize >= sect_map.len()) {
+                return Err(RetainError::MissingSection { name: name.clone(), n_sect: sym.n_sect });
+            }
+        }
+        let symbol_kept: Vec<bool> = self
+            .symbols
+            .iter()
+            .map(|n| n.n_type & N_TYPE != N_SECT || sect_map[n.n_sect as usize] != NO_SECT)
+            .collect();
+        if let Some(name) = self.relocation_conflict(|ord| section_kept[ord], &symbol_kept, &names) {
+            return Err(RetainError::Referenced(name));
+        }
+
+        let mut flags = section_kept.into_iter();
+        self.segments.retain_mut(|seg| {
+            let had_sections = !seg.sections.is_empty();
+            seg.sections.retain(|_| flags.next().unwrap_or(true));
+            !had_sections || !seg.sections.is_empty()
+        });
+        let mut kept = symbol_kept.into_iter();
+        self.retain_symbols(|_, _, _| kept.next().unwrap_or(true)).map_err(RetainError::Referenced)?;
+        for sym in &mut self.symbols {
+            if sym.n_type & N_TYPE == N_SECT {
+                sym.n_sect = sect_map[sym.n_sect as usize];
+            }
+        }
+        Ok(())
+    }
+
+    /// 按名字查找已添加的段
+    pub fn segment_mut(&mut self, name: &str) -> Option<&mut SegmentBuilder> {
+        self.segments.iter_mut().find(|seg| seg.segname == name)
+    }
+
+    /// 所有段中节末尾的最高虚拟地址
+    pub fn vm_end(&self) -> u64 {
+        self.segments.iter().map(|seg| seg.vmaddr + seg.end()).max().unwrap_or(0)
+    }
+
+    /// 把 `matches(段名, 节名)` 为真的节改名为 `new_name`，返回改动的节数
+    pub fn rename_sections(&mut self, mut matches: impl FnMut(&str, &str) -> bool, new_name: &str) -> usize {
+        let mut count = 0;
+        for seg in &mut self.segments {
+            for sec in &mut seg.sections {
+                if matches(&seg.segname, &sec.sectname) {
+                    sec.sectname = new_name.to_string();
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 设置 `matches(段名, 节名)` 为真的节的对齐（字节），返回改动的节数；节的地址不变
+    pub fn set_section_alignment(&mut self, mut matches: impl FnMut(&str, &str) -> bool, align: u32) -> usize {
+        let mut count = 0;
+        for seg in &mut self.segments {
+            for sec in &mut seg.sections {
+                if matches(&seg.segname, &sec.sectname) {
+                    sec.align = align;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// 整体平移地址：段基址（节随之移动）、重定位地址、节内符号的值、入口与初始化数组；__PAGEZERO 保持在 0。
+    /// 有段会越过地址空间两端时不做任何修改并返回该段名
+    pub fn change_addresses(&mut self, delta: i64) -> std::result::Result<(), String> {
+        let movable = |seg: &SegmentBuilder| seg.segname != "__PAGEZERO";
+        if let Some(seg) = self
+            .segments
+            .iter()
+            .filter(|seg| movable(seg))
+            .find(|seg| seg.vmaddr.checked_add_signed(delta).and_then(|a| a.checked_add(seg.end())).is_none())
+        {
+            return Err(seg.segname.clone());
+        }
+        let shift = |addr: &mut u64| *addr = addr.wrapping_add_signed(delta);
+        for seg in self.segments.iter_mut().filter(|seg| movable(seg)) {
+            shift(&mut seg.vmaddr);
+            // r_addr 是绝对地址，随节移动
+            for r in seg.sections.iter_mut().flat_map(|sec| &mut sec.relocations) {
+                shift(&mut r.r_addr);
+            }
+        }
+        for sym in &mut self.symbols {
+            if sym.n_type & N_TYPE == N_SECT {
+                shift(&mut sym.n_value);
+            }
+        }
+        if let Some(e) = &mut self.entry_point {
+            shift(&mut e.entry);
+        }
+        if let Some(c) = &mut self.init_funcs {
+            for (addr, size) in [(&mut c.preinit_addr, c.preinit_size), (&mut c.init_addr, c.init_size), (&mut c.fini_addr, c.fini_size)] {
+                if size != 0 {
+                    shift(addr);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn build(mut self) -> Vec<u8> {
         let mut result = Vec::new();
         let mut load_commands = Vec::new();
@@ -491,26 +1396,51 @@ impl OhlinkBuilder {
         let sizeof_section = std::mem::size_of::<Section64>();
         let sizeof_symtab_cmd = std::mem::size_of::<SymtabCommand>();
         let note_abi_size = 16; // cmd+u32 + cmdsize+u32 + abi_version+u32 + flags+u32
+        let init_funcs_size = if self.init_funcs.is_some() { std::mem::size_of::<InitFuncsCommand>() } else { 0 };
+        let entry_point_size = if self.entry_point.is_some() { std::mem::size_of::<EntryPointCommand>() } else { 0 };
+        // 先登记所有长名字，才能确定名字表命令的大小
+        let mut names = NameTableBuilder::default();
+        for seg in &self.segments {
+            names.encode::<16>(&seg.segname);
+            for sec in &seg.sections {
+                names.encode::<16>(&sec.sectname);
+            }
+        }
+        let name_table = names.padded();
+        let name_table_size = if name_table.is_empty() { 0 } else { 8 + name_table.len() };
+        let mut symbol_sizes = std::mem::take(&mut self.symbol_sizes);
+        let symbol_sizes_size = if symbol_sizes.iter().any(|&s| s != 0) {
+            symbol_sizes.resize(self.symbols.len(), 0);
+            8 + 8 * symbol_sizes.len()
+        } else {
+            0
+        };
         let load_commands_size: usize = self
             .segments
             .iter()
             .map(|seg| sizeof_segment_cmd + seg.sections.len() * sizeof_section)
             .sum::<usize>()
             + sizeof_symtab_cmd
-            + note_abi_size; // <-- 把 NoteAbi 算进来
+            + note_abi_size // <-- 把 NoteAbi 算进来
+            + init_funcs_size
+            + entry_point_size
+            + name_table_size
+            + symbol_sizes_size;
 
-        let base_offset = 32u64 + load_commands_size as u64;
+        // file_offset 从 32 起算（result 预留了头部），最终文件在头部后插入加载命令区，
+        // 因此绝对偏移 = file_offset + 加载命令区长度
+        let base_offset = load_commands_size as u64;
 
         // 3. 构建段 - 使用 drain 来转移所有权，并修正偏移为绝对文件偏移
         let segments = std::mem::take(&mut self.segments); // 取走所有权
         let segment_count = segments.len();
         for segment in segments {
-            let (mut segment_cmd, mut sections, section_data) = segment.build(&mut file_offset);
+            let (mut segment_cmd, mut sections, section_data) = segment.build(&mut file_offset, &mut names);
 
             // 修正偏移：加上命令区长度
-            segment_cmd.fileoff = segment_cmd.fileoff + base_offset;
+            segment_cmd.fileoff += base_offset;
             for sec in &mut sections {
-                sec.offset = (sec.offset as u64 + base_offset) as u32;
+                if sec.offset != 0 { sec.offset = (sec.offset as u64 + base_offset) as u32; }
                 if sec.reloff != 0 { sec.reloff = (sec.reloff as u64 + base_offset) as u32; }
             }
 
@@ -558,7 +1488,7 @@ impl OhlinkBuilder {
         result.extend_from_slice(&self.strings);
 
         // 6. 将 Symtab 与 NoteAbi 命令写入加载命令区
-        let mut symtab_cmd = SymtabCommand {
+        let symtab_cmd = SymtabCommand {
             cmd: LC_SYMTAB,
             cmdsize: std::mem::size_of::<SymtabCommand>() as u32,
             symoff: (symtab_offset as u64 + base_offset) as u32,
@@ -577,6 +1507,36 @@ impl OhlinkBuilder {
         load_commands.extend_from_slice(&16u32.to_le_bytes());
         load_commands.extend_from_slice(&NOTE_ABI_VERSION.to_le_bytes());
         load_commands.extend_from_slice(&0u32.to_le_bytes());
+        if let Some(init_cmd) = &self.init_funcs {
+            let init_bytes = unsafe {
+                std::slice::from_raw_parts(
+                    init_cmd as *const _ as *const u8,
+                    std::mem::size_of::<InitFuncsCommand>(),
+                )
+            };
+            load_commands.extend_from_slice(init_bytes);
+        }
+        if let Some(entry_cmd) = &self.entry_point {
+            let entry_bytes = unsafe {
+                std::slice::from_raw_parts(
+                    entry_cmd as *const _ as *const u8,
+                    std::mem::size_of::<EntryPointCommand>(),
+                )
+            };
+            load_commands.extend_from_slice(entry_bytes);
+        }
+        if name_table_size != 0 {
+            load_commands.extend_from_slice(&LC_NAME_TABLE.to_le_bytes());
+            load_commands.extend_from_slice(&(name_table_size as u32).to_le_bytes());
+            load_commands.extend_from_slice(&name_table);
+        }
+        if symbol_sizes_size != 0 {
+            load_commands.extend_from_slice(&LC_SYMBOL_SIZES.to_le_bytes());
+            load_commands.extend_from_slice(&(symbol_sizes_size as u32).to_le_bytes());
+            for size in &symbol_sizes {
+                load_commands.extend_from_slice(&size.to_le_bytes());
+            }
+        }
         // for cmd in &load_commands {
         //     match cmd {
         //         LoadCommand::Segment64(seg, secs) => {
@@ -615,7 +1575,7 @@ impl OhlinkBuilder {
             cpu_type: CPU_TYPE_ARM64,
             cpu_subtype: 0,
             file_type: self.file_type,
-            ncmds: (segment_count + 2) as u32, // 段 + 符号表命令 + NoteAbi
+            ncmds: (segment_count + 2 + self.init_funcs.is_some() as usize + self.entry_point.is_some() as usize + (name_table_size != 0) as usize + (symbol_sizes_size != 0) as usize) as u32, // 段 + 符号表命令 + NoteAbi (+ InitFuncs + EntryPoint + NameTable + SymbolSizes)
             sizeofcmds: load_commands.len() as u32,
             flags: 0,
             reserved: 0,
@@ -638,6 +1598,29 @@ impl OhlinkBuilder {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn report_serializes_names_and_relocations() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0, 0, 0, 0x94], 0);
+        b.add_symbol("_start", 0, 0);
+        let foo = b.add_undefined_symbol("foo", 0);
+        b.add_relocations_by_ord(0, &[Relocation64 { r_addr: 0, r_symbol: foo, r_type: RELOC_BRANCH26, r_addend: 0 }]);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+
+        let report = parsed.report();
+        assert_eq!(report.symbols[1].kind, "undefined");
+        assert_eq!(report.symbols[0].section.as_deref(), Some("__TEXT,__text"));
+        let reloc = &report.sections[0].relocations[0];
+        assert_eq!((reloc.type_name, reloc.symbol.as_deref()), ("BRANCH26", Some("foo")));
+
+        let json = serde_json::to_value(&report).expect("json");
+        assert_eq!(json["header"]["magic"], "3422110f");
+        assert_eq!(json["load_commands"][0]["kind"], "segment64");
+        assert_eq!(json["load_commands"][0]["sections"][0]["sectname"], "__text");
+        assert_eq!(json["sections"][0]["relocations"][0]["r_type"], RELOC_BRANCH26);
+    }
+
     #[test]
     fn build_and_parse_offsets() {
         let mut b = OhlinkBuilder::new(MH_OBJECT);
@@ -651,7 +1634,7 @@ mod tests {
         let parsed = OhlinkFile::parse(&bytes).expect("parse");
 
         assert_eq!(parsed.header.magic, OHLINK_MAGIC_64);
-        assert_eq!(parsed.header.ncmds, 2);
+        assert_eq!(parsed.header.ncmds, 3); // 段 + 符号表 + NoteAbi
 
         let mut seg_opt: Option<(SegmentCommand64, Vec<Section64>)> = None;
         let mut sym_opt: Option<SymtabCommand> = None;
@@ -670,6 +1653,8 @@ mod tests {
         let base = 32u64 + parsed.header.sizeofcmds as u64;
         assert!(seg.fileoff >= base);
         assert!(secs[0].offset as u64 >= base);
+        let off = secs[0].offset as usize;
+        assert_eq!(&bytes[off..off + 4], &[1, 2, 3, 4]);
 
         // 符号表应紧随段数据之后
         let data_end = seg.fileoff + seg.filesize;
@@ -677,10 +1662,275 @@ mod tests {
         let nlist_size = std::mem::size_of::<Nlist64>() as u64;
         assert_eq!(sym.stroff as u64, sym.symoff as u64 + nlist_size);
     }
+
+    #[test]
+    fn zero_fill_section_has_no_file_offset() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__DATA", 0)
+            .add_section("__data", &[7; 8], 0)
+            .add_section_with("__bss", &[], 8, 8, 32);
+        let bytes = b.build();
+        let parsed = OhlinkFile::parse(&bytes).expect("parse");
+        let secs = parsed
+            .commands
+            .iter()
+            .find_map(|c| if let LoadCommand::Segment64(_, secs) = c { Some(secs.clone()) } else { None })
+            .expect("segment");
+        assert_ne!(secs[0].offset, 0);
+        assert_eq!((secs[1].offset, secs[1].size), (0, 32));
+    }
+
+    #[test]
+    fn init_funcs_and_section_flags_round_trip() {
+        let mut b = OhlinkBuilder::new(MH_EXECUTE);
+        b.add_segment("__DATA", 0x8000)
+            .add_section_with("__init_array", &[0; 16], 0, 8, 16)
+            .set_section_flags(S_INIT_FUNC_POINTERS, 100);
+        b.set_init_funcs((0, 0), (0x8000, 16), (0, 0));
+        let bytes = b.build();
+        let parsed = OhlinkFile::parse(&bytes).expect("parse");
+        assert_eq!(parsed.header.ncmds, 4);
+
+        let mut found = None;
+        for cmd in &parsed.commands {
+            match cmd {
+                LoadCommand::Segment64(_, secs) => {
+                    assert_eq!(secs[0].flags & SECTION_TYPE, S_INIT_FUNC_POINTERS);
+                    assert_eq!(secs[0].reserved1, 100);
+                }
+                LoadCommand::InitFuncs(c) => found = Some(*c),
+                _ => {}
+            }
+        }
+        let init = found.expect("init funcs");
+        assert_eq!((init.init_addr, init.init_size), (0x8000, 16));
+    }
+
+    #[test]
+    fn symbol_sizes_round_trip() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 12], 0);
+        let f = b.add_symbol("f", 0, 0);
+        b.add_symbol("g", 8, 0);
+        b.add_undefined_symbol("ext", 0);
+        b.set_symbol_size(f, 8);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+        assert_eq!(parsed.symbol_sizes(), &[8, 0, 0]);
+        assert_eq!(parsed.symbol_size(0), Some(8));
+
+        // 没有非零大小时不生成命令
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        let g = b.add_symbol("g", 0, 0);
+        b.set_symbol_size(g, 0);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+        assert_eq!(parsed.symbol_size(0), None);
+    }
+
+    #[test]
+    fn rebuild_retains_symbols_and_sections() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__DWARF", 0).add_section("__debug_info", &[1; 8], 0);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0, 0, 0, 0x94], 0);
+        b.add_symbol_with("info", 0, 0, N_SECT, 0);
+        b.add_symbol_with(".Ltmp", 0, 1, N_SECT, 0);
+        b.add_symbol("f", 0, 1);
+        let ext = b.add_undefined_symbol("ext", 0);
+        b.set_symbol_size(2, 4);
+        b.add_relocations_by_ord(1, &[Relocation64 { r_addr: 0, r_symbol: ext, r_type: RELOC_BRANCH26, r_addend: 0 }]);
+        let original = b.build();
+        let parsed = OhlinkFile::parse(&original).expect("parse");
+        assert_eq!(OhlinkBuilder::from_file(&parsed).build(), original);
+
+        let mut b = OhlinkBuilder::from_file(&parsed);
+        assert_eq!(b.retain_symbols(|_, name, _| name != "ext"), Err("ext".to_string()));
+        let mut b2 = OhlinkBuilder::from_file(&parsed);
+        b2.retain_symbols(|_, name, _| !is_local_label(name)).expect("retain");
+        b2.retain_sections(|seg, sect| !is_debug_section(seg, sect)).expect("retain");
+        let stripped = OhlinkFile::parse(&b2.build()).expect("parse");
+        let names: Vec<String> = stripped.symbols().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["f", "ext"]);
+        assert_eq!(stripped.symbols()[0].1.n_sect, 1);
+        assert_eq!(stripped.symbol_sizes(), &[4, 0]);
+        let (_, text) = stripped.sections()[0];
+        assert_eq!(stripped.section_data(text), &[0, 0, 0, 0x94]);
+        assert_eq!(stripped.relocations(text)[0].r_symbol, 1);
+        // 失败时不做修改
+        assert_eq!(b.build(), original);
+    }
+
+    #[test]
+    fn section_ordinals_are_checked() {
+        assert_eq!(section_ord(254).unwrap(), 254);
+        assert!(matches!(section_ord(MAX_SECTIONS), Err(OhlinkError::TooManySections(256))));
+
+        // n_sect 超出节数（损坏的输入）时报错而不是越界
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0xc0, 0x03, 0x5f, 0xd6], 0);
+        b.add_symbol("bad", 0, 4);
+        let err = b.retain_sections(|_, _| false).unwrap_err();
+        assert!(matches!(&err, RetainError::MissingSection { name, n_sect: 5 } if name == "bad"), "{}", err);
+        let unchanged = OhlinkFile::parse(&b.build()).expect("parse");
+        assert_eq!((unchanged.sections().len(), unchanged.symbols().len()), (1, 1));
+    }
+
+    #[test]
+    fn change_addresses_moves_relocations() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0, 0, 0, 0x94, 0xc0, 0x03, 0x5f, 0xd6], 0);
+        let f = b.add_symbol("f", 4, 0);
+        b.add_relocations_by_ord(0, &[Relocation64 { r_addr: 0, r_symbol: f, r_type: RELOC_BRANCH26, r_addend: 0 }]);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+        let mut moved = OhlinkBuilder::from_file(&parsed);
+        moved.change_addresses(0x1000).expect("shift");
+        let moved = OhlinkFile::parse(&moved.build()).expect("parse");
+
+        // 重定位与符号随节移动，节内偏移不变
+        let (_, sec) = moved.sections()[0];
+        assert_eq!(sec.addr, 0x1000);
+        assert_eq!(moved.relocations(sec)[0].r_addr, sec.addr);
+        let (name, n) = &moved.symbols()[0];
+        assert_eq!((name.as_str(), n.n_sect, n.n_value), ("f", 1, 0x1004));
+
+        let mut overflow = OhlinkBuilder::from_file(&parsed);
+        assert_eq!(overflow.change_addresses(-1), Err("__TEXT".to_string()));
+    }
+
+    #[test]
+    fn symbol_extents_fall_back_to_next_symbol() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 16], 0);
+        b.add_symbol("f", 0, 0);
+        b.add_symbol("g", 4, 0);
+        let h = b.add_symbol("h", 10, 0);
+        b.add_undefined_symbol("ext", 0);
+        b.set_symbol_size(h, 2);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+        assert_eq!(parsed.symbol_extents(), vec![Some(4), Some(6), Some(2), None]);
+    }
+
+    #[test]
+    fn entry_point_and_protection() {
+        let mut b = OhlinkBuilder::new(MH_EXECUTE);
+        b.add_segment("__TEXT", 0x4000_0000)
+            .set_protection(VM_PROT_READ | VM_PROT_EXECUTE, VM_PROT_READ | VM_PROT_EXECUTE)
+            .add_section("__text", &[0; 8], 0);
+        b.set_entry_point(0x4000_0004);
+        let parsed = OhlinkFile::parse(&b.build()).expect("parse");
+        assert_eq!(parsed.header.ncmds, 4);
+        assert_eq!(parsed.entry_point(), Some(0x4000_0004));
+        let (seg, _) = parsed.sections()[0];
+        assert_eq!(seg.initprot, VM_PROT_READ | VM_PROT_EXECUTE);
+    }
+
+    #[test]
+    fn long_section_and_member_names() {
+        let long = "__text._ZN4core3fmt5write17h0123456789abcdefE";
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0)
+            .add_section("__text", &[0; 4], 0)
+            .add_section(long, &[0; 4], 4)
+            .add_section("/odd", &[0; 4], 8);
+        let bytes = b.build();
+        let parsed = OhlinkFile::parse(&bytes).expect("parse");
+        let (seg, secs) = parsed
+            .commands
+            .iter()
+            .find_map(|c| if let LoadCommand::Segment64(s, secs) = c { Some((*s, secs.clone())) } else { None })
+            .expect("segment");
+        assert_eq!(parsed.segment_name(&seg), "__TEXT");
+        let names: Vec<String> = secs.iter().map(|s| parsed.section_name(s)).collect();
+        assert_eq!(names, ["__text", long, "/odd"]);
+        assert_eq!(secs[1].sectname[0], b'/');
+
+        let member = "hnx_syscall_wrappers-8f3a2b.rcgu.o";
+        let mut lib = OhlibBuilder::new();
+        lib.add_member("a.ohlink", &bytes);
+        lib.add_member(member, &bytes);
+        let arch = OhlibArchive::parse(&lib.build()).expect("ohlib");
+        assert_eq!(arch.member(0).map(|m| m.0), Some("a.ohlink"));
+        assert_eq!(arch.member(1), Some((member, &bytes[..])));
+    }
+
+    #[test]
+    fn ohlib_symbol_index() {
+        let member = |def: &str, undef: &str| {
+            let mut b = OhlinkBuilder::new(MH_OBJECT);
+            b.add_segment("__TEXT", 0).add_section("__text", &[0; 4], 0);
+            b.add_symbol_with(def, 0, 0, N_SECT | N_EXT, 0);
+            b.add_symbol_with("local", 0, 0, N_SECT, 0);
+            b.add_undefined_symbol(undef, 0);
+            b.build()
+        };
+        let mut lib = OhlibBuilder::new();
+        lib.add_member("a.ohlink", &member("alpha", "beta"));
+        lib.add_member("b.ohlink", &member("beta", "gamma"));
+        let arch = OhlibArchive::parse(&lib.build()).expect("ohlib");
+        assert_eq!(arch.len(), 2);
+        assert_eq!(arch.member(0).map(|m| m.0), Some("a.ohlink"));
+        let expected = vec![("alpha".to_string(), 0), ("beta".to_string(), 1)];
+        assert_eq!(arch.index.as_deref(), Some(&expected[..]));
+        assert_eq!(arch.build_symbol_index(), expected);
+
+        let mut lib = OhlibBuilder::new();
+        lib.set_symbol_index(false);
+        lib.add_member("a.ohlink", &member("alpha", "beta"));
+        let arch = OhlibArchive::parse(&lib.build()).expect("ohlib");
+        assert!(arch.index.is_none());
+    }
+
+    #[test]
+    fn ohlib_edit_operations() {
+        let mut arch = OhlibArchive::new();
+        arch.append_member("a.ohlink", b"aaaa");
+        arch.append_member("b.ohlink", b"bb");
+        arch.insert_member(0, "c.ohlink", b"c");
+        assert!(arch.replace_member("b.ohlink", b"BBB"));
+        assert!(arch.move_member("c.ohlink", 2));
+        assert!(arch.remove_member("a.ohlink"));
+        assert!(!arch.remove_member("missing.ohlink"));
+        let members: Vec<(String, Vec<u8>)> = arch.members().into_iter().map(|(n, b)| (n, b.to_vec())).collect();
+        assert_eq!(members, [("b.ohlink".to_string(), b"BBB".to_vec()), ("c.ohlink".to_string(), b"c".to_vec())]);
+
+        let reparsed = OhlibArchive::parse(&arch.to_bytes(false)).expect("ohlib");
+        assert!(reparsed.index.is_none());
+        assert_eq!(reparsed.position("c.ohlink"), Some(1));
+        assert_eq!(OhlibArchive::parse(&arch.to_bytes(true)).expect("ohlib").index, Some(Vec::new()));
+    }
+
+    #[test]
+    fn weak_and_undefined_symbols() {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 8], 0);
+        b.add_symbol_with("dflt", 0, 0, N_SECT | N_EXT, N_WEAK_DEF);
+        b.add_undefined_symbol("hook", N_WEAK_REF);
+        b.add_undefined_symbol("strong", 0);
+        b.add_common_symbol("counter", 12, 8);
+
+        let bytes = b.build();
+        let parsed = OhlinkFile::parse(&bytes).expect("parse");
+        let sym = parsed
+            .commands
+            .iter()
+            .find_map(|c| if let LoadCommand::Symtab(s) = c { Some(*s) } else { None })
+            .expect("symtab");
+        let nsz = std::mem::size_of::<Nlist64>();
+        let syms: Vec<Nlist64> = (0..sym.nsyms as usize)
+            .map(|i| {
+                let off = sym.symoff as usize + i * nsz;
+                unsafe { std::ptr::read(bytes[off..off + nsz].as_ptr() as *const Nlist64) }
+            })
+            .collect();
+
+        assert!(syms[0].is_weak_def() && syms[0].is_external() && !syms[0].is_undefined());
+        assert!(syms[1].is_undefined() && syms[1].is_weak_ref());
+        assert!(syms[2].is_undefined() && !syms[2].is_weak_ref() && !syms[2].is_common());
+        assert!(syms[3].is_common());
+        assert_eq!((syms[3].n_value, syms[3].common_align()), (12, 8));
+    }
 }
 
 pub struct SegmentBuilder {
-    segname: [u8; 16],
+    segname: String,
     vmaddr: u64,
     maxprot: i32,
     initprot: i32,
@@ -690,17 +1940,14 @@ pub struct SegmentBuilder {
 
 impl SegmentBuilder {
     pub fn add_section(&mut self, name: &str, data: &[u8], addr: u64) -> &mut Self {
-        let mut sectname = [0; 16];
-        let bytes = name.as_bytes();
-        let len = bytes.len().min(15);
-        sectname[..len].copy_from_slice(&bytes[..len]);
-
         self.sections.push(SectionBuilder {
-            sectname,
+            sectname: name.to_string(),
             addr,
             size: data.len() as u64,
             data: data.to_vec(),
             align: 4,
+            flags: 0,
+            reserved1: 0,
             relocations: Vec::new(),
         });
 
@@ -708,25 +1955,44 @@ impl SegmentBuilder {
     }
 
     pub fn add_section_with(&mut self, name: &str, data: &[u8], addr: u64, align: u32, size: u64) -> &mut Self {
-        let mut sectname = [0; 16];
-        let bytes = name.as_bytes();
-        let len = bytes.len().min(15);
-        sectname[..len].copy_from_slice(&bytes[..len]);
-
         self.sections.push(SectionBuilder {
-            sectname,
+            sectname: name.to_string(),
             addr,
             size,
             data: data.to_vec(),
             align,
+            flags: 0,
+            reserved1: 0,
             relocations: Vec::new(),
         });
 
         self
     }
 
-    fn build(mut self, file_offset: &mut u64) -> (SegmentCommand64, Vec<Section64>, Vec<u8>) {
+    /// 节末尾相对段基址的最大偏移
+    pub fn end(&self) -> u64 {
+        self.sections.iter().map(|sec| sec.addr + sec.size).max().unwrap_or(0)
+    }
+
+    /// 设置段权限（VM_PROT_*），默认 VM_PROT_ALL
+    pub fn set_protection(&mut self, maxprot: i32, initprot: i32) -> &mut Self {
+        self.maxprot = maxprot;
+        self.initprot = initprot;
+        self
+    }
+
+    /// 设置最近添加的节的类型标志与 reserved1（如构造数组优先级）
+    pub fn set_section_flags(&mut self, flags: u32, reserved1: u32) -> &mut Self {
+        if let Some(sec) = self.sections.last_mut() {
+            sec.flags = flags;
+            sec.reserved1 = reserved1;
+        }
+        self
+    }
+
+    fn build(mut self, file_offset: &mut u64, names: &mut NameTableBuilder) -> (SegmentCommand64, Vec<Section64>, Vec<u8>) {
         let nsects = self.sections.len() as u32;
+        let segname = names.encode(&self.segname);
         let mut section_headers = Vec::new();
         let mut section_data = Vec::new();
 
@@ -771,16 +2037,16 @@ impl SegmentBuilder {
             }
 
             let section_header = Section64 {
-                sectname: section.sectname,
-                segname: self.segname,
+                sectname: names.encode(&section.sectname),
+                segname,
                 addr: self.vmaddr + section.addr,
                 size: section.size,
                 offset: offset_field,
                 align: section.align,
                 reloff: reloff_field,
                 nreloc: nreloc_field,
-                flags: 0,
-                reserved1: 0,
+                flags: section.flags,
+                reserved1: section.reserved1,
                 reserved2: 0,
                 reserved3: 0,
             };
@@ -793,7 +2059,7 @@ impl SegmentBuilder {
             cmd: LC_SEGMENT_64,
             cmdsize: (std::mem::size_of::<SegmentCommand64>()
                 + nsects as usize * std::mem::size_of::<Section64>()) as u32,
-            segname: self.segname,
+            segname,
             vmaddr: self.vmaddr,
             vmsize: vmend - self.vmaddr,
             fileoff,
@@ -809,11 +2075,13 @@ impl SegmentBuilder {
 }
 
 struct SectionBuilder {
-    sectname: [u8; 16],
+    sectname: String,
     addr: u64,
     size: u64,
     data: Vec<u8>,
     align: u32,
+    flags: u32,
+    reserved1: u32,
     relocations: Vec<Relocation64>,
 }
 
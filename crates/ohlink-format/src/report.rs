@@ -0,0 +1,183 @@
+// crates/ohlink-format/src/report.rs
+//! 供 `--format json` 使用的结构化视图（`serde` feature）。
+//!
+//! 原始结构（头部、加载命令、节头、nlist、重定位项）直接序列化其字段，
+//! 在此基础上补充解析后的名字，字段名与含义作为稳定的 schema 对外提供。
+use crate::*;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+/// schema 版本；删除或改变已有字段的含义时递增
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 字节串序列化为小写十六进制字符串
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl Serialize for Hex<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_str(&self.0.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+}
+
+pub(crate) fn serialize_hex<S: Serializer>(bytes: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    Hex(bytes).serialize(s)
+}
+
+/// 定长名字字段按文件中的原样（截至 NUL）输出，长名字为 "/NNN" 引用
+pub(crate) fn serialize_name<S: Serializer, const N: usize>(raw: &[u8; N], s: S) -> std::result::Result<S::Ok, S::Error> {
+    let end = raw.iter().position(|&c| c == 0).unwrap_or(N);
+    s.serialize_str(&String::from_utf8_lossy(&raw[..end]))
+}
+
+impl Serialize for LoadCommand {
+    fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut m = s.serialize_map(None)?;
+        match self {
+            LoadCommand::Segment64(seg, sections) => {
+                m.serialize_entry("kind", "segment64")?;
+                m.serialize_entry("segment", seg)?;
+                m.serialize_entry("sections", sections)?;
+            }
+            LoadCommand::Symtab(c) => {
+                m.serialize_entry("kind", "symtab")?;
+                m.serialize_entry("command", c)?;
+            }
+            LoadCommand::Unknown { cmd, cmdsize, data } => {
+                m.serialize_entry("kind", "unknown")?;
+                m.serialize_entry("cmd", cmd)?;
+                m.serialize_entry("cmdsize", cmdsize)?;
+                m.serialize_entry("data", &Hex(data))?;
+            }
+            LoadCommand::NoteAbi { abi_version, flags } => {
+                m.serialize_entry("kind", "note_abi")?;
+                m.serialize_entry("abi_version", abi_version)?;
+                m.serialize_entry("flags", flags)?;
+            }
+            LoadCommand::InitFuncs(c) => {
+                m.serialize_entry("kind", "init_funcs")?;
+                m.serialize_entry("command", c)?;
+            }
+            LoadCommand::NameTable(t) => {
+                m.serialize_entry("kind", "name_table")?;
+                m.serialize_entry("data", &Hex(t))?;
+            }
+            LoadCommand::EntryPoint(c) => {
+                m.serialize_entry("kind", "entry_point")?;
+                m.serialize_entry("command", c)?;
+            }
+            LoadCommand::SymbolSizes(sizes) => {
+                m.serialize_entry("kind", "symbol_sizes")?;
+                m.serialize_entry("sizes", sizes)?;
+            }
+        }
+        m.end()
+    }
+}
+
+/// 一个 Ohlink 文件的完整视图
+#[derive(Serialize)]
+pub struct FileReport<'a> {
+    pub header: &'a OhlinkHeader,
+    pub load_commands: &'a [LoadCommand],
+    pub sections: Vec<SectionReport>,
+    pub symbols: Vec<SymbolReport>,
+}
+
+/// 节头及其重定位；`index` 与 nlist 的 n_sect 相同（从 1 开始）
+#[derive(Serialize)]
+pub struct SectionReport {
+    pub index: usize,
+    pub segment: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub header: Section64,
+    pub relocations: Vec<RelocationReport>,
+}
+
+#[derive(Serialize)]
+pub struct RelocationReport {
+    #[serde(flatten)]
+    pub entry: Relocation64,
+    pub type_name: &'static str,
+    /// 目标符号名；节符号为所在节的名字
+    pub symbol: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolReport {
+    pub index: usize,
+    pub name: String,
+    /// undefined / absolute / section / common
+    pub kind: &'static str,
+    /// `段名,节名`，仅 kind 为 section 时存在
+    pub section: Option<String>,
+    pub external: bool,
+    pub weak: bool,
+    /// LC_SYMBOL_SIZES 记录的大小，没有大小表时为 null
+    pub size: Option<u64>,
+    #[serde(flatten)]
+    pub nlist: Nlist64,
+}
+
+impl OhlinkFile {
+    /// 构建 JSON 输出所用的视图
+    pub fn report(&self) -> FileReport<'_> {
+        let sections = self.sections();
+        let symbols = self.symbols();
+        let section_label = |n_sect: u8| {
+            sections
+                .get((n_sect as usize).wrapping_sub(1))
+                .map(|(seg, sec)| format!("{},{}", self.segment_name(seg), self.section_name(sec)))
+        };
+        let symbol_reports = symbols
+            .iter()
+            .enumerate()
+            .map(|(index, (name, n))| {
+                let kind = if n.is_common() {
+                    "common"
+                } else if n.is_undefined() {
+                    "undefined"
+                } else if n.is_absolute() {
+                    "absolute"
+                } else {
+                    "section"
+                };
+                SymbolReport {
+                    index,
+                    name: name.clone(),
+                    kind,
+                    section: if kind == "section" { section_label(n.n_sect) } else { None },
+                    external: n.is_external(),
+                    weak: n.is_weak_def() || n.is_weak_ref(),
+                    size: self.symbol_size(index),
+                    nlist: *n,
+                }
+            })
+            .collect();
+        let section_reports = sections
+            .iter()
+            .enumerate()
+            .map(|(i, (seg, sec))| SectionReport {
+                index: i + 1,
+                segment: self.segment_name(seg),
+                name: self.section_name(sec),
+                header: **sec,
+                relocations: self
+                    .relocations(sec)
+                    .into_iter()
+                    .map(|r| {
+                        let symbol = symbols.get(r.r_symbol as usize).map(|(name, n)| {
+                            if name.is_empty() {
+                                section_label(n.n_sect).unwrap_or_default()
+                            } else {
+                                name.clone()
+                            }
+                        });
+                        RelocationReport { entry: r, type_name: relocation_type_name(r.r_type), symbol }
+                    })
+                    .collect(),
+            })
+            .collect();
+        FileReport { header: &self.header, load_commands: &self.commands, sections: section_reports, symbols: symbol_reports }
+    }
+}
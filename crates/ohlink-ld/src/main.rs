@@ -1,11 +1,14 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use ohlink_format::*;
-use object::{Object, ObjectSection, ObjectSymbol};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::mem::size_of;
 
+/// 待合并的输入节：(节名, 数据, 对齐, 段内偏移, 文件索引, 旧节序号, 旧节头)
+type SectionItem = (String, Vec<u8>, u32, u64, usize, u8, Section64);
+
 /// 生成 FreeBSD 64 位风格四段布局
 fn default_bsd_layout(_args: &Args) -> OhlinkBuilder {
     let mut b = OhlinkBuilder::new(MH_EXECUTE);
@@ -13,8 +16,122 @@ fn default_bsd_layout(_args: &Args) -> OhlinkBuilder {
         .add_section_with("__pagezero", &[], 0x0, 0x1000, 0x1_0000_0000);
     b
 }
+/// 命令行上的输入及位置相关开关，保持出现顺序
+enum InputItem {
+    File(PathBuf),
+    WholeArchive(bool),
+    StartGroup,
+    EndGroup,
+}
+
+/// 已读取的输入
+enum LinkInput {
+    Object(PathBuf, Vec<u8>, OhlinkFile),
+    Archive(PathBuf, OhlibArchive),
+    WholeArchive(bool),
+    StartGroup,
+    EndGroup,
+}
+
+/// 参与按位置解析的库：符号索引与已引入的成员
+struct LibState {
+    path: PathBuf,
+    arch: OhlibArchive,
+    index: HashMap<String, usize>,
+    loaded: HashSet<usize>,
+}
+
+impl LibState {
+    fn new(path: PathBuf, arch: OhlibArchive) -> Self {
+        // 优先使用归档中的 __.SYMDEF，否则扫描成员生成；同名取第一个定义
+        let mut index = HashMap::new();
+        for (name, mi) in arch.index.clone().unwrap_or_else(|| arch.build_symbol_index()) {
+            index.entry(name).or_insert(mi as usize);
+        }
+        Self { path, arch, index, loaded: HashSet::new() }
+    }
+}
+
+/// 库成员选择的符号状态：已定义的符号，以及每个未定义符号首次被谁引用
+struct Resolver {
+    trace: bool,
+    demangle: bool,
+    defined: HashSet<String>,
+    undefined: BTreeMap<String, String>,
+}
+
+impl Resolver {
+    fn new(trace: bool, demangle: bool) -> Self {
+        Self { trace, demangle, defined: HashSet::new(), undefined: BTreeMap::new() }
+    }
+
+    /// 登记一个已引入文件的符号
+    fn add(&mut self, who: &str, file: &OhlinkFile) {
+        let syms = file.symbols();
+        // 弱引用不参与成员选择；局部符号不能满足其它文件的引用；公共符号视为定义
+        for (nm, it) in &syms {
+            if it.is_common() || (!it.is_undefined() && it.is_external()) {
+                self.undefined.remove(nm);
+                self.defined.insert(nm.clone());
+            }
+        }
+        for (nm, it) in syms {
+            if it.is_undefined() && !it.is_common() && !it.is_weak_ref() && !self.defined.contains(&nm) {
+                self.undefined.entry(nm).or_insert_with(|| who.to_string());
+            }
+        }
+    }
+
+    fn load_member(&mut self, lib: &mut LibState, mi: usize, reason: &str, out: &mut Vec<(PathBuf, Vec<u8>, OhlinkFile)>) -> Result<()> {
+        lib.loaded.insert(mi);
+        let (mname, bytes) = lib.arch.member(mi)
+            .map(|(name, bytes)| (name.to_string(), bytes.to_vec()))
+            .with_context(|| format!("symbol index of {:?} names missing member {}", lib.path, mi))?;
+        let file = OhlinkFile::parse(&bytes)
+            .with_context(|| format!("Failed to parse member {} in {:?}", mname, lib.path))?;
+        let mut pseudo = lib.path.clone();
+        pseudo.set_file_name(format!("{}({})", lib.path.file_name().unwrap().to_string_lossy(), mname));
+        if self.trace { println!("{}: {}", pseudo.display(), reason); }
+        self.add(&pseudo.display().to_string(), &file);
+        out.push((pseudo, bytes, file));
+        Ok(())
+    }
+
+    /// --whole-archive：按成员顺序引入全部成员
+    fn load_all(&mut self, lib: &mut LibState, out: &mut Vec<(PathBuf, Vec<u8>, OhlinkFile)>) -> Result<()> {
+        for mi in 0..lib.arch.len() {
+            self.load_member(lib, mi, "whole archive", out)?;
+        }
+        Ok(())
+    }
+
+    /// 反复扫描一个库，引入能解析当前未定义符号的成员，返回新引入的成员数
+    fn scan(&mut self, lib: &mut LibState, out: &mut Vec<(PathBuf, Vec<u8>, OhlinkFile)>) -> Result<usize> {
+        let mut count = 0;
+        loop {
+            let mut hits: Vec<(usize, String)> = self.undefined.keys()
+                .filter_map(|nm| lib.index.get(nm).map(|&mi| (mi, nm.clone())))
+                .filter(|(mi, _)| !lib.loaded.contains(mi))
+                .collect();
+            if hits.is_empty() { break; }
+            hits.sort();
+            hits.dedup_by_key(|h| h.0);
+            for (mi, sym) in hits {
+                // 前面引入的成员可能已经定义了它；剩下的引用留给下一轮
+                let Some(who) = self.undefined.get(&sym).cloned() else { continue };
+                self.load_member(lib, mi, &format!("loaded for `{}' referenced by {}", demangle::describe(&sym, self.demangle), who), out)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Link Ohlink object files into executable", long_about = None)]
+#[command(after_help = "Positional options (apply to the inputs that follow them):
+  --whole-archive / --no-whole-archive   Include every member of the following archives
+  --start-group / --end-group, -( / -)   Rescan the enclosed archives until no new member is loaded")]
 struct Args {
     /// Input Ohlink object files
     inputs: Vec<PathBuf>,
@@ -39,16 +156,55 @@ struct Args {
     #[arg(long, default_value_t = false)]
     library: bool,
 
-    /// Include all members from any .ohlib inputs (no selective resolution)
+    /// Print each input file and why each archive member was loaded
+    #[arg(short = 't', long, default_value_t = false)]
+    trace: bool,
+
+    /// Define an absolute symbol: --defsym name=expr (number, symbol, or sums/differences of them)
+    #[arg(long, value_name = "NAME=EXPR")]
+    defsym: Vec<String>,
+
+    /// Warn when common symbols are merged or overridden by a definition
+    #[arg(long, default_value_t = false)]
+    warn_common: bool,
+
+    /// Show symbol names in diagnostics as they are mangled (default: demangled, mangled in parentheses)
     #[arg(long, default_value_t = false)]
-    whole_archive: bool,
+    no_demangle: bool,
+
+    /// Write a link map (every input section and common symbol with its output address) to FILE; -Map FILE is accepted too
+    #[arg(long = "Map", value_name = "FILE")]
+    map: Option<PathBuf>,
+
+    /// Omit the symbol table (and debug sections) from the output
+    #[arg(short = 's', long, default_value_t = false)]
+    strip_all: bool,
+
+    /// Drop debug sections (__DWARF segment, __debug*) of the inputs
+    #[arg(short = 'S', long, default_value_t = false)]
+    strip_debug: bool,
+
+    /// Omit all local symbols from the output symbol table
+    #[arg(short = 'x', long, default_value_t = false)]
+    discard_all: bool,
+
+    /// Omit compiler-generated local labels (.L*) from the output symbol table
+    #[arg(short = 'X', long, default_value_t = false)]
+    discard_locals: bool,
 }
 
 fn main() -> Result<()> {
-    // 0. 捕获原始 argv，并解析常见 ld 开关（至少支持 -o 输出路径）
-    let raw_args: Vec<String> = std::env::args().collect();
+    link(std::env::args().collect())
+}
+
+/// 按命令行（含 argv[0]）完成一次链接
+fn link(raw_args: Vec<String>) -> Result<()> {
+    // 0. 解析常见 ld 开关（至少支持 -o 输出路径）
+    //    与位置相关的开关（--whole-archive、--start-group 等）按出现顺序记录在 layout 中
+    let known = known_flags();
     let mut override_out: Option<PathBuf> = None;
     let mut filtered: Vec<String> = Vec::new();
+    let mut layout: Vec<InputItem> = Vec::new();
     let mut i = 0;
     while i < raw_args.len() {
         let a = &raw_args[i];
@@ -57,11 +213,45 @@ fn main() -> Result<()> {
             i += 2;
             continue;
         }
+        // GNU ld 的单横线 -Map
+        if a == "-Map" || a.starts_with("-Map=") {
+            filtered.push(format!("-{}", a));
+            if a == "-Map" && i + 1 < raw_args.len() {
+                filtered.push(raw_args[i + 1].clone());
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        let positional = match a.as_str() {
+            "--whole-archive" => Some(InputItem::WholeArchive(true)),
+            "--no-whole-archive" => Some(InputItem::WholeArchive(false)),
+            "--start-group" | "-(" => Some(InputItem::StartGroup),
+            "--end-group" | "-)" => Some(InputItem::EndGroup),
+            _ => None,
+        };
+        if let Some(item) = positional {
+            layout.push(item);
+            i += 1;
+            continue;
+        }
+        // 本工具认识的开关原样交给 clap（带值的连同下一个参数）
+        if let Some(&takes_value) = known.get(a.split('=').next().unwrap_or(a)) {
+            filtered.push(a.clone());
+            if takes_value && !a.contains('=') && i + 1 < raw_args.len() {
+                filtered.push(raw_args[i + 1].clone());
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
         // 忽略其它以 '-' 开头的未知参数
         if a.starts_with('-') {
             i += 1;
             continue;
         }
+        // argv[0] 是程序名，不是输入
+        if i > 0 { layout.push(InputItem::File(PathBuf::from(a))); }
         filtered.push(a.clone());
         i += 1;
     }
@@ -71,16 +261,21 @@ fn main() -> Result<()> {
     
     if args.inputs.is_empty() { anyhow::bail!("no input files"); }
 
-    let mut inputs_data: Vec<(PathBuf, Vec<u8>, OhlinkFile)> = Vec::new();
-    let mut libraries: Vec<(PathBuf, OhlibArchive)> = Vec::new();
-
-    for p in &args.inputs {
+    // 2. 按命令行顺序读取输入；库保留在原位置，稍后按位置解析
+    let mut inputs: Vec<LinkInput> = Vec::new();
+    for item in layout {
+        let p = match item {
+            InputItem::File(p) => p,
+            InputItem::WholeArchive(on) => { inputs.push(LinkInput::WholeArchive(on)); continue; }
+            InputItem::StartGroup => { inputs.push(LinkInput::StartGroup); continue; }
+            InputItem::EndGroup => { inputs.push(LinkInput::EndGroup); continue; }
+        };
         // 0. 跳过目录
         if !p.is_file() {
             eprintln!("Skip non-file: {:?}", p);
             continue;
         }
-        let d = fs::read(p).with_context(|| format!("Failed to read file: {:?}", p))?;
+        let d = fs::read(&p).with_context(|| format!("Failed to read file: {:?}", p))?;
         if d.len() < 4 {
             eprintln!("Skip too-small file: {:?}", p);
             continue;
@@ -88,20 +283,28 @@ fn main() -> Result<()> {
         let magic: [u8; 4] = d[0..4].try_into().unwrap();
 
         // 1. 分流
-        if magic == OHLIB_MAGIC {
+        if ohlink_elf::is_ar_archive(&d) {
+            // GNU/BSD .a：成员按 ohlink-elf 的规则转换为 Ohlink 后当作 .ohlib 处理
+            let bytes = ohlink_elf::convert_ar_to_ohlib(&d, &Default::default())
+                .with_context(|| format!("Failed to convert archive: {:?}", p))?;
+            let arch = OhlibArchive::parse(&bytes)
+                .with_context(|| format!("Failed to parse converted archive: {:?}", p))?;
+            inputs.push(LinkInput::Archive(p, arch));
+        } else if magic == OHLIB_MAGIC {
             let arch = OhlibArchive::parse(&d)
                 .with_context(|| format!("Failed to parse Ohlib: {:?}", p))?;
-            libraries.push((p.clone(), arch));
+            inputs.push(LinkInput::Archive(p, arch));
         } else if magic == OHLINK_MAGIC || magic == OHLINK_MAGIC_64 {
             let file = OhlinkFile::parse(&d)
                 .with_context(|| format!("Failed to parse Ohlink file: {:?}", p))?;
-            inputs_data.push((p.clone(), d, file));
+            inputs.push(LinkInput::Object(p, d, file));
         } else {
             match object::File::parse(&*d) {
                 Ok(elf) => {
-                    let bytes = convert_elf_to_ohlink(&elf)?;
+                    let bytes = ohlink_elf::convert_elf_to_ohlink(&elf, &Default::default())
+                        .with_context(|| format!("Failed to convert ELF: {:?}", p))?;
                     let file = OhlinkFile::parse(&bytes)?;
-                    inputs_data.push((p.clone(), bytes, file));
+                    inputs.push(LinkInput::Object(p, bytes, file));
                 }
                 Err(_) => {
                     eprintln!("Skip unknown format: {:?} (magic {:02x?})", p, magic);
@@ -119,108 +322,59 @@ fn main() -> Result<()> {
                         commands: Vec::new(),
                         data: Vec::new(),
                     };
-                    inputs_data.push((p.clone(), Vec::new(), empty));
+                    inputs.push(LinkInput::Object(p, Vec::new(), empty));
                     continue;
                 }
             }
         }
     }
-    // Expand libraries: either whole-archive, or selective member inclusion by unresolved symbols
-    if !libraries.is_empty() && !args.library {
-        if args.whole_archive || inputs_data.is_empty() {
-            // Include all members when requested, or when no base objects were provided
-            for (lp, arch) in &libraries {
-                for e in &arch.entries {
-                    let name = String::from_utf8_lossy(&e.name).trim_end_matches('\0').to_string();
-                    let start = e.offset as usize;
-                    let end = start + e.size as usize;
-                    if end > arch.data.len() { anyhow::bail!("ohlib member out of bounds: {:?}:{}", lp, name); }
-                    let bytes = arch.data[start..end].to_vec();
-                    let file = OhlinkFile::parse(&bytes).with_context(|| format!("Failed to parse member {} in {:?}", name, lp))?;
-                    let mut pseudo = lp.clone();
-                    pseudo.set_file_name(format!("{}({})", lp.file_name().unwrap().to_string_lossy(), name));
-                    inputs_data.push((pseudo, bytes, file));
-                }
-            }
-        } else {
-            use std::collections::{HashSet, HashMap};
-            let mut defined: HashSet<String> = HashSet::new();
-            let mut undefined: HashSet<String> = HashSet::new();
-            // Seed from existing object inputs
-            for (_p, d, f) in &inputs_data {
-                let mut symtab: Option<SymtabCommand> = None;
-                for cmd in &f.commands { if let LoadCommand::Symtab(s) = cmd { symtab = Some(*s); } }
-        if let Some(sym) = symtab {
-            let nsz = size_of::<Nlist64>();
-            let mut entries = Vec::new();
-            for i in 0..(sym.nsyms as usize) {
-                let s = (sym.symoff as usize) + i * nsz; let e = s + nsz; if s >= d.len() || e > d.len() { break; }
-                let item: Nlist64 = unsafe { std::ptr::read(d[s..e].as_ptr() as *const _) };
-                entries.push(item);
-            }
-            let st = if (sym.stroff as usize) < d.len() {
-                let s = sym.stroff as usize; let e = (s + sym.strsize as usize).min(d.len());
-                d[s..e].to_vec()
-            } else { Vec::new() };
-            for it in entries {
-                let name = read_cstr(&st, it.n_strx as usize);
-                if it.n_sect != 0 { defined.insert(name); } else { undefined.insert(name); }
-            }
+
+    // 3. 按位置解析：库只满足出现在它之前的引用；组内的库反复扫描直到不再引入新成员
+    let mut inputs_data: Vec<(PathBuf, Vec<u8>, OhlinkFile)> = Vec::new();
+    if args.library {
+        // 库模式只打包目标文件，输入中的库被忽略
+        for input in inputs {
+            if let LinkInput::Object(p, d, f) = input { inputs_data.push((p, d, f)); }
         }
-            }
-            if let Some(entry) = &args.entry { if !defined.contains(entry) { undefined.insert(entry.clone()); } }
-
-            // Prepare candidates from libraries
-            struct Candidate { name: String, path: PathBuf, bytes: Vec<u8>, file: OhlinkFile, defs: HashSet<String>, undefs: HashSet<String> }
-            let mut candidates: Vec<Candidate> = Vec::new();
-            for (lp, arch) in &libraries {
-                for e in &arch.entries {
-                    let mname = String::from_utf8_lossy(&e.name).trim_end_matches('\0').to_string();
-                    let start = e.offset as usize; let end = start + e.size as usize; if end > arch.data.len() { continue; }
-                    let bytes = arch.data[start..end].to_vec();
-                    let file = match OhlinkFile::parse(&bytes) { Ok(f) => f, Err(_) => continue };
-                    let mut defs = HashSet::new();
-                    let mut undefs = HashSet::new();
-                    let mut symtab: Option<SymtabCommand> = None;
-                    for cmd in &file.commands { if let LoadCommand::Symtab(s) = cmd { symtab = Some(*s); } }
-                    if let Some(sym) = symtab {
-                        let nsz = size_of::<Nlist64>();
-                        let mut entries = Vec::new();
-                        for i in 0..(sym.nsyms as usize) {
-                            let s = (sym.symoff as usize) + i * nsz; let e = s + nsz; if s >= bytes.len() || e > bytes.len() { break; }
-                            let item: Nlist64 = unsafe { std::ptr::read(bytes[s..e].as_ptr() as *const _) };
-                            entries.push(item);
-                        }
-                        let st = if (sym.stroff as usize) < bytes.len() {
-                            let s = sym.stroff as usize; let e = (s + sym.strsize as usize).min(bytes.len());
-                            bytes[s..e].to_vec()
-                        } else { Vec::new() };
-                        for it in entries { let nm = read_cstr(&st, it.n_strx as usize); if it.n_sect != 0 { defs.insert(nm); } else { undefs.insert(nm); } }
-                    }
-                    let mut pseudo = lp.clone(); pseudo.set_file_name(format!("{}({})", lp.file_name().unwrap().to_string_lossy(), mname));
-                    candidates.push(Candidate { name: mname, path: pseudo, bytes, file, defs, undefs });
+    } else {
+        // 没有任何目标文件时，引入库的全部成员
+        let no_objects = !inputs.iter().any(|it| matches!(it, LinkInput::Object(..)));
+        let mut resolver = Resolver::new(args.trace, !args.no_demangle);
+        if let Some(entry) = &args.entry { resolver.undefined.insert(entry.clone(), "--entry".to_string()); }
+        let mut whole = false;
+        let mut group: Option<Vec<LibState>> = None;
+        for input in inputs {
+            match input {
+                LinkInput::Object(p, d, f) => {
+                    if args.trace { println!("{}", p.display()); }
+                    resolver.add(&p.display().to_string(), &f);
+                    inputs_data.push((p, d, f));
                 }
-            }
-
-            let mut progress = true;
-            while progress {
-                progress = false;
-                let mut i = 0;
-                while i < candidates.len() {
-                    let hit = !candidates[i].defs.is_disjoint(&undefined);
-                    if hit {
-                        // select this candidate
-                        let cand = candidates.remove(i);
-                        for nm in &cand.defs { undefined.remove(nm); defined.insert(nm.clone()); }
-                        for nm in &cand.undefs { if !defined.contains(nm) { undefined.insert(nm.clone()); } }
-                        inputs_data.push((cand.path, cand.bytes, cand.file));
-                        progress = true;
+                LinkInput::Archive(p, arch) => {
+                    let mut lib = LibState::new(p, arch);
+                    if whole || no_objects {
+                        resolver.load_all(&mut lib, &mut inputs_data)?;
                     } else {
-                        i += 1;
+                        resolver.scan(&mut lib, &mut inputs_data)?;
+                        if let Some(g) = group.as_mut() { g.push(lib); }
+                    }
+                }
+                LinkInput::WholeArchive(on) => whole = on,
+                LinkInput::StartGroup => {
+                    if group.is_some() { anyhow::bail!("nested --start-group"); }
+                    group = Some(Vec::new());
+                }
+                LinkInput::EndGroup => {
+                    let Some(mut libs) = group.take() else { anyhow::bail!("--end-group without --start-group") };
+                    loop {
+                        let mut progress = 0;
+                        for lib in libs.iter_mut() { progress += resolver.scan(lib, &mut inputs_data)?; }
+                        if progress == 0 { break; }
                     }
                 }
             }
         }
+        if group.is_some() { anyhow::bail!("--start-group without --end-group"); }
     }
 
     // Library mode: package input objects into a .ohlib archive
@@ -234,7 +388,7 @@ fn main() -> Result<()> {
             lib.add_member(&name, d);
         }
         let bytes = lib.build();
-        let out = args.output.clone().unwrap_or_else(|| {
+        let out = override_out.clone().or(args.output.clone()).unwrap_or_else(|| {
             let mut p = args.inputs[0].clone();
             p.set_extension("ohlib");
             p
@@ -246,8 +400,8 @@ fn main() -> Result<()> {
 
     let mut b = default_bsd_layout(&args);
 
-    let mut text_items: Vec<(String, Vec<u8>, u32, u64, usize, u8, Section64)> = Vec::new();
-    let mut data_items: Vec<(String, Vec<u8>, u32, u64, usize, u8, Section64)> = Vec::new();
+    let mut text_items: Vec<SectionItem> = Vec::new();
+    let mut data_items: Vec<SectionItem> = Vec::new();
     let mut sec_map: Vec<(usize, u8, u64)> = Vec::new(); // (file_idx, old_section_index, new_abs_base)
     let mut ord_map: Vec<(usize, u8, u8)> = Vec::new();  // (file_idx, old_section_index, new_ord)
     let mut text_off: u64 = 0;
@@ -279,15 +433,16 @@ fn main() -> Result<()> {
         }
     }
 
-    // 合并节并应用重定位（生成待添加项）
+    // 合并节（生成待添加项），重定位在符号解析之后再应用
+    let mut pending: Vec<(bool, SectionItem)> = Vec::new();
     for (fi, (_p, d, f)) in inputs_data.iter().enumerate() {
-        let mut old_sec_index: u8 = 0;
+        let mut old_sec_count = 0;
         for cmd in &f.commands {
             if let LoadCommand::Segment64(_seg, secs) = cmd {
                 for sec in secs {
-                    let segname = String::from_utf8_lossy(&sec.segname).trim_end_matches('\0').to_string();
-                    let name = String::from_utf8_lossy(&sec.sectname).trim_end_matches('\0').to_string();
-                    let mut data_slice = if sec.offset != 0 && sec.size > 0 {
+                    let segname = resolve_name(&sec.segname, f.name_table());
+                    let name = f.section_name(sec);
+                    let data_slice = if sec.offset != 0 && sec.size > 0 {
                         let start = sec.offset as usize;
                         let end = start + sec.size as usize;
                         if start >= d.len() { Vec::new() } else {
@@ -296,96 +451,303 @@ fn main() -> Result<()> {
                         }
                     } else { Vec::new() };
 
-                    let (base_vmaddr, cur_off, is_data) = if segname == "__DATA" { (args.data_base, &mut data_off, true) } else { (args.text_base, &mut text_off, false) };
-                    let align = sec.align as u64;
-                    if align > 0 { *cur_off = align_up(*cur_off, align); }
-                    let new_rel = *cur_off;
-                    let new_abs = base_vmaddr + new_rel;
+                    let old_sec_index = section_ord(old_sec_count)?;
+                    old_sec_count += 1;
+                    if (args.strip_all || args.strip_debug) && is_debug_section(&segname, &name) {
+                        continue;
+                    }
+                    let name = output_section_name(&name).to_string();
+                    pending.push((segname == "__DATA", (name, data_slice, sec.align, 0, fi, old_sec_index, *sec)));
+                }
+            }
+        }
+    }
+
+    // 同名节按名字首次出现的顺序排在一起，使 __start_/__stop_ 覆盖连续区间
+    let mut name_order: Vec<String> = Vec::new();
+    for (_, item) in &pending {
+        if !name_order.contains(&item.0) { name_order.push(item.0.clone()); }
+    }
+    // 零填充节排在段尾，使文件内容保持连续；构造/析构数组在组内再按优先级（reserved1）排序
+    pending.sort_by_key(|(_, item)| {
+        let section_type = item.6.flags & SECTION_TYPE;
+        let is_init_array = matches!(section_type, S_PREINIT_FUNC_POINTERS | S_INIT_FUNC_POINTERS | S_TERM_FUNC_POINTERS);
+        (section_type == S_ZEROFILL, name_order.iter().position(|n| *n == item.0), if is_init_array { item.6.reserved1 } else { 0 })
+    });
+    for (is_data, mut item) in pending {
+        let (base_vmaddr, cur_off) = if is_data { (args.data_base, &mut data_off) } else { (args.text_base, &mut text_off) };
+        let align = item.6.align as u64;
+        if align > 0 { *cur_off = align_up(*cur_off, align); }
+        item.3 = *cur_off;
+        sec_map.push((item.4, item.5, base_vmaddr + item.3));
+        *cur_off += item.6.size;
+        if is_data { data_items.push(item); } else { text_items.push(item); }
+    }
+
+    // 定义符号的新地址：旧节内偏移 + 新节基址
+    let relocated = |fi: usize, e: &Nlist64| -> Option<u64> {
+        if e.is_absolute() { return Some(e.n_value); }
+        let old_si = e.n_sect.saturating_sub(1);
+        let (_, _, base) = sec_map.iter().find(|(f, s, _)| *f == fi && *s == old_si).cloned()?;
+        let old_sec = text_items.iter().chain(data_items.iter()).find(|(_, _, _, _, f, s, _)| *f == fi && *s == old_si).map(|(_, _, _, _, _, _, sec)| *sec)?;
+        let offset = e.n_value as i128 - old_sec.addr as i128;
+        Some((base as i128 + offset) as u64)
+    };
+
+    // 全局符号解析：强定义覆盖弱定义，多个强定义报错，多个弱定义取第一个
+    // name -> (value, is_weak, file_idx, sym_idx)
+    let mut global_defs: HashMap<String, (u64, bool, usize, usize)> = HashMap::new();
+    for (fi, entries, st) in &all_symbols {
+        for (si, e) in entries.iter().enumerate() {
+            if e.is_undefined() || !e.is_external() { continue; }
+            let Some(new_val) = relocated(*fi, e) else { continue };
+            let name = read_cstr(st, e.n_strx as usize);
+            let weak = e.is_weak_def();
+            match global_defs.get(&name) {
+                None => { global_defs.insert(name, (new_val, weak, *fi, si)); }
+                Some(&(_, true, _, _)) if !weak => { global_defs.insert(name, (new_val, weak, *fi, si)); }
+                Some(&(_, false, other, _)) if !weak => {
+                    anyhow::bail!("duplicate symbol: {} (defined in {:?} and {:?})", demangle::describe(&name, !args.no_demangle), inputs_data[other].0, inputs_data[*fi].0);
+                }
+                Some(_) => {}
+            }
+        }
+    }
 
-                    // 应用重定位：使用旧节地址计算偏移，使用新地址作为 place
-                    if sec.nreloc > 0 {
-                        apply_relocations_with_base(&mut data_slice, sec, new_abs, d, &all_symbols[fi].1)?;
+    // 公共符号：同名取最大的大小与对齐，已有真正定义时并入该定义；其余在 __DATA,__common 中分配
+    let mut commons: Vec<(String, u64, u64, usize, usize)> = Vec::new(); // (name, size, align, file_idx, sym_idx)
+    let mut common_index: HashMap<String, usize> = HashMap::new();
+    for (fi, entries, st) in &all_symbols {
+        for (si, e) in entries.iter().enumerate() {
+            if !e.is_common() { continue; }
+            let name = read_cstr(st, e.n_strx as usize);
+            if let Some(d) = global_defs.get(&name) {
+                if args.warn_common {
+                    eprintln!("warning: common of `{}' in {:?} overridden by definition in {:?}", demangle::describe(&name, !args.no_demangle), inputs_data[*fi].0, inputs_data[d.2].0);
+                }
+                continue;
+            }
+            match common_index.get(&name) {
+                Some(&ci) => {
+                    let c = &mut commons[ci];
+                    if args.warn_common {
+                        eprintln!("warning: multiple common of `{}' ({:?} size {}, {:?} size {})", demangle::describe(&name, !args.no_demangle), inputs_data[c.3].0, c.1, inputs_data[*fi].0, e.n_value);
                     }
+                    c.1 = c.1.max(e.n_value);
+                    c.2 = c.2.max(e.common_align());
+                }
+                None => {
+                    common_index.insert(name.clone(), commons.len());
+                    commons.push((name, e.n_value, e.common_align(), *fi, si));
+                }
+            }
+        }
+    }
+    let common_align = commons.iter().map(|c| c.2).max().unwrap_or(1);
+    let common_rel = align_up(data_off, common_align);
+    let mut common_size: u64 = 0;
+    let mut common_addrs: Vec<u64> = Vec::with_capacity(commons.len()); // 与 commons 一一对应的分配地址
+    for (name, size, align, fi, si) in &commons {
+        let off = align_up(common_size, *align);
+        common_addrs.push(args.data_base + common_rel + off);
+        global_defs.insert(name.clone(), (args.data_base + common_rel + off, false, *fi, *si));
+        common_size = off + size;
+    }
 
-                    if is_data {
-                        data_items.push((name, data_slice, sec.align, new_rel, fi, old_sec_index, *sec));
-                    } else {
-                        text_items.push((name, data_slice, sec.align, new_rel, fi, old_sec_index, *sec));
+    // 链接器定义的边界符号：仅在被引用且输入中没有定义时按最终布局合成
+    let text_end = args.text_base + text_off;
+    let data_end = args.data_base + data_off;
+    let image_end = if commons.is_empty() { data_end } else { args.data_base + common_rel + common_size };
+    let bss_start = data_items.iter()
+        .filter(|it| it.1.is_empty() && it.6.size > 0)
+        .map(|it| args.data_base + it.3)
+        .chain((!commons.is_empty()).then_some(args.data_base + common_rel))
+        .min()
+        .unwrap_or(data_end);
+    let section_bounds = |sect: &str| -> Option<(u64, u64)> {
+        text_items.iter().map(|it| (args.text_base, it))
+            .chain(data_items.iter().map(|it| (args.data_base, it)))
+            .filter(|(_, it)| it.0 == sect)
+            .map(|(base, it)| (base + it.3, base + it.3 + it.6.size))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+    };
+    // LC_INIT_FUNCS：各函数指针数组的 (地址, 字节数)
+    let array_range = |sect: &str| section_bounds(sect).map_or((0, 0), |(start, end)| (start, end - start));
+    let init_funcs = [array_range("__preinit_array"), array_range("__init_array"), array_range("__fini_array")];
+    let boundary_symbol = |name: &str| -> Option<u64> {
+        match name {
+            "__text_start" => Some(args.text_base),
+            "__etext" | "_etext" => Some(text_end),
+            "__data_start" => Some(args.data_base),
+            "__bss_start" => Some(bss_start),
+            "_end" => Some(image_end),
+            "__preinit_array_start" => section_bounds("__preinit_array").map(|b| b.0),
+            "__preinit_array_end" => section_bounds("__preinit_array").map(|b| b.1),
+            "__init_array_start" => section_bounds("__init_array").map(|b| b.0),
+            "__init_array_end" => section_bounds("__init_array").map(|b| b.1),
+            "__fini_array_start" => section_bounds("__fini_array").map(|b| b.0),
+            "__fini_array_end" => section_bounds("__fini_array").map(|b| b.1),
+            _ => {
+                if let Some(sect) = name.strip_prefix("__start_") {
+                    section_bounds(sect).map(|b| b.0)
+                } else if let Some(sect) = name.strip_prefix("__stop_") {
+                    section_bounds(sect).map(|b| b.1)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+    let mut synthetic: Vec<(String, u64)> = Vec::new();
+    for (_, entries, st) in &all_symbols {
+        for e in entries {
+            if !e.is_undefined() || e.is_common() { continue; }
+            let name = read_cstr(st, e.n_strx as usize);
+            if global_defs.contains_key(&name) || synthetic.iter().any(|(n, _)| *n == name) { continue; }
+            if let Some(v) = boundary_symbol(&name) { synthetic.push((name, v)); }
+        }
+    }
+    // --defsym name=expr：定义绝对符号，优先于输入文件中的同名定义
+    for spec in &args.defsym {
+        let (name, expr) = spec.split_once('=').with_context(|| format!("invalid --defsym: {}", spec))?;
+        let value = eval_symbol_expr(expr, |sym| {
+            synthetic.iter().rev().find(|(n, _)| n == sym).map(|(_, v)| *v)
+                .or_else(|| global_defs.get(sym).map(|d| d.0))
+                .or_else(|| boundary_symbol(sym))
+        }).with_context(|| format!("invalid --defsym: {}", spec))?;
+        synthetic.retain(|(n, _)| n != name);
+        synthetic.push((name.to_string(), value));
+    }
+    for (name, value) in &synthetic {
+        global_defs.insert(name.clone(), (*value, false, usize::MAX, usize::MAX));
+    }
+
+    // 每个输入文件内各符号的最终取值（供重定位使用）
+    let mut sym_values: Vec<Vec<u64>> = Vec::with_capacity(all_symbols.len());
+    let mut unresolved: Vec<String> = Vec::new();
+    for (fi, entries, st) in &all_symbols {
+        let mut values = Vec::with_capacity(entries.len());
+        for e in entries {
+            let val = if !e.is_undefined() {
+                if e.is_external() {
+                    // 被强定义覆盖的弱定义也绑定到最终定义
+                    let name = read_cstr(st, e.n_strx as usize);
+                    global_defs.get(&name).map(|d| d.0).or_else(|| relocated(*fi, e)).unwrap_or(0)
+                } else {
+                    relocated(*fi, e).unwrap_or(0)
+                }
+            } else {
+                let name = read_cstr(st, e.n_strx as usize);
+                match global_defs.get(&name) {
+                    Some(d) => d.0,
+                    None => {
+                        // 未解析的弱引用取 0
+                        if !e.is_weak_ref() && !unresolved.contains(&name) { unresolved.push(name); }
+                        0
                     }
-                    sec_map.push((fi, old_sec_index, new_abs));
-                    *cur_off += sec.size;
-                    old_sec_index = old_sec_index.wrapping_add(1);
                 }
+            };
+            values.push(val);
+        }
+        sym_values.push(values);
+    }
+    if !unresolved.is_empty() {
+        let names: Vec<String> = unresolved.iter().map(|n| demangle::describe(n, !args.no_demangle)).collect();
+        anyhow::bail!("undefined symbols: {}", names.join(", "));
+    }
+
+    // 应用重定位：使用旧节地址计算偏移，使用新地址作为 place
+    for (items, base_vmaddr) in [(&mut text_items, args.text_base), (&mut data_items, args.data_base)] {
+        for (_, data_slice, _, rel, fi, _, sec) in items.iter_mut() {
+            if sec.nreloc > 0 {
+                apply_relocations_with_base(data_slice, sec, base_vmaddr + *rel, &inputs_data[*fi].1, &sym_values[*fi])?;
             }
         }
     }
 
-    // 添加段与节，生成 ord 映射
+    // 添加段与节，生成 ord 映射；ord 从布局中已有的节（__pagezero）之后开始
+    let first_ord = b.section_count();
     {
         let text_seg = b.add_segment("__TEXT", args.text_base);
-        for (name, data_slice, align, rel, fi, si, _old) in &text_items {
-            text_seg.add_section_with(name, data_slice, *rel, *align, data_slice.len() as u64);
-            let ord = ord_map.len() as u8;
+        for (name, data_slice, align, rel, fi, si, old) in &text_items {
+            text_seg.add_section_with(name, data_slice, *rel, *align, old.size);
+            let ord = section_ord(first_ord + ord_map.len())?;
             ord_map.push((*fi, *si, ord));
         }
     }
     {
         let data_seg = b.add_segment("__DATA", args.data_base);
-        for (name, data_slice, align, rel, fi, si, _old) in &data_items {
-            data_seg.add_section_with(name, data_slice, *rel, *align, data_slice.len() as u64);
-            let ord = ord_map.len() as u8;
+        for (name, data_slice, align, rel, fi, si, old) in &data_items {
+            data_seg.add_section_with(name, data_slice, *rel, *align, old.size);
+            if old.flags != 0 || old.reserved1 != 0 {
+                data_seg.set_section_flags(old.flags, old.reserved1);
+            }
+            let ord = section_ord(first_ord + ord_map.len())?;
             ord_map.push((*fi, *si, ord));
         }
+        if !commons.is_empty() {
+            data_seg.add_section_with("__common", &[], common_rel, common_align as u32, common_size);
+            data_seg.set_section_flags(S_ZEROFILL, 0);
+        }
+    }
+    let common_ord = if commons.is_empty() { NO_SECT } else { section_ord(first_ord + ord_map.len())? };
+    if init_funcs.iter().any(|r| r.1 > 0) {
+        b.set_init_funcs(init_funcs[0], init_funcs[1], init_funcs[2]);
     }
 
-    // 全局符号解析与重建符号表
-    // 建立名称到地址映射以解析未定义符号
-    let mut global_defs: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
-    for (fi, entries, st) in &all_symbols {
-        // 先记录定义符号的地址
-        for e in entries {
+    // 重建输出符号表：外部符号只保留最终胜出的定义，已解析的引用不再输出；
+    // 大小取符号在输出中的范围：输入节原样放入各自的输出节，因此等于输入中的范围
+    // （LC_SYMBOL_SIZES，没有时到同一节中下一个符号或节末），公共符号取合并后的大小。-s 时不输出符号表
+    let extents: Vec<Vec<Option<u64>>> = inputs_data.iter().map(|(_, _, f)| f.symbol_extents()).collect();
+    for (fi, entries, st) in all_symbols.iter().filter(|_| !args.strip_all) {
+        for (si, e) in entries.iter().enumerate() {
             let name = read_cstr(st, e.n_strx as usize);
-            if e.n_sect != 0 {
-                // 找到该符号所在节的新基址
-                let old_si = e.n_sect.saturating_sub(1);
-                if let Some((_, _, base)) = sec_map.iter().find(|(f, s, _)| *f == *fi && *s as u8 == old_si).cloned() {
-                    // 计算符号相对旧节的偏移
-                    let old_sec = text_items.iter().chain(data_items.iter()).find(|(_, _, _, _, f, s, _)| *f == *fi && *s == old_si).map(|(_, _, _, _, _, _, sec)| *sec);
-                    if let Some(sec_hdr) = old_sec {
-                        let offset = (e.n_value as i128 - sec_hdr.addr as i128) as i128;
-                        let new_val = (base as i128 + offset) as u64;
-                        global_defs.insert(name.clone(), new_val);
+            if e.is_common() {
+                if let Some(d) = global_defs.get(&name).filter(|d| (d.2, d.3) == (*fi, si)) {
+                    let idx = b.add_symbol_with(&name, d.0, common_ord, N_SECT | N_EXT, 0);
+                    if let Some(&ci) = common_index.get(&name) {
+                        b.set_symbol_size(idx, commons[ci].1);
                     }
                 }
+                continue;
+            }
+            if e.is_undefined() {
+                if !global_defs.contains_key(&name) {
+                    b.add_undefined_symbol(&name, e.n_desc);
+                }
+                continue;
             }
+            if e.is_external() && global_defs.get(&name).is_some_and(|d| (d.2, d.3) != (*fi, si)) {
+                continue;
+            }
+            if e.is_absolute() {
+                if e.is_external() { b.add_absolute_symbol(&name, e.n_value); }
+                continue;
+            }
+            // 节符号（无名局部符号）只用于重定位，不进入输出符号表
+            if name.is_empty() {
+                continue;
+            }
+            if !e.is_external() && (args.discard_all || args.discard_locals && is_local_label(&name)) {
+                continue;
+            }
+            // 所在节被丢弃（-S）的符号不输出
+            let old_si = e.n_sect.saturating_sub(1);
+            let Some(sect_ord) = ord_map.iter().find(|(f, s, _)| *f == *fi && *s == old_si).map(|(_, _, o)| *o) else { continue };
+            let idx = b.add_symbol_with(&name, sym_values[*fi][si], sect_ord, e.n_type, e.n_desc);
+            b.set_symbol_size(idx, extents[*fi][si].unwrap_or(0));
         }
     }
-
-    // 将所有符号写入输出符号表（未定义符号若可解析则赋值，否则报错）
-    for (fi, entries, st) in &all_symbols {
-        for e in entries {
-            let name = read_cstr(st, e.n_strx as usize);
-            let (new_val, sect_ord) = if e.n_sect != 0 {
-                let old_si = e.n_sect.saturating_sub(1);
-                if let Some((_, _, base)) = sec_map.iter().find(|(f, s, _)| *f == *fi && *s as u8 == old_si).cloned() {
-                    let old_sec = text_items.iter().chain(data_items.iter()).find(|(_, _, _, _, f, s, _)| *f == *fi && *s == old_si).map(|(_, _, _, _, _, _, sec)| *sec).unwrap();
-                    let offset = (e.n_value as i128 - old_sec.addr as i128) as i128;
-                    let val = (base as i128 + offset) as u64;
-                    let ord = ord_map.iter().find(|(f, s, _)| *f == *fi && *s as u8 == old_si).map(|(_, _, o)| *o).unwrap_or(0);
-                    (val, ord)
-                } else { (0, 0) }
-            } else {
-                // 未定义：尝试用 global_defs 解析
-                let val = *global_defs.get(&name).unwrap_or(&0);
-                (val, 0)
-            };
-            b.add_symbol_with(&name, new_val, sect_ord, e.n_type, e.n_desc);
-        }
+    for (name, value) in synthetic.iter().filter(|_| !args.strip_all) {
+        b.add_absolute_symbol(name, *value);
     }
     // 默认入口
     let entry_sym = args.entry.unwrap_or_else(|| "_start".to_string());
-    let entry_val = *global_defs.get(&entry_sym).unwrap_or(&0);
+    let entry_val = global_defs.get(&entry_sym).map(|d| d.0).unwrap_or(0);
     println!("Entry {} at {:#x}", entry_sym, entry_val);
+    if global_defs.contains_key(&entry_sym) {
+        b.set_entry_point(entry_val);
+    }
 
     let bytes = b.build();
     let out = override_out
@@ -396,111 +758,94 @@ fn main() -> Result<()> {
             p
         });
     fs::write(&out, &bytes).with_context(|| format!("Failed to write output: {:?}", out))?;
+    if let Some(map) = &args.map {
+        let text = link_map(&out, &inputs_data, [(args.text_base, &text_items), (args.data_base, &data_items)], &commons, &common_addrs);
+        fs::write(map, text).with_context(|| format!("Failed to write link map: {:?}", map))?;
+    }
     println!("Linked: {} inputs -> {:?} ({} bytes)", args.inputs.len(), out, bytes.len());
     Ok(())
 }
 
-fn read_cstr(buf: &[u8], off: usize) -> String {
-    if off >= buf.len() { return String::new(); }
-    let mut end = off;
-    while end < buf.len() && buf[end] != 0 { end += 1; }
-    String::from_utf8_lossy(&buf[off..end]).to_string()
-}
-
-fn convert_elf_to_ohlink(elf: &object::File) -> Result<Vec<u8>> {
-    use std::collections::HashMap;
-    let mut builder = OhlinkBuilder::new(MH_OBJECT);
-
-    let mut text_additions: Vec<(&'static str, Vec<u8>, u64, usize)> = Vec::new();
-    let mut data_additions: Vec<(&'static str, Vec<u8>, u64, usize)> = Vec::new();
-
-    for (elf_section_idx, section) in elf.sections().enumerate() {
-        if let Ok(name) = section.name() {
-            let is_text   = name.starts_with(".text");
-            let is_rodata = name.starts_with(".rodata");
-            let is_data   = name.starts_with(".data");
-            let is_bss    = name.starts_with(".bss");
-            if is_text {
-                if let Ok(data) = section.data() { if !data.is_empty() { text_additions.push(("__text", data.to_vec(), section.address(), elf_section_idx)); } }
-            } else if is_rodata {
-                if let Ok(data) = section.data() { if !data.is_empty() { text_additions.push(("__rodata", data.to_vec(), section.address(), elf_section_idx)); } }
-            } else if is_data {
-                if let Ok(data) = section.data() { if !data.is_empty() { data_additions.push(("__data", data.to_vec(), section.address(), elf_section_idx)); } }
-            } else if is_bss {
-                if section.size() > 0 { data_additions.push(("__bss", Vec::new(), section.address(), elf_section_idx)); }
-            }
+/// 链接映射：每行一个输入节（或公共符号）的输出地址、大小、输出节与来源，以制表符分隔，
+/// ohlink-size --map 按此格式把输出大小归到输入文件
+fn link_map(
+    out: &Path,
+    inputs: &[(PathBuf, Vec<u8>, OhlinkFile)],
+    segments: [(u64, &Vec<SectionItem>); 2],
+    commons: &[(String, u64, u64, usize, usize)],
+    common_addrs: &[u64],
+) -> String {
+    let mut text = format!("# Link map of {}\n# address\tsize\toutput section\tinput\tinput section\n", out.display());
+    for ((base, items), segname) in segments.into_iter().zip(["__TEXT", "__DATA"]) {
+        for (name, _, _, rel, fi, _, old) in items {
+            let (path, _, file) = &inputs[*fi];
+            text.push_str(&format!(
+                "{:#018x}\t{:#x}\t{},{}\t{}\t{}\n",
+                base + rel, old.size, segname, name, path.display(), file.section_name(old)
+            ));
         }
     }
-
-    let mut section_map: HashMap<usize, u8> = HashMap::new();
-    let mut section_ord: u8 = 0;
-    {
-        let text_segment = builder.add_segment("__TEXT", 0);
-        for (name, data, addr, elf_idx) in text_additions.drain(..) {
-            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
-            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
-            text_segment.add_section_with(name, &data, addr, align, size);
-            section_map.insert(elf_idx, section_ord);
-            section_ord = section_ord.wrapping_add(1);
-        }
-    }
-    {
-        let data_segment = builder.add_segment("__DATA", 0);
-        for (name, data, addr, elf_idx) in data_additions.drain(..) {
-            let align = elf.sections().nth(elf_idx).map(|s| s.align() as u32).unwrap_or(4);
-            let size = elf.sections().nth(elf_idx).map(|s| s.size()).unwrap_or(data.len() as u64);
-            data_segment.add_section_with(name, &data, addr, align, size);
-            section_map.insert(elf_idx, section_ord);
-            section_ord = section_ord.wrapping_add(1);
-        }
-    }
-
-    let mut elf_to_oh_sym: HashMap<usize, u32> = HashMap::new();
-    for symbol in elf.symbols() {
-        if let Ok(name) = symbol.name() {
-            if name.is_empty() { continue; }
-            let symbol_section = match symbol.section() {
-                object::SymbolSection::Section(idx) => *section_map.get(&idx.0).unwrap_or(&0u8),
-                _ => 0u8,
-            };
-            let n_type = if matches!(symbol.section(), object::SymbolSection::Undefined) { 0x00 } else if symbol.is_global() { 0x0f } else { 0x0e };
-            let symbol_idx = builder.add_symbol_with(name, symbol.address(), symbol_section, n_type, 0);
-            let elf_sym_idx = symbol.index().0;
-            elf_to_oh_sym.insert(elf_sym_idx, symbol_idx);
-        }
+    for ((name, size, _, fi, _), addr) in commons.iter().zip(common_addrs) {
+        text.push_str(&format!(
+            "{:#018x}\t{:#x}\t__DATA,__common\t{}\t*COMMON* {}\n",
+            addr, size, inputs[*fi].0.display(), name
+        ));
     }
+    text
+}
 
-    use std::collections::HashMap as Map;
-    let mut reloc_map: Map<usize, Vec<Relocation64>> = Map::new();
-    for (elf_section_idx, section) in elf.sections().enumerate() {
-        for (offset, reloc) in section.relocations() {
-            let r_addr = section.address().wrapping_add(offset);
-            let r_symbol_elf = match reloc.target() { object::RelocationTarget::Symbol(si) => si.0, _ => 0 };
-            let r_symbol = elf_to_oh_sym.get(&r_symbol_elf).copied().unwrap_or(0);
-            let r_type = map_relocation_type(&reloc);
-            let r_addend = reloc.addend();
-            let r = Relocation64 { r_addr, r_symbol, r_type, r_addend };
-            reloc_map.entry(elf_section_idx).or_default().push(r);
-        }
+/// 求值 `--defsym` 表达式：数字（十进制或 0x 十六进制）或符号，以 `+`/`-` 连接
+fn eval_symbol_expr(expr: &str, lookup: impl Fn(&str) -> Option<u64>) -> Result<u64> {
+    let mut total: i128 = 0;
+    let mut rest = expr.trim();
+    let mut sign: i128 = 1;
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+        if term.is_empty() { anyhow::bail!("malformed expression: {}", expr); }
+        let value = if let Some(hex) = term.strip_prefix("0x").or_else(|| term.strip_prefix("0X")) {
+            u64::from_str_radix(hex, 16).with_context(|| format!("bad number: {}", term))?
+        } else if term.starts_with(|c: char| c.is_ascii_digit()) {
+            term.parse::<u64>().with_context(|| format!("bad number: {}", term))?
+        } else {
+            lookup(term).with_context(|| format!("undefined symbol in expression: {}", term))?
+        };
+        total += sign * value as i128;
+        if end == rest.len() { break; }
+        sign = if rest.as_bytes()[end] == b'+' { 1 } else { -1 };
+        rest = &rest[end + 1..];
     }
-    for (elf_idx, relocs) in reloc_map.iter() {
-        if let Some(ord) = section_map.get(elf_idx) { builder.add_relocations_by_ord(*ord, relocs); }
+    Ok(total as u64)
+}
+
+/// Args 中声明的开关 -> 是否带值
+fn known_flags() -> HashMap<String, bool> {
+    use clap::CommandFactory;
+    let mut known = HashMap::new();
+    for a in Args::command().get_arguments() {
+        let takes_value = a.get_action().takes_values();
+        if let Some(l) = a.get_long() { known.insert(format!("--{}", l), takes_value); }
+        if let Some(c) = a.get_short() { known.insert(format!("-{}", c), takes_value); }
     }
+    known
+}
 
-    Ok(builder.build())
+fn read_cstr(buf: &[u8], off: usize) -> String {
+    if off >= buf.len() { return String::new(); }
+    let mut end = off;
+    while end < buf.len() && buf[end] != 0 { end += 1; }
+    String::from_utf8_lossy(&buf[off..end]).to_string()
 }
 
-fn map_relocation_type(r: &object::Relocation) -> u32 {
-    use object::RelocationKind as K;
-    match r.kind() {
-        K::Absolute => RELOC_ABS64,
-        K::Relative => RELOC_REL64,
-        K::PltRelative => RELOC_REL64,
-        _ => RELOC_ABS64,
+/// 输入节名 -> 输出节名：`__text.foo` 等按输入节保留的名字并入 `__text`，自定义节保持原名
+fn output_section_name(name: &str) -> &str {
+    match name.strip_prefix("__").and_then(|rest| rest.find('.')) {
+        Some(dot) => &name[..dot + 2],
+        None => name,
     }
 }
 
-fn apply_relocations_with_base(section_data: &mut [u8], old_sec: &Section64, new_abs_base: u64, file_data: &[u8], symbols: &[Nlist64]) -> Result<()> {
+fn apply_relocations_with_base(section_data: &mut [u8], old_sec: &Section64, new_abs_base: u64, file_data: &[u8], sym_values: &[u64]) -> Result<()> {
     let rs = old_sec.reloff as usize;
     let rsz = size_of::<Relocation64>();
     for i in 0..(old_sec.nreloc as usize) {
@@ -508,14 +853,22 @@ fn apply_relocations_with_base(section_data: &mut [u8], old_sec: &Section64, new
         let end = start + rsz;
         if end > file_data.len() { break; }
         let r: Relocation64 = unsafe { std::ptr::read(file_data[start..end].as_ptr() as *const _) };
-        let offset_in_section = (r.r_addr - old_sec.addr) as usize;
+        let Some(offset_in_section) = r.r_addr.checked_sub(old_sec.addr).map(|off| off as usize) else {
+            anyhow::bail!("relocation at {:#x} lies before the start of its section ({:#x})", r.r_addr, old_sec.addr);
+        };
         let place = (new_abs_base as i128) + (offset_in_section as i128);
-        if offset_in_section + 8 > section_data.len() { continue; }
+        if r.r_type == RELOC_NONE { continue; }
+        // 按重定位类型检查写入宽度，4 字节的指令/数据重定位可以位于节末尾
+        let width = if matches!(r.r_type, RELOC_ABS64 | RELOC_REL64) { 8 } else { 4 };
+        if offset_in_section + width > section_data.len() {
+            anyhow::bail!("relocation at {:#x} extends past the end of its section ({} bytes)", r.r_addr, section_data.len());
+        }
 
         let sym_idx = r.r_symbol as usize;
-        if sym_idx >= symbols.len() { continue; }
-        let sym = symbols[sym_idx];
-        let target = sym.n_value as i128;
+        if sym_idx >= sym_values.len() {
+            anyhow::bail!("relocation at {:#x} refers to missing symbol {}", r.r_addr, sym_idx);
+        }
+        let target = sym_values[sym_idx] as i128;
         let addend = r.r_addend as i128;
 
         match r.r_type {
@@ -551,7 +904,7 @@ fn apply_relocations_with_base(section_data: &mut [u8], old_sec: &Section64, new
             RELOC_AARCH64_ADR_PREL_PG_HI21 => {
                 // Patch ADRP-style page-relative immediate: imm21 split into immlo[30:29] and immhi[23:5]
                 // imm = sign21((page(target) - page(place)))
-                let place_page = (place as i128) >> 12;
+                let place_page = place >> 12;
                 let target_page = ((target + addend) as i128) >> 12;
                 let imm = (target_page - place_page) as i32; // signed 21-bit
                 let immlo = (imm & 0x3) as u32;         // bits[1:0]
@@ -590,4 +943,67 @@ fn apply_relocations_with_base(section_data: &mut [u8], old_sec: &Section64, new
     Ok(())
 }
 
-fn align_up(x: u64, a: u64) -> u64 { if a == 0 { x } else { ((x + a - 1) / a) * a } }
+fn align_up(x: u64, a: u64) -> u64 { if a == 0 { x } else { x.div_ceil(a) * a } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` 个各含一条 ret 的节，`_start` 位于最后一个节
+    fn many_sections(count: usize) -> Vec<u8> {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        let seg = b.add_segment("__TEXT", 0);
+        for i in 0..count {
+            seg.add_section(&format!("__text.f{}", i), &[0xc0, 0x03, 0x5f, 0xd6], 0);
+        }
+        b.add_symbol_with("_start", 0, (count - 1) as u8, N_SECT | N_EXT, 0);
+        b.build()
+    }
+
+    #[test]
+    fn section_ordinal_limit() {
+        let dir = std::env::temp_dir().join(format!("ohlink-ld-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let link_sections = |count: usize| {
+            let input = dir.join(format!("in{}.ohlink", count));
+            let output = dir.join(format!("out{}.ohlink", count));
+            fs::write(&input, many_sections(count)).unwrap();
+            let argv = ["ohlink-ld", "-o", output.to_str().unwrap(), input.to_str().unwrap()];
+            link(argv.map(String::from).to_vec()).map(|_| fs::read(&output).unwrap())
+        };
+
+        // __PAGEZERO 占第 1 个节，254 个输入节正好用满 255 个 n_sect
+        let out = link_sections(254).expect("link");
+        let out = OhlinkFile::parse(&out).expect("parse");
+        assert_eq!(out.sections().len(), MAX_SECTIONS);
+        let (_, start) = out.symbols().into_iter().find(|(name, _)| name == "_start").unwrap();
+        assert_eq!(start.n_sect, 255);
+        assert_eq!(out.sections()[254].1.addr, start.n_value);
+
+        let err = link_sections(255).unwrap_err();
+        assert!(err.to_string().contains("Too many sections"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn symbol_sizes_from_extents() {
+        let dir = std::env::temp_dir().join(format!("ohlink-ld-sizes-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 16], 0);
+        b.add_symbol("_start", 0, 0);
+        let helper = b.add_symbol("helper", 8, 0);
+        b.add_symbol("tail", 12, 0);
+        b.set_symbol_size(helper, 2);
+        let (input, output) = (dir.join("in.ohlink"), dir.join("out.ohlink"));
+        fs::write(&input, b.build()).unwrap();
+        let argv = ["ohlink-ld", "-o", output.to_str().unwrap(), input.to_str().unwrap()];
+        link(argv.map(String::from).to_vec()).expect("link");
+
+        // 没有记录大小的符号取到下一个符号或节末的距离，记录了的保持不变
+        let out = OhlinkFile::parse(&fs::read(&output).unwrap()).expect("parse");
+        let sizes: Vec<(String, Option<u64>)> = out.symbols().into_iter().enumerate().map(|(i, (name, _))| (name, out.symbol_size(i))).collect();
+        assert_eq!(sizes, [("_start".to_string(), Some(8)), ("helper".to_string(), Some(2)), ("tail".to_string(), Some(4))]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
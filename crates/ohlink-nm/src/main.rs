@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use ohlink_format::*;
 use std::fs;
 use std::path::PathBuf;
@@ -7,59 +7,339 @@ use std::path::PathBuf;
 #[derive(Parser, Debug)]
 #[command(author, version, about = "List symbols from Ohlink file", long_about = None)]
 struct Args {
-    input: PathBuf,
+    /// Ohlink files or .ohlib archives
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Print the archive symbol index before the member symbols
+    #[arg(long = "print-armap")]
+    print_armap: bool,
+    /// Display only undefined symbols
+    #[arg(short = 'u', long = "undefined-only", conflicts_with = "defined_only")]
+    undefined_only: bool,
+    /// Display only defined symbols
+    #[arg(long = "defined-only")]
+    defined_only: bool,
+    /// Display only external symbols
+    #[arg(short = 'g', long = "extern-only")]
+    extern_only: bool,
+    /// Sort by address instead of by name
+    #[arg(short = 'n', long = "numeric-sort", conflicts_with = "no_sort")]
+    numeric_sort: bool,
+    /// Keep symbol table order
+    #[arg(short = 'p', long = "no-sort")]
+    no_sort: bool,
+    /// Reverse the sort order (ignored with -p)
+    #[arg(short = 'r', long = "reverse-sort")]
+    reverse_sort: bool,
+    /// Print the size of defined symbols (recorded size, else the distance to the next symbol or the section end)
+    #[arg(short = 'S', long = "print-size")]
+    print_size: bool,
+    /// Prefix every line with the file (and archive member) name
+    #[arg(short = 'A', visible_short_alias = 'o', long = "print-file-name")]
+    print_file_name: bool,
+    /// POSIX portable output: `name type value size`
+    #[arg(short = 'P', long = "portability")]
+    portability: bool,
+    /// Demangle Rust and C++ symbol names
+    #[arg(short = 'C', long)]
+    demangle: bool,
+    /// Also show mapping symbols ($x, $d)
+    #[arg(long = "special-syms")]
+    special_syms: bool,
+    /// Output format; json lists every selected symbol with its section and flags
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let data = fs::read(&args.input)
-        .with_context(|| format!("Failed to read file: {:?}", args.input))?;
-    let magic: [u8; 4] = data[0..4].try_into().unwrap();
-    if magic == OHLIB_MAGIC {
-        let arch = OhlibArchive::parse(&data).with_context(|| "Failed to parse Ohlib archive")?;
-        for e in &arch.entries {
-            let mname = String::from_utf8_lossy(&e.name).trim_end_matches('\0').to_string();
-            let start = e.offset as usize; let end = start + e.size as usize; if end > arch.data.len() { continue; }
-            let bytes = arch.data[start..end].to_vec();
-            if let Ok(file) = OhlinkFile::parse(&bytes) {
-                let mut symtab: Option<SymtabCommand> = None;
-                for cmd in &file.commands { if let LoadCommand::Symtab(s) = cmd { symtab = Some(*s); } }
-                if let Some(sym) = symtab {
-                    let nsz = std::mem::size_of::<Nlist64>();
-                    let mut entries: Vec<Nlist64> = Vec::new();
-                    for i in 0..(sym.nsyms as usize) {
-                        let s = (sym.symoff as usize) + i * nsz; let e = s + nsz; if e > bytes.len() { break; }
-                        let item: Nlist64 = unsafe { std::ptr::read(bytes[s..e].as_ptr() as *const _) };
-                        entries.push(item);
-                    }
-                    let strtab = &bytes[(sym.stroff as usize)..(sym.stroff as usize + sym.strsize as usize).min(bytes.len())];
-                    for it in entries { let name = read_cstr(strtab, it.n_strx as usize); println!("{:#018x} {}({})", it.n_value, mname, name); }
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// 一条待输出的符号
+struct NmSymbol {
+    /// 在符号表中的下标
+    index: usize,
+    name: String,
+    nlist: Nlist64,
+    letter: char,
+    size: Option<u64>,
+}
+
+/// POSIX nm 类型字母，局部符号为小写
+fn symbol_letter(file: &OhlinkFile, sections: &[(&SegmentCommand64, &Section64)], n: &Nlist64) -> char {
+    if n.is_common() {
+        return 'C';
+    }
+    if n.is_undefined() {
+        return if n.is_weak_ref() { 'w' } else { 'U' };
+    }
+    let letter = if n.is_absolute() {
+        'A'
+    } else if n.is_weak_def() {
+        'W'
+    } else {
+        match sections.get((n.n_sect as usize).wrapping_sub(1)) {
+            Some((seg, sec)) => section_letter(file, seg, sec),
+            None => '?',
+        }
+    };
+    if n.is_external() || n.is_weak_def() { letter } else { letter.to_ascii_lowercase() }
+}
+
+/// 节的种类：零填充为 B，可写段为 D，可执行段中的只读数据（__const/__cstring）为 R，其余为 T
+fn section_letter(file: &OhlinkFile, seg: &SegmentCommand64, sec: &Section64) -> char {
+    if sec.flags & SECTION_TYPE == S_ZEROFILL {
+        return 'B';
+    }
+    let prot = file.segment_protection(seg);
+    let name = file.section_name(sec);
+    if prot & VM_PROT_WRITE != 0 {
+        'D'
+    } else if prot & VM_PROT_EXECUTE != 0 && !name.starts_with("__const") && !name.starts_with("__cstring") {
+        'T'
+    } else {
+        'R'
+    }
+}
+
+/// 按选项筛选并排序一个文件的符号
+fn collect_symbols(file: &OhlinkFile, args: &Args) -> Vec<NmSymbol> {
+    let sections = file.sections();
+    let all = file.symbols();
+
+    let extents = file.symbol_extents();
+
+    let mut out: Vec<NmSymbol> = all
+        .iter()
+        .enumerate()
+        .filter(|(_, (name, n))| {
+            // 无名节符号只用于重定位
+            !name.is_empty()
+                && (args.special_syms || !is_mapping_symbol(name))
+                && (!args.undefined_only || n.is_undefined() && !n.is_common())
+                && (!args.defined_only || !n.is_undefined() || n.is_common())
+                && (!args.extern_only || n.is_external())
+        })
+        .map(|(index, (name, n))| NmSymbol {
+            index,
+            name: name.clone(),
+            nlist: *n,
+            letter: symbol_letter(file, &sections, n),
+            size: extents[index],
+        })
+        .collect();
+    if !args.no_sort {
+        if args.numeric_sort {
+            // 未定义符号没有地址，排在最前
+            out.sort_by(|a, b| {
+                (!a.nlist.is_undefined() || a.nlist.is_common(), a.nlist.n_value, &a.name)
+                    .cmp(&(!b.nlist.is_undefined() || b.nlist.is_common(), b.nlist.n_value, &b.name))
+            });
+        } else {
+            out.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        // 与 GNU nm 一致，-r 只反转排序结果，-p 时保持符号表顺序
+        if args.reverse_sort {
+            out.reverse();
+        }
+    }
+    out
+}
+
+fn print_symbols(symbols: &[NmSymbol], prefix: &str, args: &Args) {
+    for s in symbols {
+        let name = demangle::display_name(&s.name, args.demangle);
+        let defined = !s.nlist.is_undefined() || s.nlist.is_common();
+        let size = s.size.filter(|_| args.print_size);
+        if args.portability {
+            let mut line = format!("{}{} {}", prefix, name, s.letter);
+            if defined {
+                line.push_str(&format!(" {:016x}", s.nlist.n_value));
+                if let Some(size) = size {
+                    line.push_str(&format!(" {:016x}", size));
                 }
             }
+            println!("{}", line);
+            continue;
+        }
+        let value = if defined { format!("{:016x}", s.nlist.n_value) } else { " ".repeat(16) };
+        match size {
+            Some(size) => println!("{}{} {:016x} {} {}", prefix, value, size, s.letter, name),
+            None => println!("{}{} {} {}", prefix, value, s.letter, name),
+        }
+    }
+}
+
+/// 一个输入展开后的文件：(归档成员名, 文件)
+type InputFiles = Vec<(Option<String>, OhlinkFile)>;
+
+fn load_input(path: &PathBuf, data: &[u8]) -> Result<(Option<OhlibArchive>, InputFiles)> {
+    if data.get(0..4) == Some(&OHLIB_MAGIC[..]) {
+        let arch = OhlibArchive::parse(data).with_context(|| format!("Failed to parse Ohlib archive: {:?}", path))?;
+        let mut files = Vec::new();
+        for (name, bytes) in arch.members() {
+            let file = OhlinkFile::parse(bytes).with_context(|| format!("Failed to parse member {:?}({})", path, name))?;
+            files.push((Some(name), file));
         }
+        Ok((Some(arch), files))
     } else {
-        let file = OhlinkFile::parse(&data).with_context(|| "Failed to parse Ohlink file")?;
-        let mut symtab: Option<SymtabCommand> = None;
-        for cmd in &file.commands { if let LoadCommand::Symtab(s) = cmd { symtab = Some(*s); } }
-        let sym = symtab.context("No symbol table")?;
-        let nlist_sz = std::mem::size_of::<Nlist64>();
-        let mut entries = Vec::new();
-        for i in 0..(sym.nsyms as usize) {
-            let start = (sym.symoff as usize) + i * nlist_sz;
-            let end = start + nlist_sz;
-            if end > data.len() { break; }
-            let e: Nlist64 = unsafe { std::ptr::read(data[start..end].as_ptr() as *const _) };
-            entries.push(e);
+        let file = OhlinkFile::parse(data).with_context(|| format!("Failed to parse Ohlink file: {:?}", path))?;
+        Ok((None, vec![(None, file)]))
+    }
+}
+
+fn print_armap(arch: &OhlibArchive) {
+    match &arch.index {
+        Some(index) => {
+            println!("Archive index:");
+            for (name, mi) in index {
+                let member = arch.member(*mi as usize).map(|m| m.0.to_string()).unwrap_or_default();
+                println!("{} in {}", name, member);
+            }
+            println!();
+        }
+        None => println!("(no archive index)\n"),
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut json_files = Vec::new();
+    for path in &args.inputs {
+        let data = fs::read(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+        let (arch, files) = load_input(path, &data)?;
+        let shown = path.display().to_string();
+        if args.format == Format::Text {
+            if let Some(arch) = arch.as_ref().filter(|_| args.print_armap) {
+                print_armap(arch);
+            }
+        }
+        for (member, file) in &files {
+            let symbols = collect_symbols(file, &args);
+            if args.format == Format::Json {
+                // 与 ohlink-objdump 相同的外层结构，每个文件只含 symbols（附加 nm 类型字母）
+                let reports = file.report().symbols;
+                let selected: Vec<serde_json::Value> = symbols
+                    .iter()
+                    .map(|s| {
+                        let mut v = serde_json::to_value(&reports[s.index])?;
+                        v["type"] = s.letter.to_string().into();
+                        if let Some(d) = demangle::demangle(&s.name).filter(|_| args.demangle) {
+                            v["demangled"] = d.into();
+                        }
+                        Ok(v)
+                    })
+                    .collect::<Result<_>>()?;
+                json_files.push(serde_json::json!({ "path": shown, "member": member, "symbols": selected }));
+                continue;
+            }
+            // 多个输入或归档成员时，未使用 -A 则以文件名分组
+            let prefix = match (args.print_file_name, member) {
+                (true, Some(m)) => format!("{}:{}: ", shown, m),
+                (true, None) => format!("{}: ", shown),
+                (false, _) => String::new(),
+            };
+            if !args.print_file_name {
+                match member {
+                    Some(m) => println!("\n{}:", m),
+                    None if args.inputs.len() > 1 => println!("\n{}:", shown),
+                    None => {}
+                }
+            }
+            print_symbols(&symbols, &prefix, &args);
         }
-        let strtab = &data[(sym.stroff as usize)..(sym.stroff as usize + sym.strsize as usize).min(data.len())];
-        for e in entries { let name = read_cstr(strtab, e.n_strx as usize); println!("{:#018x} {}", e.n_value, name); }
+    }
+    if args.format == Format::Json {
+        let doc = serde_json::json!({ "schema_version": report::SCHEMA_VERSION, "files": json_files });
+        println!("{}", serde_json::to_string_pretty(&doc)?);
     }
     Ok(())
 }
 
-fn read_cstr(buf: &[u8], off: usize) -> String {
-    if off >= buf.len() { return String::new(); }
-    let mut end = off;
-    while end < buf.len() && buf[end] != 0 { end += 1; }
-    String::from_utf8_lossy(&buf[off..end]).to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 覆盖各类节与符号种类的小目标文件
+    fn sample() -> OhlinkFile {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 16], 0).add_section("__const", &[0; 8], 0x10);
+        b.add_segment("__DATA", 0x100)
+            .add_section("__data", &[0; 8], 0x100)
+            .add_section_with("__bss", &[], 0x108, 3, 16)
+            .set_section_flags(S_ZEROFILL, 0);
+        b.add_symbol("main", 4, 0);
+        b.add_symbol_with("helper", 0, 0, N_SECT, 0);
+        b.add_symbol("table", 0x10, 1);
+        b.add_symbol("counter", 0x100, 2);
+        b.add_symbol_with("buf", 0x108, 3, N_SECT, 0);
+        b.add_symbol_with("$x", 0, 0, N_SECT, 0);
+        b.add_undefined_symbol("puts", 0);
+        b.add_undefined_symbol("opt", N_WEAK_REF);
+        b.add_symbol_with("wdef", 8, 0, N_SECT | N_EXT, N_WEAK_DEF);
+        b.add_absolute_symbol("abs", 0x40);
+        b.add_common_symbol("cc", 8, 3);
+        OhlinkFile::parse(&b.build()).expect("parse")
+    }
+
+    fn listed(file: &OhlinkFile, flags: &[&str]) -> Vec<(String, char)> {
+        let args = Args::parse_from(["ohlink-nm"].iter().chain(flags).chain(&["x.o"]));
+        collect_symbols(file, &args).into_iter().map(|s| (s.name, s.letter)).collect()
+    }
+
+    fn names(file: &OhlinkFile, flags: &[&str]) -> Vec<String> {
+        listed(file, flags).into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn letters() {
+        let file = sample();
+        let letters: Vec<(String, char)> = listed(&file, &["-p", "--special-syms"]);
+        let expect = [
+            ("main", 'T'),
+            ("helper", 't'),
+            ("table", 'R'),
+            ("counter", 'D'),
+            ("buf", 'b'),
+            ("$x", 't'),
+            ("puts", 'U'),
+            ("opt", 'w'),
+            ("wdef", 'W'),
+            ("abs", 'A'),
+            ("cc", 'C'),
+        ];
+        assert_eq!(letters, expect.map(|(n, l)| (n.to_string(), l)));
+
+        let sections = file.sections();
+        let kinds: Vec<char> = sections.iter().map(|(seg, sec)| section_letter(&file, seg, sec)).collect();
+        assert_eq!(kinds, ['T', 'R', 'D', 'B']);
+    }
+
+    #[test]
+    fn filters() {
+        let file = sample();
+        assert_eq!(names(&file, &["-p", "-u"]), ["puts", "opt"]);
+        assert_eq!(names(&file, &["-p", "-g"]), ["main", "table", "counter", "puts", "opt", "wdef", "abs", "cc"]);
+        assert_eq!(names(&file, &["-p", "--defined-only"]), ["main", "helper", "table", "counter", "buf", "wdef", "abs", "cc"]);
+        assert_eq!(names(&file, &["-p", "-g", "--defined-only"]), ["main", "table", "counter", "wdef", "abs", "cc"]);
+    }
+
+    #[test]
+    fn ordering() {
+        let file = sample();
+        let by_name = ["abs", "buf", "cc", "counter", "helper", "main", "opt", "puts", "table", "wdef"];
+        assert_eq!(names(&file, &[]), by_name);
+        let mut reversed = by_name;
+        reversed.reverse();
+        assert_eq!(names(&file, &["-r"]), reversed);
+        // 未定义符号在前，其余按地址、同地址按名字
+        let numeric = ["opt", "puts", "helper", "main", "cc", "wdef", "table", "abs", "counter", "buf"];
+        assert_eq!(names(&file, &["-n"]), numeric);
+        // -p 保持符号表顺序，-r 不影响
+        let table = ["main", "helper", "table", "counter", "buf", "puts", "opt", "wdef", "abs", "cc"];
+        assert_eq!(names(&file, &["-p"]), table);
+        assert_eq!(names(&file, &["-p", "-r"]), table);
+    }
 }
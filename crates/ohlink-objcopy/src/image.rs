@@ -0,0 +1,144 @@
+//! 平坦映像格式：raw binary、Intel HEX 与 Motorola S-record。
+//! HEX 与 S-record 的每行以 CRLF 结尾，与 GNU/LLVM objcopy 的输出相同
+use anyhow::{bail, Result};
+use std::fmt::Write as _;
+
+/// 一块要写出的内容：(加载地址, 字节)
+pub type Chunk<'a> = (u64, &'a [u8]);
+
+/// 每条 HEX/S-record 记录的数据字节数
+const RECORD_LEN: usize = 16;
+
+/// raw binary 不应超过的大小，防止地址相距很远的段生成巨大的文件
+const MAX_BINARY: u64 = 1 << 30;
+
+/// 按地址排列各块，从最低地址开始，块之间的空隙以 `fill` 填充；`pad_to` 大于末尾时补齐到该地址
+pub fn binary(chunks: &[Chunk], fill: u8, pad_to: Option<u64>) -> Result<Vec<u8>> {
+    let Some(start) = chunks.iter().map(|c| c.0).min() else { return Ok(Vec::new()) };
+    let end = chunks.iter().map(|c| c.0 + c.1.len() as u64).chain(pad_to).max().unwrap_or(start);
+    if end - start > MAX_BINARY {
+        bail!("binary image from {:#x} to {:#x} would be {} bytes", start, end, end - start);
+    }
+    let mut out = vec![fill; (end - start) as usize];
+    for (addr, bytes) in chunks {
+        let off = (addr - start) as usize;
+        out[off..off + bytes.len()].copy_from_slice(bytes);
+    }
+    Ok(out)
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(out, "{:02X}", b);
+    }
+}
+
+/// Intel HEX 记录：`:LLAAAATT<数据>CC`，校验和使各字节之和为 0
+fn ihex_record(out: &mut String, kind: u8, addr: u16, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b));
+    bytes.push(sum.wrapping_neg());
+    out.push(':');
+    push_hex(out, &bytes);
+    out.push_str("\r\n");
+}
+
+/// Intel HEX（I32HEX）：高 16 位地址变化时写扩展线性地址记录（04），入口写入起始线性地址记录（05）
+pub fn ihex(chunks: &[Chunk], entry: Option<u64>) -> Result<String> {
+    let mut out = String::new();
+    let mut upper: Option<u16> = None;
+    for (addr, bytes) in chunks {
+        if addr + bytes.len() as u64 > 1 << 32 {
+            bail!("address {:#x} does not fit in Intel HEX", addr + bytes.len() as u64);
+        }
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let a = addr + pos as u64;
+            // 一条记录不跨越 64 KiB 边界
+            let len = RECORD_LEN.min(bytes.len() - pos).min((0x1_0000 - (a & 0xffff)) as usize);
+            let hi = (a >> 16) as u16;
+            if upper != Some(hi) {
+                ihex_record(&mut out, 0x04, 0, &hi.to_be_bytes());
+                upper = Some(hi);
+            }
+            ihex_record(&mut out, 0x00, a as u16, &bytes[pos..pos + len]);
+            pos += len;
+        }
+    }
+    if let Some(entry) = entry {
+        let entry = u32::try_from(entry).map_err(|_| anyhow::anyhow!("entry point {:#x} does not fit in Intel HEX", entry))?;
+        ihex_record(&mut out, 0x05, 0, &entry.to_be_bytes());
+    }
+    ihex_record(&mut out, 0x01, 0, &[]);
+    Ok(out)
+}
+
+/// S-record：`S<t><字节数><地址><数据><校验>`，字节数包括地址、数据与校验，校验为其余字节和的反码
+fn srec_record(out: &mut String, kind: u8, addr: u64, addr_len: usize, data: &[u8]) {
+    let mut bytes = vec![(addr_len + data.len() + 1) as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes()[8 - addr_len..]);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b));
+    bytes.push(!sum);
+    let _ = write!(out, "S{}", kind);
+    push_hex(out, &bytes);
+    out.push_str("\r\n");
+}
+
+/// Motorola S-record：按最高地址选用 S1/S2/S3 数据记录（16/24/32 位地址），
+/// 以 S0 头记录开始，S5/S6 记录数，S9/S8/S7 结束并给出入口
+pub fn srec(chunks: &[Chunk], entry: Option<u64>, header: &str) -> Result<String> {
+    let end = chunks.iter().map(|c| c.0 + c.1.len() as u64).chain(entry).max().unwrap_or(0);
+    let (data_kind, addr_len) = match end {
+        e if e <= 1 << 16 => (1, 2),
+        e if e <= 1 << 24 => (2, 3),
+        e if e <= 1 << 32 => (3, 4),
+        e => bail!("address {:#x} does not fit in an S-record", e),
+    };
+    let mut out = String::new();
+    srec_record(&mut out, 0, 0, 2, header.as_bytes());
+    let mut count: u64 = 0;
+    for (addr, bytes) in chunks {
+        for (i, chunk) in bytes.chunks(RECORD_LEN).enumerate() {
+            srec_record(&mut out, data_kind, addr + (i * RECORD_LEN) as u64, addr_len, chunk);
+            count += 1;
+        }
+    }
+    if count <= 0xffff {
+        srec_record(&mut out, 5, count, 2, &[]);
+    } else if count <= 0xff_ffff {
+        srec_record(&mut out, 6, count, 3, &[]);
+    }
+    srec_record(&mut out, 10 - data_kind, entry.unwrap_or(0), addr_len, &[]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_fills_gaps() {
+        let image = binary(&[(0x1004, &[1, 2]), (0x1000, &[3])], 0xff, Some(0x1008)).unwrap();
+        assert_eq!(image, [3, 0xff, 0xff, 0xff, 1, 2, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn ihex_records_and_checksums() {
+        let text = ihex(&[(0x4000_0100, &[1, 2])], Some(0x4000_0100)).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines, [":020000044000BA", ":020100000102FA", ":0400000540000100B6", ":00000001FF"]);
+    }
+
+    #[test]
+    fn srec_picks_address_width() {
+        let text = srec(&[(0, &[1])], None, "").unwrap();
+        assert_eq!(text.lines().collect::<Vec<_>>(), ["S0030000FC", "S104000001FA", "S5030001FB", "S9030000FC"]);
+        let text = srec(&[(0x4000_0000, &[1])], Some(0x4000_0000), "").unwrap();
+        assert!(text.lines().nth(1).unwrap().starts_with("S3064000000001"));
+        assert_eq!(text.lines().last(), Some("S70540000000BA"));
+    }
+}
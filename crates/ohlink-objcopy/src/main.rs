@@ -0,0 +1,202 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use ohlink_format::*;
+use std::fs;
+use std::path::PathBuf;
+
+mod image;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Copy and translate Ohlink files to raw binary, Intel HEX or S-record",
+    long_about = None,
+    after_help = "Section names are SECT or SEG,SECT (e.g. __text or __TEXT,__text).\n\
+                  Edits are applied in this order: -j/-R, --add-section, --rename-section,\n\
+                  --set-section-alignment, --change-addresses."
+)]
+struct Args {
+    /// Input Ohlink file
+    input: PathBuf,
+    /// Output file (default: rewrite the input)
+    output: Option<PathBuf>,
+    /// Output format
+    #[arg(short = 'O', long = "output-target", value_enum, default_value_t = Target::Ohlink)]
+    target: Target,
+    /// Copy only the named section (may be repeated)
+    #[arg(short = 'j', long = "only-section", value_name = "NAME")]
+    only_section: Vec<String>,
+    /// Remove the named section (may be repeated)
+    #[arg(short = 'R', long = "remove-section", value_name = "NAME")]
+    remove_section: Vec<String>,
+    /// Add a section with the contents of FILE, appended to segment SEG (default __DATA, created if missing)
+    #[arg(long = "add-section", value_name = "[SEG,]SECT=FILE")]
+    add_section: Vec<String>,
+    /// Rename a section
+    #[arg(long = "rename-section", value_name = "OLD=NEW")]
+    rename_section: Vec<String>,
+    /// Set the alignment (in bytes, a power of two) recorded for a section
+    #[arg(long = "set-section-alignment", value_name = "NAME=ALIGN")]
+    set_section_alignment: Vec<String>,
+    /// Add INCR (may be negative) to every segment, section and symbol address and to the entry point
+    #[arg(long = "change-addresses", visible_alias = "adjust-vma", value_name = "INCR", allow_hyphen_values = true)]
+    change_addresses: Option<String>,
+    /// Byte used to fill the gaps between sections in binary output
+    #[arg(long = "gap-fill", value_name = "BYTE", default_value = "0")]
+    gap_fill: String,
+    /// Pad binary output with the gap-fill byte up to ADDR
+    #[arg(long = "pad-to", value_name = "ADDR")]
+    pad_to: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum Target {
+    Ohlink,
+    Binary,
+    Ihex,
+    Srec,
+}
+
+/// 十进制或 0x 十六进制数，可带符号
+fn parse_number(s: &str) -> Result<i128> {
+    let (neg, digits) = match s.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.trim().strip_prefix('+').unwrap_or(s.trim())),
+    };
+    let v = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse::<i128>(),
+    }
+    .with_context(|| format!("bad number: {}", s))?;
+    Ok(if neg { -v } else { v })
+}
+
+/// `SECT` 匹配任意段中的同名节，`SEG,SECT` 只匹配该段中的节
+fn matches(spec: &str, segname: &str, sectname: &str) -> bool {
+    match spec.split_once(',') {
+        Some((seg, sect)) => seg == segname && sect == sectname,
+        None => spec == sectname,
+    }
+}
+
+fn split_assignment<'a>(option: &str, spec: &'a str) -> Result<(&'a str, &'a str)> {
+    spec.split_once('=').with_context(|| format!("{} expects NAME=VALUE, got `{}'", option, spec))
+}
+
+/// 按选项编辑节、符号与地址
+fn edit(b: &mut OhlinkBuilder, args: &Args) -> Result<()> {
+    if !args.only_section.is_empty() || !args.remove_section.is_empty() {
+        b.retain_sections(|seg, sect| {
+            (args.only_section.is_empty() || args.only_section.iter().any(|s| matches(s, seg, sect)))
+                && !args.remove_section.iter().any(|s| matches(s, seg, sect))
+        })
+        .map_err(|e| match e {
+            RetainError::Referenced(name) => anyhow!("cannot remove the section defining `{}': it is named in a relocation", name),
+            e => e.into(),
+        })?;
+    }
+    for spec in &args.add_section {
+        let (name, path) = split_assignment("--add-section", spec)?;
+        let (seg, sect) = name.split_once(',').unwrap_or(("__DATA", name));
+        let data = fs::read(path).with_context(|| format!("Failed to read section contents: {:?}", path))?;
+        let vm_end = b.vm_end();
+        match b.segment_mut(seg) {
+            Some(segment) => {
+                let at = segment.end().next_multiple_of(4);
+                segment.add_section_with(sect, &data, at, 4, data.len() as u64);
+            }
+            None => {
+                b.add_segment(seg, vm_end.next_multiple_of(0x1000)).add_section_with(sect, &data, 0, 4, data.len() as u64);
+            }
+        }
+    }
+    for spec in &args.rename_section {
+        let (old, new) = split_assignment("--rename-section", spec)?;
+        if b.rename_sections(|seg, sect| matches(old, seg, sect), new) == 0 {
+            eprintln!("warning: --rename-section: no section named {}", old);
+        }
+    }
+    for spec in &args.set_section_alignment {
+        let (name, align) = split_assignment("--set-section-alignment", spec)?;
+        let align = u32::try_from(parse_number(align)?).ok().filter(|a| a.is_power_of_two());
+        let Some(align) = align else { bail!("--set-section-alignment: alignment must be a power of two: {}", spec) };
+        if b.set_section_alignment(|seg, sect| matches(name, seg, sect), align) == 0 {
+            eprintln!("warning: --set-section-alignment: no section named {}", name);
+        }
+    }
+    if let Some(incr) = &args.change_addresses {
+        let delta = i64::try_from(parse_number(incr)?).context("--change-addresses: increment out of range")?;
+        b.change_addresses(delta)
+            .map_err(|seg| anyhow!("--change-addresses {}: segment {} would leave the address space", incr, seg))?;
+    }
+    Ok(())
+}
+
+/// 可执行文件中不同段的节不能重叠（例如 --add-section 追加到已有段后越过了下一个段）
+fn check_overlaps(file: &OhlinkFile) -> Result<()> {
+    if file.header.file_type != MH_EXECUTE {
+        return Ok(());
+    }
+    let mut ranges: Vec<(u64, u64, String)> = file
+        .sections()
+        .into_iter()
+        .filter(|(seg, sec)| sec.size > 0 && file.segment_name(seg) != "__PAGEZERO")
+        .map(|(seg, sec)| (sec.addr, sec.addr + sec.size, format!("{},{}", file.segment_name(seg), file.section_name(sec))))
+        .collect();
+    ranges.sort();
+    for w in ranges.windows(2) {
+        if w[1].0 < w[0].1 {
+            bail!("section {} ({:#x}) overlaps {} ({:#x}-{:#x})", w[1].2, w[1].0, w[0].2, w[0].0, w[0].1);
+        }
+    }
+    Ok(())
+}
+
+/// 平坦映像的内容：有文件内容的节按地址排列，__PAGEZERO、调试节与零填充节不输出
+fn loadable_chunks(file: &OhlinkFile) -> Vec<image::Chunk<'_>> {
+    let mut chunks: Vec<image::Chunk> = file
+        .sections()
+        .into_iter()
+        .filter(|(seg, sec)| {
+            let segname = file.segment_name(seg);
+            segname != "__PAGEZERO" && !is_debug_section(&segname, &file.section_name(sec))
+        })
+        .map(|(_, sec)| (sec.addr, file.section_data(sec)))
+        .filter(|(_, data)| !data.is_empty())
+        .collect();
+    chunks.sort_by_key(|c| c.0);
+    chunks
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let data = fs::read(&args.input).with_context(|| format!("Failed to read file: {:?}", args.input))?;
+    if data.get(0..4) == Some(&OHLIB_MAGIC[..]) {
+        bail!("{:?}: archives are not supported; extract the members with ohlink-ar first", args.input);
+    }
+    let file = OhlinkFile::parse(&data).with_context(|| format!("Failed to parse Ohlink file: {:?}", args.input))?;
+
+    let mut b = OhlinkBuilder::from_file(&file);
+    edit(&mut b, &args)?;
+    let bytes = b.build();
+    let edited = OhlinkFile::parse(&bytes).context("Failed to parse the rewritten file")?;
+    check_overlaps(&edited)?;
+
+    let out = match args.target {
+        Target::Ohlink => bytes,
+        Target::Binary => {
+            let fill = u8::try_from(parse_number(&args.gap_fill)?).context("--gap-fill must be a byte")?;
+            let pad_to = args.pad_to.as_deref().map(parse_number).transpose()?.map(|v| v as u64);
+            image::binary(&loadable_chunks(&edited), fill, pad_to)?
+        }
+        Target::Ihex => image::ihex(&loadable_chunks(&edited), edited.entry_point())?.into_bytes(),
+        Target::Srec => {
+            let name = args.input.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            image::srec(&loadable_chunks(&edited), edited.entry_point(), &name)?.into_bytes()
+        }
+    };
+    let path = args.output.as_ref().unwrap_or(&args.input);
+    fs::write(path, &out).with_context(|| format!("Failed to write output: {:?}", path))?;
+    Ok(())
+}
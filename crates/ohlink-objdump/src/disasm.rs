@@ -0,0 +1,1185 @@
+//! A64 反汇编：覆盖整数、分支、系统指令、通用/SIMD 加载存储以及常用的标量浮点与少量向量指令，
+//! 语法与别名跟随 llvm-objdump。无法识别的编码输出为 `.inst`。
+
+use std::fmt;
+
+/// 一条解码后的指令
+pub struct Insn {
+    pub mnemonic: String,
+    pub operands: Vec<String>,
+    /// PC 相对的目标地址及其所在操作数下标（分支、ADR/ADRP、LDR literal）
+    pub target: Option<(usize, u64)>,
+}
+
+impl fmt::Display for Insn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.operands.is_empty() {
+            write!(f, "{}", self.mnemonic)
+        } else {
+            write!(f, "{}\t{}", self.mnemonic, self.operands.join(", "))
+        }
+    }
+}
+
+fn insn(mnemonic: &str, operands: Vec<String>) -> Option<Insn> {
+    Some(Insn { mnemonic: mnemonic.to_string(), operands, target: None })
+}
+
+fn insn_target(mnemonic: &str, operands: Vec<String>, index: usize, target: u64) -> Option<Insn> {
+    Some(Insn { mnemonic: mnemonic.to_string(), operands, target: Some((index, target)) })
+}
+
+/// 解码 `pc` 处的指令字
+pub fn decode(word: u32, pc: u64) -> Insn {
+    let decoded = match (word >> 25) & 0xf {
+        0b1000 | 0b1001 => data_processing_imm(word, pc),
+        0b1010 | 0b1011 => branch_system(word, pc),
+        0b0100 | 0b0110 | 0b1100 | 0b1110 => load_store(word, pc),
+        0b0101 | 0b1101 => data_processing_reg(word),
+        0b0111 | 0b1111 => simd_fp(word),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| Insn { mnemonic: ".inst".to_string(), operands: vec![format!("{:#010x}", word)], target: None })
+}
+
+fn bits(word: u32, hi: u32, lo: u32) -> u32 {
+    (word >> lo) & ((1u32 << (hi - lo + 1)) - 1)
+}
+
+fn bit(word: u32, n: u32) -> bool {
+    (word >> n) & 1 != 0
+}
+
+fn sign_extend(value: u32, width: u32) -> i64 {
+    let shift = 64 - width;
+    ((value as i64) << shift) >> shift
+}
+
+/// 通用寄存器；31 号在 `sp` 为真时是栈指针，否则是零寄存器
+fn reg(n: u32, x: bool, sp: bool) -> String {
+    match (n, x, sp) {
+        (31, true, true) => "sp".to_string(),
+        (31, false, true) => "wsp".to_string(),
+        (31, true, false) => "xzr".to_string(),
+        (31, false, false) => "wzr".to_string(),
+        (n, true, _) => format!("x{}", n),
+        (n, false, _) => format!("w{}", n),
+    }
+}
+
+fn xreg(n: u32) -> String {
+    reg(n, true, false)
+}
+
+fn xreg_sp(n: u32) -> String {
+    reg(n, true, true)
+}
+
+fn imm(v: i64) -> String {
+    format!("#{}", v)
+}
+
+fn hex_imm(v: u64) -> String {
+    if v == 0 { "#0".to_string() } else { format!("#{:#x}", v) }
+}
+
+/// mov 别名（MOVZ/MOVN/ORR）按寄存器宽度显示有符号十进制值
+fn mov_imm(v: u64, x: bool) -> String {
+    if x { imm(v as i64) } else { imm(v as u32 as i32 as i64) }
+}
+
+const CONDS: [&str; 16] = ["eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al", "nv"];
+
+fn cond(c: u32) -> String {
+    CONDS[c as usize & 0xf].to_string()
+}
+
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+/// 逻辑立即数（DecodeBitMasks）
+fn decode_bit_masks(n: u32, imms: u32, immr: u32, x: bool) -> Option<u64> {
+    let combined = (n << 6) | (!imms & 0x3f);
+    if combined == 0 {
+        return None;
+    }
+    let len = 31 - combined.leading_zeros();
+    if len < 1 || (!x && n == 1) {
+        return None;
+    }
+    let size = 1u32 << len;
+    let levels = size - 1;
+    let s = imms & levels;
+    let r = immr & levels;
+    if s == levels {
+        return None;
+    }
+    let welem: u64 = (1u64 << (s + 1)) - 1;
+    let emask = if size == 64 { u64::MAX } else { (1u64 << size) - 1 };
+    let elem = if r == 0 { welem } else { ((welem >> r) | (welem << (size - r))) & emask };
+    let mut value = 0u64;
+    let mut pos = 0;
+    while pos < 64 {
+        value |= elem << pos;
+        pos += size;
+    }
+    Some(if x { value } else { value & 0xffff_ffff })
+}
+
+fn data_processing_imm(w: u32, pc: u64) -> Option<Insn> {
+    let x = bit(w, 31);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    match bits(w, 25, 23) {
+        0b000 | 0b001 => {
+            let imm = sign_extend((bits(w, 23, 5) << 2) | bits(w, 30, 29), 21);
+            if x {
+                let target = (pc & !0xfff).wrapping_add((imm << 12) as u64);
+                insn_target("adrp", vec![xreg(rd), format!("{:#x}", target)], 1, target)
+            } else {
+                let target = pc.wrapping_add(imm as u64);
+                insn_target("adr", vec![xreg(rd), format!("{:#x}", target)], 1, target)
+            }
+        }
+        0b010 => {
+            let (op, s, sh) = (bit(w, 30), bit(w, 29), bit(w, 22));
+            let imm12 = bits(w, 21, 10) as i64;
+            let mut ops = vec![reg(rd, x, !s), reg(rn, x, true), imm(imm12)];
+            if sh {
+                ops.push("lsl #12".to_string());
+            }
+            match (op, s) {
+                (false, false) if !sh && imm12 == 0 && (rd == 31 || rn == 31) => insn("mov", vec![reg(rd, x, true), reg(rn, x, true)]),
+                (false, true) if rd == 31 => insn("cmn", ops[1..].to_vec()),
+                (true, true) if rd == 31 => insn("cmp", ops[1..].to_vec()),
+                (false, false) => insn("add", ops),
+                (false, true) => insn("adds", ops),
+                (true, false) => insn("sub", ops),
+                (true, true) => insn("subs", ops),
+            }
+        }
+        0b100 => {
+            let opc = bits(w, 30, 29);
+            let value = decode_bit_masks(bits(w, 22, 22), bits(w, 15, 10), bits(w, 21, 16), x)?;
+            match opc {
+                0 => insn("and", vec![reg(rd, x, true), reg(rn, x, false), hex_imm(value)]),
+                1 if rn == 31 => insn("mov", vec![reg(rd, x, true), mov_imm(value, x)]),
+                1 => insn("orr", vec![reg(rd, x, true), reg(rn, x, false), hex_imm(value)]),
+                2 => insn("eor", vec![reg(rd, x, true), reg(rn, x, false), hex_imm(value)]),
+                _ if rd == 31 => insn("tst", vec![reg(rn, x, false), hex_imm(value)]),
+                _ => insn("ands", vec![reg(rd, x, false), reg(rn, x, false), hex_imm(value)]),
+            }
+        }
+        0b101 => {
+            let opc = bits(w, 30, 29);
+            let hw = bits(w, 22, 21);
+            let imm16 = bits(w, 20, 5) as u64;
+            if opc == 1 || (!x && hw >= 2) {
+                return None;
+            }
+            let shifted = imm16 << (hw * 16);
+            let mask = if x { u64::MAX } else { 0xffff_ffff };
+            match opc {
+                0 if !(imm16 == 0 && hw != 0) && (x || imm16 != 0xffff) => insn("mov", vec![reg(rd, x, false), mov_imm(!shifted & mask, x)]),
+                2 if !(imm16 == 0 && hw != 0) => insn("mov", vec![reg(rd, x, false), mov_imm(shifted, x)]),
+                _ => {
+                    let mnemonic = ["movn", "", "movz", "movk"][opc as usize];
+                    let mut ops = vec![reg(rd, x, false), imm(imm16 as i64)];
+                    if hw != 0 {
+                        ops.push(format!("lsl #{}", hw * 16));
+                    }
+                    insn(mnemonic, ops)
+                }
+            }
+        }
+        0b110 => bitfield(w),
+        0b111 => {
+            if bits(w, 30, 29) != 0 || bit(w, 21) || bit(w, 22) != x {
+                return None;
+            }
+            let rm = bits(w, 20, 16);
+            let lsb = bits(w, 15, 10) as i64;
+            if rn == rm {
+                insn("ror", vec![reg(rd, x, false), reg(rn, x, false), imm(lsb)])
+            } else {
+                insn("extr", vec![reg(rd, x, false), reg(rn, x, false), reg(rm, x, false), imm(lsb)])
+            }
+        }
+        _ => None,
+    }
+}
+
+fn bitfield(w: u32) -> Option<Insn> {
+    let x = bit(w, 31);
+    // 32 位形式的 immr/imms 不能超过 31
+    if bit(w, 22) != x || !x && (bits(w, 21, 16) | bits(w, 15, 10)) & 0x20 != 0 {
+        return None;
+    }
+    let (rd, rn) = (bits(w, 4, 0), bits(w, 9, 5));
+    let immr = bits(w, 21, 16) as i64;
+    let imms = bits(w, 15, 10) as i64;
+    let size: i64 = if x { 64 } else { 32 };
+    let d = reg(rd, x, false);
+    let n = reg(rn, x, false);
+    let wn = reg(rn, false, false);
+    match bits(w, 30, 29) {
+        0 => {
+            if imms == size - 1 {
+                insn("asr", vec![d, n, imm(immr)])
+            } else if immr == 0 && imms == 7 {
+                insn("sxtb", vec![d, wn])
+            } else if immr == 0 && imms == 15 {
+                insn("sxth", vec![d, wn])
+            } else if immr == 0 && imms == 31 {
+                insn("sxtw", vec![d, wn])
+            } else if imms < immr {
+                insn("sbfiz", vec![d, n, imm(size - immr), imm(imms + 1)])
+            } else {
+                insn("sbfx", vec![d, n, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        1 => {
+            if imms < immr {
+                if rn == 31 {
+                    insn("bfc", vec![d, imm(size - immr), imm(imms + 1)])
+                } else {
+                    insn("bfi", vec![d, n, imm(size - immr), imm(imms + 1)])
+                }
+            } else {
+                insn("bfxil", vec![d, n, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        2 => {
+            if imms != size - 1 && imms + 1 == immr {
+                insn("lsl", vec![d, n, imm(size - 1 - imms)])
+            } else if imms == size - 1 {
+                insn("lsr", vec![d, n, imm(immr)])
+            } else if !x && immr == 0 && imms == 7 {
+                insn("uxtb", vec![d, wn])
+            } else if !x && immr == 0 && imms == 15 {
+                insn("uxth", vec![d, wn])
+            } else if imms < immr {
+                insn("ubfiz", vec![d, n, imm(size - immr), imm(imms + 1)])
+            } else {
+                insn("ubfx", vec![d, n, imm(immr), imm(imms - immr + 1)])
+            }
+        }
+        _ => None,
+    }
+}
+
+fn branch_system(w: u32, pc: u64) -> Option<Insn> {
+    // B / BL
+    if bits(w, 30, 26) == 0b00101 {
+        let target = pc.wrapping_add((sign_extend(bits(w, 25, 0), 26) << 2) as u64);
+        let mnemonic = if bit(w, 31) { "bl" } else { "b" };
+        return insn_target(mnemonic, vec![format!("{:#x}", target)], 0, target);
+    }
+    // CBZ / CBNZ
+    if bits(w, 30, 25) == 0b011010 {
+        let target = pc.wrapping_add((sign_extend(bits(w, 23, 5), 19) << 2) as u64);
+        let mnemonic = if bit(w, 24) { "cbnz" } else { "cbz" };
+        return insn_target(mnemonic, vec![reg(bits(w, 4, 0), bit(w, 31), false), format!("{:#x}", target)], 1, target);
+    }
+    // TBZ / TBNZ
+    if bits(w, 30, 25) == 0b011011 {
+        let target = pc.wrapping_add((sign_extend(bits(w, 18, 5), 14) << 2) as u64);
+        let bitpos = (bits(w, 31, 31) << 5) | bits(w, 23, 19);
+        let mnemonic = if bit(w, 24) { "tbnz" } else { "tbz" };
+        let ops = vec![reg(bits(w, 4, 0), bitpos >= 32, false), imm(bitpos as i64), format!("{:#x}", target)];
+        return insn_target(mnemonic, ops, 2, target);
+    }
+    // B.cond
+    if bits(w, 31, 24) == 0b0101_0100 && !bit(w, 4) {
+        let target = pc.wrapping_add((sign_extend(bits(w, 23, 5), 19) << 2) as u64);
+        return insn_target(&format!("b.{}", cond(bits(w, 3, 0))), vec![format!("{:#x}", target)], 0, target);
+    }
+    // 异常生成
+    if bits(w, 31, 24) == 0b1101_0100 {
+        let imm16 = bits(w, 20, 5) as u64;
+        let mnemonic = match (bits(w, 23, 21), bits(w, 4, 0)) {
+            (0, 1) => "svc",
+            (0, 2) => "hvc",
+            (0, 3) => "smc",
+            (1, 0) => "brk",
+            (2, 0) => "hlt",
+            _ => return None,
+        };
+        return insn(mnemonic, vec![hex_imm(imm16)]);
+    }
+    // 系统指令
+    if bits(w, 31, 22) == 0b11_0101_0100 {
+        return system(w);
+    }
+    // 寄存器跳转
+    if bits(w, 31, 25) == 0b1101011 {
+        if bits(w, 20, 16) != 0b11111 || bits(w, 15, 10) != 0 || bits(w, 4, 0) != 0 {
+            return None;
+        }
+        let rn = bits(w, 9, 5);
+        return match bits(w, 24, 21) {
+            0 => insn("br", vec![xreg(rn)]),
+            1 => insn("blr", vec![xreg(rn)]),
+            2 if rn == 30 => insn("ret", vec![]),
+            2 => insn("ret", vec![xreg(rn)]),
+            4 if rn == 31 => insn("eret", vec![]),
+            5 if rn == 31 => insn("drps", vec![]),
+            _ => None,
+        };
+    }
+    None
+}
+
+const BARRIER_OPTIONS: [&str; 16] = [
+    "#0x0", "oshld", "oshst", "osh", "#0x4", "nshld", "nshst", "nsh",
+    "#0x8", "ishld", "ishst", "ish", "#0xc", "ld", "st", "sy",
+];
+
+/// 系统寄存器编码 (op0, op1, CRn, CRm, op2)
+type SysRegKey = (u32, u32, u32, u32, u32);
+
+/// 常用系统寄存器 -> 名字
+const SYSREGS: &[(SysRegKey, &str)] = &[
+    ((3, 0, 0, 0, 0), "MIDR_EL1"),
+    ((3, 0, 0, 0, 5), "MPIDR_EL1"),
+    ((3, 0, 0, 4, 0), "ID_AA64PFR0_EL1"),
+    ((3, 0, 0, 7, 0), "ID_AA64MMFR0_EL1"),
+    ((3, 0, 1, 0, 0), "SCTLR_EL1"),
+    ((3, 0, 1, 0, 2), "CPACR_EL1"),
+    ((3, 0, 2, 0, 0), "TTBR0_EL1"),
+    ((3, 0, 2, 0, 1), "TTBR1_EL1"),
+    ((3, 0, 2, 0, 2), "TCR_EL1"),
+    ((3, 0, 4, 0, 0), "SPSR_EL1"),
+    ((3, 0, 4, 0, 1), "ELR_EL1"),
+    ((3, 0, 4, 1, 0), "SP_EL0"),
+    ((3, 0, 4, 2, 0), "SPSel"),
+    ((3, 0, 4, 2, 2), "CurrentEL"),
+    ((3, 0, 5, 2, 0), "ESR_EL1"),
+    ((3, 0, 6, 0, 0), "FAR_EL1"),
+    ((3, 0, 7, 4, 0), "PAR_EL1"),
+    ((3, 0, 10, 2, 0), "MAIR_EL1"),
+    ((3, 0, 10, 3, 0), "AMAIR_EL1"),
+    ((3, 0, 12, 0, 0), "VBAR_EL1"),
+    ((3, 0, 13, 0, 1), "CONTEXTIDR_EL1"),
+    ((3, 0, 13, 0, 4), "TPIDR_EL1"),
+    ((3, 0, 14, 1, 0), "CNTKCTL_EL1"),
+    ((3, 3, 4, 2, 0), "NZCV"),
+    ((3, 3, 4, 2, 1), "DAIF"),
+    ((3, 3, 4, 4, 0), "FPCR"),
+    ((3, 3, 4, 4, 1), "FPSR"),
+    ((3, 3, 13, 0, 2), "TPIDR_EL0"),
+    ((3, 3, 13, 0, 3), "TPIDRRO_EL0"),
+    ((3, 3, 14, 0, 0), "CNTFRQ_EL0"),
+    ((3, 3, 14, 0, 1), "CNTPCT_EL0"),
+    ((3, 3, 14, 0, 2), "CNTVCT_EL0"),
+    ((3, 3, 14, 2, 0), "CNTP_TVAL_EL0"),
+    ((3, 3, 14, 2, 1), "CNTP_CTL_EL0"),
+    ((3, 3, 14, 2, 2), "CNTP_CVAL_EL0"),
+    ((3, 3, 14, 3, 0), "CNTV_TVAL_EL0"),
+    ((3, 3, 14, 3, 1), "CNTV_CTL_EL0"),
+    ((3, 3, 14, 3, 2), "CNTV_CVAL_EL0"),
+    ((3, 4, 1, 0, 0), "SCTLR_EL2"),
+    ((3, 4, 1, 1, 0), "HCR_EL2"),
+    ((3, 4, 4, 0, 0), "SPSR_EL2"),
+    ((3, 4, 4, 0, 1), "ELR_EL2"),
+    ((3, 4, 4, 1, 0), "SP_EL1"),
+    ((3, 4, 12, 0, 0), "VBAR_EL2"),
+    ((3, 4, 13, 0, 2), "TPIDR_EL2"),
+    ((3, 4, 14, 0, 3), "CNTVOFF_EL2"),
+    ((3, 4, 14, 1, 0), "CNTHCTL_EL2"),
+];
+
+/// SYS 操作编码 (op1, CRn, CRm, op2)
+type SysOpKey = (u32, u32, u32, u32);
+
+/// SYS 别名 -> (助记符, 操作, 是否带寄存器)
+const SYS_OPS: &[(SysOpKey, &str, &str, bool)] = &[
+    ((0, 7, 1, 0), "ic", "ialluis", false),
+    ((0, 7, 5, 0), "ic", "iallu", false),
+    ((3, 7, 5, 1), "ic", "ivau", true),
+    ((0, 7, 6, 1), "dc", "ivac", true),
+    ((0, 7, 6, 2), "dc", "isw", true),
+    ((0, 7, 10, 2), "dc", "csw", true),
+    ((0, 7, 14, 2), "dc", "cisw", true),
+    ((3, 7, 4, 1), "dc", "zva", true),
+    ((3, 7, 10, 1), "dc", "cvac", true),
+    ((3, 7, 11, 1), "dc", "cvau", true),
+    ((3, 7, 14, 1), "dc", "civac", true),
+    ((0, 8, 3, 0), "tlbi", "vmalle1is", false),
+    ((0, 8, 3, 1), "tlbi", "vae1is", true),
+    ((0, 8, 3, 2), "tlbi", "aside1is", true),
+    ((0, 8, 7, 0), "tlbi", "vmalle1", false),
+    ((0, 8, 7, 1), "tlbi", "vae1", true),
+    ((0, 8, 7, 2), "tlbi", "aside1", true),
+    ((0, 8, 7, 3), "tlbi", "vaae1", true),
+    ((0, 8, 7, 5), "tlbi", "vale1", true),
+    ((0, 8, 7, 7), "tlbi", "vaale1", true),
+    ((4, 8, 7, 0), "tlbi", "alle2", false),
+    ((4, 8, 7, 4), "tlbi", "alle1", false),
+];
+
+fn sysreg_name(key: SysRegKey) -> String {
+    SYSREGS.iter().find(|(k, _)| *k == key).map(|(_, n)| n.to_string())
+        .unwrap_or_else(|| format!("S{}_{}_C{}_C{}_{}", key.0, key.1, key.2, key.3, key.4))
+}
+
+fn system(w: u32) -> Option<Insn> {
+    let l = bit(w, 21);
+    let op0 = bits(w, 20, 19);
+    let op1 = bits(w, 18, 16);
+    let crn = bits(w, 15, 12);
+    let crm = bits(w, 11, 8);
+    let op2 = bits(w, 7, 5);
+    let rt = bits(w, 4, 0);
+    match (l, op0) {
+        (false, 0) if crn == 2 && op1 == 3 && rt == 31 => {
+            let hint = (crm << 3) | op2;
+            match hint {
+                0 => insn("nop", vec![]),
+                1 => insn("yield", vec![]),
+                2 => insn("wfe", vec![]),
+                3 => insn("wfi", vec![]),
+                4 => insn("sev", vec![]),
+                5 => insn("sevl", vec![]),
+                7 => insn("xpaclri", vec![]),
+                25 => insn("paciasp", vec![]),
+                29 => insn("autiasp", vec![]),
+                32 => insn("bti", vec![]),
+                34 => insn("bti", vec!["c".to_string()]),
+                36 => insn("bti", vec!["j".to_string()]),
+                38 => insn("bti", vec!["jc".to_string()]),
+                _ => insn("hint", vec![hex_imm(hint as u64)]),
+            }
+        }
+        (false, 0) if crn == 3 && op1 == 3 && rt == 31 => match op2 {
+            2 => insn("clrex", if crm == 15 { vec![] } else { vec![hex_imm(crm as u64)] }),
+            4 => insn("dsb", vec![BARRIER_OPTIONS[crm as usize].to_string()]),
+            5 => insn("dmb", vec![BARRIER_OPTIONS[crm as usize].to_string()]),
+            6 => insn("isb", if crm == 15 { vec![] } else { vec![hex_imm(crm as u64)] }),
+            _ => None,
+        },
+        (false, 0) if crn == 4 && rt == 31 => {
+            let field = match (op1, op2) {
+                (0, 5) => "SPSel".to_string(),
+                (3, 6) => "DAIFSet".to_string(),
+                (3, 7) => "DAIFClr".to_string(),
+                _ => format!("S0_{}_C4_C{}_{}", op1, crm, op2),
+            };
+            insn("msr", vec![field, imm(crm as i64)])
+        }
+        (_, 1) => {
+            if !l {
+                if let Some((_, m, op, has_reg)) = SYS_OPS.iter().find(|(k, ..)| *k == (op1, crn, crm, op2)) {
+                    let mut ops = vec![op.to_string()];
+                    if *has_reg || rt != 31 {
+                        ops.push(xreg(rt));
+                    }
+                    return insn(m, ops);
+                }
+            }
+            let mut ops = vec![imm(op1 as i64), format!("c{}", crn), format!("c{}", crm), imm(op2 as i64)];
+            if l {
+                ops.insert(0, xreg(rt));
+                insn("sysl", ops)
+            } else {
+                if rt != 31 {
+                    ops.push(xreg(rt));
+                }
+                insn("sys", ops)
+            }
+        }
+        (false, 2 | 3) => insn("msr", vec![sysreg_name((op0, op1, crn, crm, op2)), xreg(rt)]),
+        (true, 2 | 3) => insn("mrs", vec![xreg(rt), sysreg_name((op0, op1, crn, crm, op2))]),
+        _ => None,
+    }
+}
+
+/// PRFM 的预取操作名，未定义的编码按数字显示
+fn prfop(rt: u32) -> String {
+    let (kind, target, policy) = (rt >> 3, (rt >> 1) & 3, rt & 1);
+    if kind < 3 && target < 3 {
+        format!("{}l{}{}", ["pld", "pli", "pst"][kind as usize], target + 1, ["keep", "strm"][policy as usize])
+    } else {
+        imm(rt as i64)
+    }
+}
+
+/// SIMD/FP 寄存器名：大小以字节的 log2 表示（0=b ... 4=q）
+fn vreg(n: u32, log2: u32) -> String {
+    format!("{}{}", ["b", "h", "s", "d", "q"][log2 as usize], n)
+}
+
+/// 基址 + 立即数偏移的内存操作数
+fn mem_offset(rn: u32, offset: i64) -> String {
+    if offset == 0 {
+        format!("[{}]", xreg_sp(rn))
+    } else {
+        format!("[{}, #{}]", xreg_sp(rn), offset)
+    }
+}
+
+fn load_store(w: u32, pc: u64) -> Option<Insn> {
+    let v = bit(w, 26);
+    let rt = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+
+    // 独占/获取-释放
+    if bits(w, 29, 24) == 0b001000 && !v {
+        return load_store_exclusive(w);
+    }
+    // LDR (literal)
+    if bits(w, 29, 27) == 0b011 && bits(w, 25, 24) == 0 {
+        let target = pc.wrapping_add((sign_extend(bits(w, 23, 5), 19) << 2) as u64);
+        let opc = bits(w, 31, 30);
+        let (mnemonic, rt_name) = match (v, opc) {
+            (false, 0) => ("ldr", reg(rt, false, false)),
+            (false, 1) => ("ldr", xreg(rt)),
+            (false, 2) => ("ldrsw", xreg(rt)),
+            (false, _) => ("prfm", prfop(rt)),
+            (true, 3) => return None,
+            (true, _) => ("ldr", vreg(rt, opc + 2)),
+        };
+        return insn_target(mnemonic, vec![rt_name, format!("{:#x}", target)], 1, target);
+    }
+    // LDP/STP
+    if bits(w, 29, 27) == 0b101 {
+        let opc = bits(w, 31, 30);
+        let l = bit(w, 22);
+        let rt2 = bits(w, 14, 10);
+        let (r1, r2, scale, base) = match (v, opc) {
+            (false, 0) => (reg(rt, false, false), reg(rt2, false, false), 2, "p"),
+            // LDPSW 没有非临时（mode 0）形式
+            (false, 1) if l && bits(w, 24, 23) != 0 => (xreg(rt), xreg(rt2), 2, "psw"),
+            (false, 2) => (xreg(rt), xreg(rt2), 3, "p"),
+            (true, 0..=2) => (vreg(rt, opc + 2), vreg(rt2, opc + 2), opc + 2, "p"),
+            _ => return None,
+        };
+        let offset = sign_extend(bits(w, 21, 15), 7) << scale;
+        let mode = bits(w, 24, 23);
+        let prefix = match (l, mode) {
+            (false, 0) => "stn",
+            (true, 0) => "ldn",
+            (false, _) => "st",
+            (true, _) => "ld",
+        };
+        let addr = match mode {
+            1 => vec![format!("[{}]", xreg_sp(rn)), imm(offset)],
+            3 => vec![format!("[{}, #{}]!", xreg_sp(rn), offset)],
+            _ => vec![mem_offset(rn, offset)],
+        };
+        let mut ops = vec![r1, r2];
+        ops.extend(addr);
+        return insn(&format!("{}{}", prefix, base), ops);
+    }
+    // 单寄存器加载存储
+    if bits(w, 29, 27) == 0b111 {
+        let size = bits(w, 31, 30);
+        let opc = bits(w, 23, 22);
+        let (name, rt_name, scale) = if v {
+            let log2 = if opc >= 2 { if size != 0 { return None; } 4 } else { size };
+            (if opc & 1 == 1 { "ldr" } else { "str" }, vreg(rt, log2), log2)
+        } else {
+            let (name, x) = match (size, opc) {
+                (0, 0) => ("strb", false),
+                (0, 1) => ("ldrb", false),
+                (0, 2) => ("ldrsb", true),
+                (0, 3) => ("ldrsb", false),
+                (1, 0) => ("strh", false),
+                (1, 1) => ("ldrh", false),
+                (1, 2) => ("ldrsh", true),
+                (1, 3) => ("ldrsh", false),
+                (2, 0) => ("str", false),
+                (2, 1) => ("ldr", false),
+                (2, 2) => ("ldrsw", true),
+                (3, 0) => ("str", true),
+                (3, 1) => ("ldr", true),
+                (3, 2) => ("prfm", true),
+                _ => return None,
+            };
+            let rt_name = if name == "prfm" { prfop(rt) } else { reg(rt, x, false) };
+            (name, rt_name, size)
+        };
+        if bit(w, 24) {
+            let offset = (bits(w, 21, 10) as i64) << scale;
+            return insn(name, vec![rt_name, mem_offset(rn, offset)]);
+        }
+        if !bit(w, 21) {
+            let offset = sign_extend(bits(w, 20, 12), 9);
+            return match bits(w, 11, 10) {
+                0 => {
+                    // LDUR/STUR 系列：ldr -> ldur、strb -> sturb、prfm -> prfum
+                    let unscaled = if name == "prfm" { "prfum".to_string() } else { format!("{}u{}", &name[..2], &name[2..]) };
+                    insn(&unscaled, vec![rt_name, mem_offset(rn, offset)])
+                }
+                1 => insn(name, vec![rt_name, format!("[{}]", xreg_sp(rn)), imm(offset)]),
+                2 => {
+                    if v || name == "prfm" {
+                        return None;
+                    }
+                    insn(&format!("{}t{}", &name[..2], &name[2..]), vec![rt_name, mem_offset(rn, offset)])
+                }
+                _ => insn(name, vec![rt_name, format!("[{}, #{}]!", xreg_sp(rn), offset)]),
+            };
+        }
+        if bits(w, 11, 10) == 2 {
+            let rm = bits(w, 20, 16);
+            let option = bits(w, 15, 13);
+            let s = bit(w, 12);
+            let index = match option {
+                2 => "uxtw",
+                3 => "lsl",
+                6 => "sxtw",
+                7 => "sxtx",
+                _ => return None,
+            };
+            let rm_name = reg(rm, option & 1 == 1, false);
+            let mem = match (index, s) {
+                ("lsl", false) => format!("[{}, {}]", xreg_sp(rn), rm_name),
+                (_, false) => format!("[{}, {}, {}]", xreg_sp(rn), rm_name, index),
+                (_, true) => format!("[{}, {}, {} #{}]", xreg_sp(rn), rm_name, index, scale),
+            };
+            return insn(name, vec![rt_name, mem]);
+        }
+    }
+    None
+}
+
+fn load_store_exclusive(w: u32) -> Option<Insn> {
+    let size = bits(w, 31, 30);
+    let o2 = bit(w, 23);
+    let l = bit(w, 22);
+    let o1 = bit(w, 21);
+    let rs = bits(w, 20, 16);
+    let o0 = bit(w, 15);
+    let rt2 = bits(w, 14, 10);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    let suffix = ["b", "h", "", ""][size as usize];
+    let x = size == 3;
+    let mem = format!("[{}]", xreg_sp(rn));
+    match (o2, o1) {
+        (false, false) => {
+            let base = match (l, o0) {
+                (false, false) => "stxr",
+                (false, true) => "stlxr",
+                (true, false) => "ldxr",
+                (true, true) => "ldaxr",
+            };
+            let mnemonic = format!("{}{}", base, suffix);
+            if l {
+                insn(&mnemonic, vec![reg(rt, x, false), mem])
+            } else {
+                insn(&mnemonic, vec![reg(rs, false, false), reg(rt, x, false), mem])
+            }
+        }
+        (false, true) if size >= 2 => {
+            let base = match (l, o0) {
+                (false, false) => "stxp",
+                (false, true) => "stlxp",
+                (true, false) => "ldxp",
+                (true, true) => "ldaxp",
+            };
+            if l {
+                insn(base, vec![reg(rt, x, false), reg(rt2, x, false), mem])
+            } else {
+                insn(base, vec![reg(rs, false, false), reg(rt, x, false), reg(rt2, x, false), mem])
+            }
+        }
+        (true, false) => {
+            let base = match (l, o0) {
+                (false, false) => "stllr",
+                (false, true) => "stlr",
+                (true, false) => "ldlar",
+                (true, true) => "ldar",
+            };
+            insn(&format!("{}{}", base, suffix), vec![reg(rt, x, false), mem])
+        }
+        _ => None,
+    }
+}
+
+fn data_processing_reg(w: u32) -> Option<Insn> {
+    let x = bit(w, 31);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    let d = reg(rd, x, false);
+    let n = reg(rn, x, false);
+    let m = reg(rm, x, false);
+    if !bit(w, 28) {
+        if !bit(w, 24) {
+            // 逻辑运算（移位寄存器）
+            let shift = bits(w, 23, 22);
+            let amount = bits(w, 15, 10);
+            if !x && amount >= 32 {
+                return None;
+            }
+            let shifted = |mut ops: Vec<String>| {
+                if amount != 0 || shift != 0 {
+                    ops.push(format!("{} #{}", SHIFTS[shift as usize], amount));
+                }
+                ops
+            };
+            let mnemonic = match (bits(w, 30, 29), bit(w, 21)) {
+                (1, false) if rn == 31 && shift == 0 && amount == 0 => return insn("mov", vec![d, m]),
+                (1, true) if rn == 31 => return insn("mvn", shifted(vec![d, m])),
+                (3, false) if rd == 31 => return insn("tst", shifted(vec![n, m])),
+                (0, false) => "and",
+                (0, true) => "bic",
+                (1, false) => "orr",
+                (1, true) => "orn",
+                (2, false) => "eor",
+                (2, true) => "eon",
+                (3, false) => "ands",
+                _ => "bics",
+            };
+            return insn(mnemonic, shifted(vec![d, n, m]));
+        }
+        let (op, s) = (bit(w, 30), bit(w, 29));
+        if !bit(w, 21) {
+            // 加减（移位寄存器）
+            let shift = bits(w, 23, 22);
+            let amount = bits(w, 15, 10);
+            if shift == 3 || (!x && amount >= 32) {
+                return None;
+            }
+            let mut tail = vec![m];
+            if amount != 0 {
+                tail.push(format!("{} #{}", SHIFTS[shift as usize], amount));
+            }
+            let with = |head: Vec<String>| head.into_iter().chain(tail.clone()).collect::<Vec<_>>();
+            return match (op, s) {
+                (false, true) if rd == 31 => insn("cmn", with(vec![n])),
+                (true, true) if rd == 31 => insn("cmp", with(vec![n])),
+                (true, false) if rn == 31 => insn("neg", with(vec![d])),
+                (true, true) if rn == 31 => insn("negs", with(vec![d])),
+                (false, false) => insn("add", with(vec![d, n])),
+                (false, true) => insn("adds", with(vec![d, n])),
+                (true, false) => insn("sub", with(vec![d, n])),
+                (true, true) => insn("subs", with(vec![d, n])),
+            };
+        }
+        // 加减（扩展寄存器）
+        if bits(w, 23, 22) != 0 {
+            return None;
+        }
+        let option = bits(w, 15, 13);
+        let amount = bits(w, 12, 10);
+        if amount > 4 {
+            return None;
+        }
+        let rm_name = reg(rm, x && option & 3 == 3, false);
+        let lsl_ok = (rd == 31 || rn == 31) && option == if x { 3 } else { 2 };
+        let extend = if lsl_ok {
+            (amount != 0).then(|| format!("lsl #{}", amount))
+        } else {
+            let name = ["uxtb", "uxth", "uxtw", "uxtx", "sxtb", "sxth", "sxtw", "sxtx"][option as usize];
+            Some(if amount != 0 { format!("{} #{}", name, amount) } else { name.to_string() })
+        };
+        let mut tail = vec![rm_name];
+        tail.extend(extend);
+        let dst = reg(rd, x, !s);
+        let src = reg(rn, x, true);
+        let with = |head: Vec<String>| head.into_iter().chain(tail.clone()).collect::<Vec<_>>();
+        return match (op, s) {
+            (false, true) if rd == 31 => insn("cmn", with(vec![src])),
+            (true, true) if rd == 31 => insn("cmp", with(vec![src])),
+            (false, false) => insn("add", with(vec![dst, src])),
+            (false, true) => insn("adds", with(vec![dst, src])),
+            (true, false) => insn("sub", with(vec![dst, src])),
+            (true, true) => insn("subs", with(vec![dst, src])),
+        };
+    }
+
+    let (op, s) = (bit(w, 30), bit(w, 29));
+    match bits(w, 24, 21) {
+        0b0000 if bits(w, 15, 10) == 0 => match (op, s) {
+            (false, false) => insn("adc", vec![d, n, m]),
+            (false, true) => insn("adcs", vec![d, n, m]),
+            (true, false) if rn == 31 => insn("ngc", vec![d, m]),
+            (true, true) if rn == 31 => insn("ngcs", vec![d, m]),
+            (true, false) => insn("sbc", vec![d, n, m]),
+            (true, true) => insn("sbcs", vec![d, n, m]),
+        },
+        0b0010 if s && !bit(w, 10) && !bit(w, 4) => {
+            let second = if bit(w, 11) { imm(rm as i64) } else { m };
+            let mnemonic = if op { "ccmp" } else { "ccmn" };
+            insn(mnemonic, vec![n, second, imm(bits(w, 3, 0) as i64), cond(bits(w, 15, 12))])
+        }
+        0b0100 if !s => {
+            let c = bits(w, 15, 12);
+            let inverted = cond(c ^ 1);
+            let usable = c < 14;
+            match (op, bits(w, 11, 10)) {
+                (false, 0) => insn("csel", vec![d, n, m, cond(c)]),
+                (false, 1) if usable && rn == 31 && rm == 31 => insn("cset", vec![d, inverted]),
+                (false, 1) if usable && rn == rm => insn("cinc", vec![d, n, inverted]),
+                (false, 1) => insn("csinc", vec![d, n, m, cond(c)]),
+                (true, 0) if usable && rn == 31 && rm == 31 => insn("csetm", vec![d, inverted]),
+                (true, 0) if usable && rn == rm => insn("cinv", vec![d, n, inverted]),
+                (true, 0) => insn("csinv", vec![d, n, m, cond(c)]),
+                (true, 1) if usable && rn == rm => insn("cneg", vec![d, n, inverted]),
+                (true, 1) => insn("csneg", vec![d, n, m, cond(c)]),
+                _ => None,
+            }
+        }
+        0b0110 if !s => {
+            if !op {
+                let mnemonic = match bits(w, 15, 10) {
+                    2 => "udiv",
+                    3 => "sdiv",
+                    8 => "lsl",
+                    9 => "lsr",
+                    10 => "asr",
+                    11 => "ror",
+                    _ => return None,
+                };
+                insn(mnemonic, vec![d, n, m])
+            } else {
+                if rm != 0 {
+                    return None;
+                }
+                let mnemonic = match (bits(w, 15, 10), x) {
+                    (0, _) => "rbit",
+                    (1, _) => "rev16",
+                    (2, false) => "rev",
+                    (2, true) => "rev32",
+                    (3, true) => "rev",
+                    (4, _) => "clz",
+                    (5, _) => "cls",
+                    _ => return None,
+                };
+                insn(mnemonic, vec![d, n])
+            }
+        }
+        0b1000..=0b1111 if bits(w, 30, 29) == 0 => {
+            let ra = bits(w, 14, 10);
+            let o0 = bit(w, 15);
+            let op31 = bits(w, 23, 21);
+            let wn = reg(rn, false, false);
+            let wm = reg(rm, false, false);
+            match (op31, o0) {
+                (0, false) if ra == 31 => insn("mul", vec![d, n, m]),
+                (0, false) => insn("madd", vec![d, n, m, reg(ra, x, false)]),
+                (0, true) if ra == 31 => insn("mneg", vec![d, n, m]),
+                (0, true) => insn("msub", vec![d, n, m, reg(ra, x, false)]),
+                _ if !x => None,
+                (1, false) if ra == 31 => insn("smull", vec![d, wn, wm]),
+                (1, false) => insn("smaddl", vec![d, wn, wm, xreg(ra)]),
+                (1, true) if ra == 31 => insn("smnegl", vec![d, wn, wm]),
+                (1, true) => insn("smsubl", vec![d, wn, wm, xreg(ra)]),
+                (2, false) => insn("smulh", vec![d, n, m]),
+                (5, false) if ra == 31 => insn("umull", vec![d, wn, wm]),
+                (5, false) => insn("umaddl", vec![d, wn, wm, xreg(ra)]),
+                (5, true) if ra == 31 => insn("umnegl", vec![d, wn, wm]),
+                (5, true) => insn("umsubl", vec![d, wn, wm, xreg(ra)]),
+                (6, false) => insn("umulh", vec![d, n, m]),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// FMOV (立即数) 的 8 位编码展开为数值
+fn fp_imm8(imm8: u32) -> f64 {
+    let sign = if imm8 & 0x80 != 0 { -1.0 } else { 1.0 };
+    let b = imm8 & 0x40 != 0;
+    let cd = ((imm8 >> 4) & 3) as i32;
+    let exp = if b { cd - 3 } else { cd + 1 };
+    sign * (1.0 + (imm8 & 0xf) as f64 / 16.0) * 2f64.powi(exp)
+}
+
+fn simd_fp(w: u32) -> Option<Insn> {
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    // 标量浮点：ftype 0=单精度 1=双精度 3=半精度
+    if bits(w, 30, 24) == 0b0011110 && bit(w, 21) {
+        let log2 = match bits(w, 23, 22) {
+            0 => 2,
+            1 => 3,
+            3 => 1,
+            _ => return None,
+        };
+        let fd = vreg(rd, log2);
+        let fname = vreg(rn, log2);
+        let fm = vreg(rm, log2);
+        let sf = bit(w, 31);
+        if bits(w, 15, 10) == 0 {
+            // 浮点与整数之间的转换
+            let gp = |r: u32| reg(r, sf, false);
+            let op = (bits(w, 20, 19), bits(w, 18, 16));
+            return match op {
+                (0, 2) => insn("scvtf", vec![fd, gp(rn)]),
+                (0, 3) => insn("ucvtf", vec![fd, gp(rn)]),
+                (3, 0) => insn("fcvtzs", vec![gp(rd), fname]),
+                (3, 1) => insn("fcvtzu", vec![gp(rd), fname]),
+                (0, 0) => insn("fcvtns", vec![gp(rd), fname]),
+                (0, 1) => insn("fcvtnu", vec![gp(rd), fname]),
+                (0, 4) => insn("fcvtas", vec![gp(rd), fname]),
+                (0, 5) => insn("fcvtau", vec![gp(rd), fname]),
+                (1, 0) => insn("fcvtps", vec![gp(rd), fname]),
+                (1, 1) => insn("fcvtpu", vec![gp(rd), fname]),
+                (2, 0) => insn("fcvtms", vec![gp(rd), fname]),
+                (2, 1) => insn("fcvtmu", vec![gp(rd), fname]),
+                (0, 6) => insn("fmov", vec![gp(rd), fname]),
+                (0, 7) => insn("fmov", vec![fd, gp(rn)]),
+                _ => None,
+            };
+        }
+        if sf {
+            return None;
+        }
+        if bits(w, 11, 10) == 2 {
+            let mnemonic = match bits(w, 15, 12) {
+                0 => "fmul",
+                1 => "fdiv",
+                2 => "fadd",
+                3 => "fsub",
+                4 => "fmax",
+                5 => "fmin",
+                6 => "fmaxnm",
+                7 => "fminnm",
+                8 => "fnmul",
+                _ => return None,
+            };
+            return insn(mnemonic, vec![fd, fname, fm]);
+        }
+        if bits(w, 11, 10) == 3 {
+            return insn("fcsel", vec![fd, fname, fm, cond(bits(w, 15, 12))]);
+        }
+        if bits(w, 14, 10) == 0b10000 {
+            return match bits(w, 20, 15) {
+                0 => insn("fmov", vec![fd, fname]),
+                1 => insn("fabs", vec![fd, fname]),
+                2 => insn("fneg", vec![fd, fname]),
+                3 => insn("fsqrt", vec![fd, fname]),
+                4 => insn("fcvt", vec![vreg(rd, 2), fname]),
+                5 => insn("fcvt", vec![vreg(rd, 3), fname]),
+                7 => insn("fcvt", vec![vreg(rd, 1), fname]),
+                _ => None,
+            };
+        }
+        if bits(w, 13, 10) == 0b1000 {
+            return match bits(w, 4, 0) {
+                0 => insn("fcmp", vec![fname, fm]),
+                8 => insn("fcmp", vec![fname, "#0.0".to_string()]),
+                16 => insn("fcmpe", vec![fname, fm]),
+                24 => insn("fcmpe", vec![fname, "#0.0".to_string()]),
+                _ => None,
+            };
+        }
+        if bits(w, 12, 10) == 0b100 && bits(w, 9, 5) == 0 {
+            return insn("fmov", vec![fd, format!("#{:.8}", fp_imm8(bits(w, 20, 13)))]);
+        }
+        return None;
+    }
+    // 标量浮点乘加
+    if bits(w, 31, 24) == 0b0001_1111 {
+        let log2 = match bits(w, 23, 22) {
+            0 => 2,
+            1 => 3,
+            3 => 1,
+            _ => return None,
+        };
+        let mnemonic = match (bit(w, 21), bit(w, 15)) {
+            (false, false) => "fmadd",
+            (false, true) => "fmsub",
+            (true, false) => "fnmadd",
+            (true, true) => "fnmsub",
+        };
+        return insn(mnemonic, vec![vreg(rd, log2), vreg(rn, log2), vreg(rm, log2), vreg(bits(w, 14, 10), log2)]);
+    }
+    // 向量按位运算：AND/ORR/EOR（以及 mov 别名）
+    if !bit(w, 31) && bits(w, 28, 24) == 0b01110 && bit(w, 21) && bits(w, 15, 10) == 0b000111 {
+        let arrangement = if bit(w, 30) { "16b" } else { "8b" };
+        let v = |r: u32| format!("v{}.{}", r, arrangement);
+        return match (bit(w, 29), bits(w, 23, 22)) {
+            (false, 0) => insn("and", vec![v(rd), v(rn), v(rm)]),
+            (false, 2) if rn == rm => insn("mov", vec![v(rd), v(rn)]),
+            (false, 2) => insn("orr", vec![v(rd), v(rn), v(rm)]),
+            (true, 0) => insn("eor", vec![v(rd), v(rn), v(rm)]),
+            _ => None,
+        };
+    }
+    // MOVI（字节与 64 位形式）
+    if !bit(w, 31) && bits(w, 28, 19) == 0b0111100000 && bits(w, 11, 10) == 0b01 && bits(w, 15, 12) == 0b1110 {
+        let abc = bits(w, 18, 16);
+        let defgh = bits(w, 9, 5);
+        let imm8 = (abc << 5) | defgh;
+        let q = bit(w, 30);
+        if bit(w, 29) {
+            let mut value = 0u64;
+            for i in 0..8 {
+                if imm8 & (1 << i) != 0 {
+                    value |= 0xff << (i * 8);
+                }
+            }
+            return if q {
+                insn("movi", vec![format!("v{}.2d", rd), hex_imm(value)])
+            } else {
+                insn("movi", vec![vreg(rd, 3), hex_imm(value)])
+            };
+        }
+        let arrangement = if q { "16b" } else { "8b" };
+        return insn("movi", vec![format!("v{}.{}", rd, arrangement), imm(imm8 as i64)]);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dis(word: u32, pc: u64) -> String {
+        decode(word, pc).to_string().replace('\t', " ")
+    }
+
+    #[test]
+    fn decodes_common_instructions() {
+        // 期望值来自 llvm-mc 汇编后 llvm-objdump 的输出
+        let cases: &[(u32, u64, &str)] = &[
+            (0x90000000, 0x4000_0000, "adrp x0, 0x40000000"),
+            (0x91008000, 0, "add x0, x0, #32"),
+            (0x94000002, 0x4000_0010, "bl 0x40000018"),
+            (0xd65f03c0, 0, "ret"),
+            (0xa9bf7bfd, 0, "stp x29, x30, [sp, #-16]!"),
+            (0xa8c17bfd, 0, "ldp x29, x30, [sp], #16"),
+            (0x910003fd, 0, "mov x29, sp"),
+            (0xf9400401, 0, "ldr x1, [x0, #8]"),
+            (0xb85fc020, 0, "ldur w0, [x1, #-4]"),
+            (0x38616800, 0, "ldrb w0, [x0, x1]"),
+            (0x385ff020, 0, "ldurb w0, [x1, #-1]"),
+            (0xb89fc020, 0, "ldursw x0, [x1, #-4]"),
+            (0xf8617800, 0, "ldr x0, [x0, x1, lsl #3]"),
+            (0xd2800540, 0, "mov x0, #42"),
+            (0xf2a24680, 0, "movk x0, #4660, lsl #16"),
+            (0x12800000, 0, "mov w0, #-1"),
+            (0xd3607c00, 0, "lsl x0, x0, #32"),
+            (0x53087c20, 0, "lsr w0, w1, #8"),
+            (0x92401c00, 0, "and x0, x0, #0xff"),
+            (0xf100041f, 0, "cmp x0, #1"),
+            (0xeb01001f, 0, "cmp x0, x1"),
+            (0xaa0103e0, 0, "mov x0, x1"),
+            (0x1a9f17e0, 0, "cset w0, eq"),
+            (0x54000040, 0x100, "b.eq 0x108"),
+            (0xb4000041, 0x100, "cbz x1, 0x108"),
+            (0x37080040, 0x100, "tbnz w0, #1, 0x108"),
+            (0x9b027c20, 0, "mul x0, x1, x2"),
+            (0x9ac20c20, 0, "sdiv x0, x1, x2"),
+            (0xd4000001, 0, "svc #0"),
+            (0xd4207d00, 0, "brk #0x3e8"),
+            (0xf9800000, 0, "prfm pldl1keep, [x0]"),
+            (0xd503201f, 0, "nop"),
+            (0xd5033fdf, 0, "isb"),
+            (0xd5033b9f, 0, "dsb ish"),
+            (0xd5381000, 0, "mrs x0, SCTLR_EL1"),
+            (0xd518c000, 0, "msr VBAR_EL1, x0"),
+            (0xd508871f, 0, "tlbi vmalle1"),
+            (0xc85f7c20, 0, "ldxr x0, [x1]"),
+            (0x1e602820, 0, "fadd d0, d1, d0"),
+            (0x1e6e1000, 0, "fmov d0, #1.00000000"),
+            (0x4ea11c20, 0, "mov v0.16b, v1.16b"),
+            (0x3dc00020, 0, "ldr q0, [x1]"),
+            (0x8b224020, 0, "add x0, x1, w2, uxtw"),
+            (0x0b228020, 0, "add w0, w1, w2, sxtb"),
+            (0xcb2427e3, 0, "sub x3, sp, w4, uxth #1"),
+            (0x8b22683f, 0, "add sp, x1, x2, lsl #2"),
+            (0xcb020c20, 0, "sub x0, x1, x2, lsl #3"),
+            (0xb13ffc20, 0, "adds x0, x1, #4095"),
+            (0xcb0103e0, 0, "neg x0, x1"),
+            (0x6b820fe1, 0, "negs w1, w2, asr #3"),
+            (0x2a2103e0, 0, "mvn w0, w1"),
+            (0x12010020, 0, "and w0, w1, #0x80000000"),
+            (0xb200f3e0, 0, "mov x0, #6148914691236517205"),
+            (0x7200001f, 0, "tst w0, #0x1"),
+            (0x8a220020, 0, "bic x0, x1, x2"),
+            (0x2ae51c83, 0, "orn w3, w4, w5, ror #7"),
+            (0xea021420, 0, "ands x0, x1, x2, lsl #5"),
+            (0x13031c20, 0, "sbfx w0, w1, #3, #5"),
+            (0x93760c20, 0, "sbfiz x0, x1, #10, #4"),
+            (0xd3485c62, 0, "ubfx x2, x3, #8, #16"),
+            (0x531e14a4, 0, "ubfiz w4, w5, #2, #6"),
+            (0xb3781c20, 0, "bfi x0, x1, #8, #8"),
+            (0x33041c62, 0, "bfxil w2, w3, #4, #4"),
+            (0x131f7c20, 0, "asr w0, w1, #31"),
+            (0x53001c20, 0, "uxtb w0, w1"),
+            (0x93403c20, 0, "sxth x0, w1"),
+            (0x93c21c20, 0, "extr x0, x1, x2, #7"),
+            (0x13810c20, 0, "ror w0, w1, #3"),
+            (0x9b020c20, 0, "madd x0, x1, x2, x3"),
+            (0x1b028c20, 0, "msub w0, w1, w2, w3"),
+            (0x9b227c20, 0, "smull x0, w1, w2"),
+            (0x9bc27c20, 0, "umulh x0, x1, x2"),
+            (0x1ac20820, 0, "udiv w0, w1, w2"),
+            (0x9ac22020, 0, "lsl x0, x1, x2"),
+            (0xdac01020, 0, "clz x0, x1"),
+            (0x5ac00020, 0, "rbit w0, w1"),
+            (0xdac00c20, 0, "rev x0, x1"),
+            (0x5ac00420, 0, "rev16 w0, w1"),
+            (0x9a821020, 0, "csel x0, x1, x2, ne"),
+            (0x1a82b420, 0, "csinc w0, w1, w2, lt"),
+            (0xda815420, 0, "cneg x0, x1, mi"),
+            (0x5a9f93e0, 0, "csetm w0, hi"),
+            (0xfa430804, 0, "ccmp x0, #3, #4, eq"),
+            (0x3a41a000, 0, "ccmn w0, w1, #0, ge"),
+            (0x52bfffe0, 0, "mov w0, #-65536"),
+            (0x92e00000, 0, "movn x0, #0, lsl #48"),
+            (0xd61f0200, 0, "br x16"),
+            (0xd63f0020, 0, "blr x1"),
+            (0xb8404c20, 0, "ldr w0, [x1, #4]!"),
+            (0xf85f8420, 0, "ldr x0, [x1], #-8"),
+            (0xf81f0fe0, 0, "str x0, [sp, #-16]!"),
+            (0x79800020, 0, "ldrsh x0, [x1]"),
+            (0x39c00420, 0, "ldrsb w0, [x1, #1]"),
+            (0xb9800420, 0, "ldrsw x0, [x1, #4]"),
+            (0x29410440, 0, "ldp w0, w1, [x2, #8]"),
+            (0x69410440, 0, "ldpsw x0, x1, [x2, #8]"),
+            (0x68c10440, 0, "ldpsw x0, x1, [x2], #8"),
+            (0xa8000440, 0, "stnp x0, x1, [x2]"),
+            (0xac410440, 0, "ldnp q0, q1, [x2, #32]"),
+            (0x6dbf07e0, 0, "stp d0, d1, [sp, #-16]!"),
+            (0x885ffc20, 0, "ldaxr w0, [x1]"),
+            (0xc802fc20, 0, "stlxr w2, x0, [x1]"),
+            (0xc8dffc20, 0, "ldar x0, [x1]"),
+            (0x889ffc20, 0, "stlr w0, [x1]"),
+            (0xd5033bbf, 0, "dmb ish"),
+            (0xd503207f, 0, "wfi"),
+            (0xd69f03e0, 0, "eret"),
+            (0xd4000022, 0, "hvc #0x1"),
+            (0xd53bd040, 0, "mrs x0, TPIDR_EL0"),
+            (0xd50342df, 0, "msr DAIFSet, #2"),
+            (0x1e220820, 0, "fmul s0, s1, s2"),
+            (0x1e623820, 0, "fsub d0, d1, d2"),
+            (0x1e651883, 0, "fdiv d3, d4, d5"),
+            (0x1e602008, 0, "fcmp d0, #0.0"),
+            (0x1e780020, 0, "fcvtzs w0, d1"),
+            (0x9e620020, 0, "scvtf d0, x1"),
+            (0x9e660020, 0, "fmov x0, d1"),
+            (0x1e270020, 0, "fmov s0, w1"),
+            (0x1e62cc20, 0, "fcsel d0, d1, d2, gt"),
+            (0x1e20c020, 0, "fabs s0, s1"),
+            (0x1e614020, 0, "fneg d0, d1"),
+            (0x1e61c020, 0, "fsqrt d0, d1"),
+            (0x1f420c20, 0, "fmadd d0, d1, d2, d3"),
+            (0xfd400400, 0, "ldr d0, [x0, #8]"),
+            (0xbc237841, 0, "str s1, [x2, x3, lsl #2]"),
+            (0x3cdf0020, 0, "ldur q0, [x1, #-16]"),
+            (0x10000040, 0x1000, "adr x0, 0x1008"),
+            (0x17ffffff, 0x1000, "b 0xffc"),
+            (0x35ffffc3, 0x1000, "cbnz w3, 0xff8"),
+            (0xb6400069, 0x1000, "tbz x9, #40, 0x100c"),
+            (0x18000080, 0x1000, "ldr w0, 0x1010"),
+            // 32 位扩展寄存器形式的 uxtx/sxtx 仍用 W 寄存器
+            (0x2b396948, 0, "adds w8, w10, w25, uxtx #2"),
+            // 未分配的编码：32 位位域的 immr/imms >= 32，LDPSW 的非临时形式
+            (0x133124ec, 0, ".inst 0x133124ec"),
+            (0x3311f387, 0, ".inst 0x3311f387"),
+            (0x68401b85, 0, ".inst 0x68401b85"),
+            (0x00000000, 0, ".inst 0x00000000"),
+        ];
+        for &(word, pc, expected) in cases {
+            assert_eq!(dis(word, pc), expected, "{:#010x}", word);
+        }
+    }
+}
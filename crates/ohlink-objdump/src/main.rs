@@ -1,17 +1,171 @@
-use anyhow::Result;
-use clap::Parser;
-use ohlink_format::{OhlinkFile, LoadCommand};
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser, ValueEnum};
+use ohlink_format::{demangle, is_mapping_symbol, report, OhlibArchive, OhlinkFile, LoadCommand, Nlist64, Relocation64, Section64, SegmentCommand64};
+use std::collections::HashMap;
+
+mod disasm;
 
 #[derive(Parser)]
-#[command(author, version, about = "Display Ohlink file structure", long_about = None)]
+#[command(
+    author,
+    version,
+    about = "Display Ohlink file structure",
+    long_about = None,
+    disable_help_flag = true,
+    after_help = "Without a mode option the file header and load commands are shown (-f).\n\
+                  .ohlib archives are processed member by member.\n\
+                  The first argument may also be a subcommand: header (-f), sections (-h), relocs (-r),\n\
+                  symbols (-t), contents (-s) or disassemble (-d)."
+)]
 struct Args {
-    file: String,
+    /// Ohlink files or .ohlib archives
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    /// Display the file header and load commands
+    #[arg(short = 'f', long)]
+    file_headers: bool,
+
+    /// Display section headers (offsets, alignment, flags)
+    #[arg(short = 'h', long = "section-headers", visible_alias = "headers")]
+    section_headers: bool,
+
+    /// Display relocation entries
+    #[arg(short = 'r', long)]
+    reloc: bool,
+
+    /// Display the symbol table
+    #[arg(short = 't', long)]
+    syms: bool,
+
+    /// Display the full contents of sections (hex and ASCII)
+    #[arg(short = 's', long)]
+    full_contents: bool,
+
+    /// Only process the named section (NAME or SEGMENT,NAME); may be repeated
+    #[arg(short = 'j', long = "section", value_name = "NAME")]
+    sections: Vec<String>,
+
+    /// Display all headers (-f -h -r -t)
+    #[arg(short = 'x', long)]
+    all_headers: bool,
+
+    /// Disassemble executable sections (A64)
+    #[arg(short = 'd', long)]
+    disassemble: bool,
+
+    /// Demangle Rust and C++ symbol names
+    #[arg(short = 'C', long)]
+    demangle: bool,
+
+    /// Output format; json emits header, load commands, sections, relocations and symbols
+    /// of every file (the mode options are ignored)
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// 子命令写法（README 中的 `ohlink-objdump header a.ohlink`）改写为对应的选项
+fn subcommand_flag(arg: &str) -> Option<&'static str> {
+    Some(match arg {
+        "header" => "-f",
+        "sections" => "-h",
+        "relocs" => "-r",
+        "symbols" => "-t",
+        "contents" => "-s",
+        "disassemble" => "-d",
+        _ => return None,
+    })
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-    let data = std::fs::read(&args.file)?;
-    let oh = OhlinkFile::parse(&data)?;
+    let mut argv: Vec<String> = std::env::args().collect();
+    if argv.len() > 2 {
+        if let Some(flag) = subcommand_flag(&argv[1]) {
+            argv[1] = flag.to_string();
+        }
+    }
+    let args = Args::parse_from(argv);
+    let mut json_files = Vec::new();
+    for file in &args.files {
+        let data = std::fs::read(file).with_context(|| format!("Failed to read file: {}", file))?;
+        if data.get(0..4) == Some(&ohlink_format::OHLIB_MAGIC[..]) {
+            let arch = OhlibArchive::parse(&data).with_context(|| format!("Failed to parse Ohlib archive: {}", file))?;
+            if args.format == Format::Text {
+                println!("In archive {}:", file);
+            }
+            for (name, bytes) in arch.members() {
+                let oh = OhlinkFile::parse(bytes).with_context(|| format!("Failed to parse member {}({})", file, name))?;
+                match args.format {
+                    Format::Text => dump(&oh, &format!("{}({})", file, name), &args),
+                    Format::Json => json_files.push(json_entry(&oh, file, Some(&name))?),
+                }
+            }
+        } else {
+            let oh = OhlinkFile::parse(&data).with_context(|| format!("Failed to parse Ohlink file: {}", file))?;
+            match args.format {
+                Format::Text => dump(&oh, file, &args),
+                Format::Json => json_files.push(json_entry(&oh, file, None)?),
+            }
+        }
+    }
+    if args.format == Format::Json {
+        let doc = serde_json::json!({ "schema_version": report::SCHEMA_VERSION, "files": json_files });
+        println!("{}", serde_json::to_string_pretty(&doc)?);
+    }
+    Ok(())
+}
+
+/// JSON 中的一个文件：`path`（归档成员另有 `member`）加上完整的文件视图
+fn json_entry(oh: &OhlinkFile, path: &str, member: Option<&str>) -> Result<serde_json::Value> {
+    let mut entry = serde_json::to_value(oh.report())?;
+    entry["path"] = path.into();
+    entry["member"] = member.into();
+    Ok(entry)
+}
+
+/// 按选项输出一个 Ohlink 文件（或归档成员）
+fn dump(oh: &OhlinkFile, label: &str, args: &Args) {
+    let all = args.all_headers;
+    let any_mode = args.file_headers || args.section_headers || args.reloc || args.syms || args.full_contents || args.disassemble;
+    println!("\n{}:\tfile format ohlink-aarch64", label);
+    // 显示用的符号表：下标与文件中的一致，名字按需还原
+    let symbols: Vec<(String, Nlist64)> = oh
+        .symbols()
+        .into_iter()
+        .map(|(name, n)| (demangle::display_name(&name, args.demangle).into_owned(), n))
+        .collect();
+    if args.file_headers || all || !any_mode {
+        print_file_header(oh);
+    }
+    if args.section_headers || all {
+        print_section_headers(oh, &args.sections);
+    }
+    if args.syms || all {
+        print_symbols(oh, &symbols);
+    }
+    if args.reloc || all {
+        print_relocations(oh, &symbols, &args.sections);
+    }
+    if args.full_contents {
+        print_contents(oh, &args.sections);
+    }
+    if args.disassemble {
+        disassemble(oh, &symbols, &args.sections);
+    }
+}
+
+/// 头部与加载命令
+fn print_file_header(oh: &OhlinkFile) {
     println!("Magic   : {:02x?}", oh.header.magic);
     println!("CPU     : {:#x}", oh.header.cpu_type);
     println!("Type    : {:#x}", oh.header.file_type);
@@ -19,11 +173,16 @@ fn main() -> Result<()> {
     for cmd in &oh.commands {
         match cmd {
             LoadCommand::Segment64(seg, secs) => {
-                let name = std::str::from_utf8(&seg.segname).unwrap_or("").trim_end_matches('\0');
+                let name = oh.segment_name(seg);
                 println!("Segment {:8} vm={:#012x} fileoff={:#012x} filesz={:#x}", name, seg.vmaddr, seg.fileoff, seg.filesize);
                 for s in secs {
-                    let sname = std::str::from_utf8(&s.sectname).unwrap_or("").trim_end_matches('\0');
-                    println!("  Section {:16} addr={:#012x} size={:#x}", sname, s.addr, s.size);
+                    let sname = oh.section_name(s);
+                    match s.flags & ohlink_format::SECTION_TYPE {
+                        ohlink_format::S_PREINIT_FUNC_POINTERS | ohlink_format::S_INIT_FUNC_POINTERS | ohlink_format::S_TERM_FUNC_POINTERS => {
+                            println!("  Section {:16} addr={:#012x} size={:#x} type={:#x} priority={}", sname, s.addr, s.size, s.flags & ohlink_format::SECTION_TYPE, s.reserved1);
+                        }
+                        _ => println!("  Section {:16} addr={:#012x} size={:#x}", sname, s.addr, s.size),
+                    }
                 }
             }
             LoadCommand::Symtab(sym) => {
@@ -32,6 +191,16 @@ fn main() -> Result<()> {
             LoadCommand::NoteAbi { abi_version, flags } => {
                 println!("NoteAbi version={} flags={:#x}", abi_version, flags);
             }
+            LoadCommand::InitFuncs(f) => {
+                println!("InitFuncs preinit={:#x}+{:#x} init={:#x}+{:#x} fini={:#x}+{:#x}",
+                    f.preinit_addr, f.preinit_size, f.init_addr, f.init_size, f.fini_addr, f.fini_size);
+            }
+            LoadCommand::EntryPoint(e) => {
+                println!("EntryPoint {:#x}", e.entry);
+            }
+            LoadCommand::SymbolSizes(sizes) => {
+                println!("SymbolSizes count={}", sizes.len());
+            }
             _ => {}
         }
     }
@@ -53,5 +222,246 @@ fn main() -> Result<()> {
             off += cmdsize as usize;
         }
     }
-    Ok(())
+}
+
+/// `-j` 过滤：不指定时选中所有节；可以写节名或 `段名,节名`
+fn selected(oh: &OhlinkFile, sec: &Section64, filter: &[String]) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    let name = oh.section_name(sec);
+    let full = format!("{},{}", resolve_segname(oh, sec), name);
+    filter.iter().any(|f| *f == name || *f == full)
+}
+
+/// 节所在段的名字（取节头中的 segname 字段）
+fn resolve_segname(oh: &OhlinkFile, sec: &Section64) -> String {
+    ohlink_format::resolve_name(&sec.segname, oh.name_table())
+}
+
+fn section_type_name(sec: &Section64) -> String {
+    match sec.flags & ohlink_format::SECTION_TYPE {
+        ohlink_format::S_REGULAR => String::new(),
+        ohlink_format::S_ZEROFILL => "zerofill".to_string(),
+        ohlink_format::S_INIT_FUNC_POINTERS => format!("init_func_pointers priority={}", sec.reserved1),
+        ohlink_format::S_TERM_FUNC_POINTERS => format!("term_func_pointers priority={}", sec.reserved1),
+        ohlink_format::S_PREINIT_FUNC_POINTERS => format!("preinit_func_pointers priority={}", sec.reserved1),
+        t => format!("type={:#x}", t),
+    }
+}
+
+fn print_section_headers(oh: &OhlinkFile, filter: &[String]) {
+    println!("\nSections:");
+    println!("Idx Name             Segment          Size     Address          Offset   Align  Type Flags");
+    for (i, (seg, sec)) in oh.sections().iter().enumerate() {
+        if !selected(oh, sec, filter) {
+            continue;
+        }
+        let kind = if sec.flags & ohlink_format::SECTION_TYPE == ohlink_format::S_ZEROFILL {
+            "BSS "
+        } else if is_code_section(oh, seg, sec) {
+            "TEXT"
+        } else {
+            "DATA"
+        };
+        let align = if sec.align.is_power_of_two() { format!("2**{}", sec.align.trailing_zeros()) } else { sec.align.to_string() };
+        let mut flags = section_type_name(sec);
+        if sec.nreloc > 0 {
+            if !flags.is_empty() {
+                flags.push(' ');
+            }
+            flags.push_str(&format!("relocs={}", sec.nreloc));
+        }
+        println!(
+            "{:3} {:16} {:16} {:08x} {:016x} {:08x} {:6} {} {}",
+            i + 1, oh.section_name(sec), oh.segment_name(seg), sec.size, sec.addr, sec.offset, align, kind, flags
+        );
+    }
+}
+
+fn print_symbols(oh: &OhlinkFile, symbols: &[(String, Nlist64)]) {
+    let sections = oh.sections();
+    println!("\nSYMBOL TABLE:");
+    for (i, (name, n)) in symbols.iter().enumerate() {
+        let scope = if n.is_external() { 'g' } else { 'l' };
+        let weak = if n.is_weak_def() || n.is_weak_ref() { 'w' } else { ' ' };
+        let (section, value) = if n.is_common() {
+            (format!("*COM* (align {})", n.common_align()), n.n_value)
+        } else if n.is_undefined() {
+            ("*UND*".to_string(), n.n_value)
+        } else if n.is_absolute() {
+            ("*ABS*".to_string(), n.n_value)
+        } else {
+            let section = sections.get((n.n_sect as usize).wrapping_sub(1))
+                .map(|(seg, sec)| format!("{},{}", oh.segment_name(seg), oh.section_name(sec)))
+                .unwrap_or_else(|| format!("<section {}>", n.n_sect));
+            (section, n.n_value)
+        };
+        // 节符号没有名字，以 d 标记并显示节名
+        let (kind, shown) = if name.is_empty() { ('d', section.clone()) } else { (' ', name.clone()) };
+        // 大小：公共符号为 n_value，其余取 LC_SYMBOL_SIZES（没有时为 0）
+        let size = if n.is_common() { n.n_value } else { oh.symbol_size(i).unwrap_or(0) };
+        println!("{:016x} {}{}{} {:24} {:016x} {}", value, scope, weak, kind, section, size, shown);
+    }
+}
+
+fn print_relocations(oh: &OhlinkFile, symbols: &[(String, Nlist64)], filter: &[String]) {
+    for (seg, sec) in oh.sections() {
+        if sec.nreloc == 0 || !selected(oh, sec, filter) {
+            continue;
+        }
+        println!("\nRELOCATION RECORDS FOR [{},{}]:", oh.segment_name(seg), oh.section_name(sec));
+        println!("OFFSET           TYPE                 VALUE");
+        for r in oh.relocations(sec) {
+            println!(
+                "{:016x} {:20} {}",
+                r.r_addr.wrapping_sub(sec.addr), ohlink_format::relocation_type_name(r.r_type), relocation_target(oh, symbols, &r)
+            );
+        }
+    }
+}
+
+/// 十六进制 + ASCII 转储节内容，零填充节没有文件内容，跳过
+fn print_contents(oh: &OhlinkFile, filter: &[String]) {
+    for (seg, sec) in oh.sections() {
+        if !selected(oh, sec, filter) || sec.flags & ohlink_format::SECTION_TYPE == ohlink_format::S_ZEROFILL {
+            continue;
+        }
+        let bytes = oh.section_data(sec);
+        println!("Contents of section {},{}:", oh.segment_name(seg), oh.section_name(sec));
+        let end = sec.addr + bytes.len() as u64;
+        let width = (format!("{:x}", end).len()).max(4);
+        for (row, chunk) in bytes.chunks(16).enumerate() {
+            let mut hex = String::new();
+            for (i, b) in chunk.iter().enumerate() {
+                if i % 4 == 0 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x}", b));
+            }
+            let ascii: String = chunk.iter().map(|&b| if (0x20..0x7f).contains(&b) { b as char } else { '.' }).collect();
+            println!(" {:0width$x}{:36}  {}", sec.addr + row as u64 * 16, hex, ascii, width = width);
+        }
+    }
+}
+
+/// 是否包含代码：段可执行（默认权限 VM_PROT_ALL 时按 __TEXT 段名判断），且不是只读数据或零填充节
+fn is_code_section(oh: &OhlinkFile, seg: &SegmentCommand64, sec: &Section64) -> bool {
+    let prot = match seg.initprot {
+        ohlink_format::VM_PROT_ALL if oh.segment_name(seg) == "__TEXT" => ohlink_format::VM_PROT_EXECUTE,
+        ohlink_format::VM_PROT_ALL => 0,
+        prot => prot,
+    };
+    let name = oh.section_name(sec);
+    prot & ohlink_format::VM_PROT_EXECUTE != 0
+        && sec.flags & ohlink_format::SECTION_TYPE == ohlink_format::S_REGULAR
+        && !name.starts_with("__const")
+        && !name.starts_with("__cstring")
+}
+
+/// 重定位目标的符号化表示；节符号（无名）显示为所在节名
+fn relocation_target(oh: &OhlinkFile, symbols: &[(String, Nlist64)], r: &Relocation64) -> String {
+    let name = match symbols.get(r.r_symbol as usize) {
+        Some((name, _)) if !name.is_empty() => name.clone(),
+        Some((_, n)) => oh.sections().get((n.n_sect as usize).wrapping_sub(1))
+            .map(|(_, sec)| oh.section_name(sec))
+            .unwrap_or_else(|| format!("<section {}>", n.n_sect)),
+        None => format!("<symbol {}>", r.r_symbol),
+    };
+    match r.r_addend {
+        0 => name,
+        a if a < 0 => format!("{}-{:#x}", name, a.unsigned_abs()),
+        a => format!("{}+{:#x}", name, a),
+    }
+}
+
+/// 反汇编所有代码节：符号作为标签，分支/ADRP 目标标注最近的符号，目标文件中被重定位的操作数显示为符号
+fn disassemble(oh: &OhlinkFile, symbols: &[(String, Nlist64)], filter: &[String]) {
+    let sections = oh.sections();
+    let is_object = oh.header.file_type == ohlink_format::MH_OBJECT;
+    // (节序号, 地址, 名字)，按地址排序
+    let mut labels: Vec<(u8, u64, &str)> = symbols.iter()
+        .filter(|(name, n)| !name.is_empty() && !is_mapping_symbol(name) && n.n_type & ohlink_format::N_TYPE == ohlink_format::N_SECT)
+        .map(|(name, n)| (n.n_sect, n.n_value, name.as_str()))
+        .collect();
+    labels.sort_by_key(|&(sect, addr, _)| (addr, sect));
+
+    for (i, (seg, sec)) in sections.iter().enumerate() {
+        if !is_code_section(oh, seg, sec) || !selected(oh, sec, filter) {
+            continue;
+        }
+        let n_sect = (i + 1) as u8;
+        let bytes = oh.section_data(sec);
+        let relocs: HashMap<u64, Relocation64> = oh.relocations(sec).into_iter().map(|r| (r.r_addr, r)).collect();
+        // 目标文件中各节地址都从 0 开始，按节序号归属；映像中地址唯一，按地址范围归属
+        let in_section = |sect: u8, addr: u64| {
+            if is_object { sect == n_sect } else { addr >= sec.addr && addr < sec.addr + sec.size }
+        };
+        let mut mapping: Vec<(u64, bool)> = symbols.iter()
+            .filter(|(name, n)| n.n_type & ohlink_format::N_TYPE == ohlink_format::N_SECT && in_section(n.n_sect, n.n_value) && is_mapping_symbol(name))
+            .map(|(name, n)| (n.n_value, name.starts_with("$d")))
+            .collect();
+        mapping.sort();
+        // 目标文件中只在本节内查找标签；(地址, 名字) 按地址排序，供二分查找
+        let own: Vec<(u64, &str)> = labels.iter()
+            .filter(|&&(sect, _, _)| !is_object || sect == n_sect)
+            .map(|&(_, a, name)| (a, name))
+            .collect();
+        let near = |addr: u64| {
+            let end = own.partition_point(|&(a, _)| a <= addr);
+            own[..end].last()
+                .map(|&(a, name)| if a == addr { format!("<{}>", name) } else { format!("<{}+{:#x}>", name, addr - a) })
+        };
+
+        println!("\nDisassembly of section {},{}:", oh.segment_name(seg), oh.section_name(sec));
+        let mut off = 0usize;
+        while off < bytes.len() {
+            let pc = sec.addr + off as u64;
+            let start = own.partition_point(|&(a, _)| a < pc);
+            for &(_, name) in own[start..].iter().take_while(|&&(a, _)| a == pc) {
+                println!("\n{:016x} <{}>:", pc, name);
+            }
+            let is_data = mapping[..mapping.partition_point(|&(a, _)| a <= pc)].last().is_some_and(|&(_, d)| d);
+            if bytes.len() - off < 4 {
+                let rest = &bytes[off..];
+                let hex: Vec<String> = rest.iter().map(|b| format!("{:02x}", b)).collect();
+                let list: Vec<String> = rest.iter().map(|b| format!("{:#04x}", b)).collect();
+                println!("{:8x}: {:<12}\t.byte\t{}", pc, hex.join(" "), list.join(", "));
+                break;
+            }
+            let word = u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+            let hex = format!("{:02x} {:02x} {:02x} {:02x}", bytes[off], bytes[off + 1], bytes[off + 2], bytes[off + 3]);
+            let reloc = relocs.get(&pc);
+            let text = if is_data {
+                let mut text = format!(".word\t{:#010x}", word);
+                if let Some(r) = reloc {
+                    text.push_str(&format!("\t// {} {}", ohlink_format::relocation_type_name(r.r_type), relocation_target(oh, symbols, r)));
+                }
+                text
+            } else {
+                let mut insn = disasm::decode(word, pc);
+                let mut note = None;
+                match (reloc, insn.target) {
+                    (Some(r), _) if r.r_type == ohlink_format::RELOC_AARCH64_ADD_ABS_LO12_NC && !insn.operands.is_empty() => {
+                        let last = insn.operands.len() - 1;
+                        insn.operands[last] = format!(":lo12:{}", relocation_target(oh, symbols, r));
+                    }
+                    (Some(r), Some((index, _))) => insn.operands[index] = relocation_target(oh, symbols, r),
+                    (Some(r), None) => note = Some(format!("{} {}", ohlink_format::relocation_type_name(r.r_type), relocation_target(oh, symbols, r))),
+                    (None, Some((index, target))) => {
+                        if let Some(label) = near(target) {
+                            insn.operands[index] = format!("{:#x} {}", target, label);
+                        }
+                    }
+                    (None, None) => {}
+                }
+                match note {
+                    Some(note) => format!("{}\t// {}", insn, note),
+                    None => insn.to_string(),
+                }
+            };
+            println!("{:8x}: {}  \t{}", pc, hex, text);
+            off += 4;
+        }
+    }
 }
@@ -0,0 +1,460 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use ohlink_format::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Report section and symbol sizes of Ohlink files", long_about = None)]
+struct Args {
+    /// Ohlink files or .ohlib archives
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Output format: berkeley (text/data/bss per file) or sysv (one line per section)
+    #[arg(long, value_enum, default_value_t = Format::Berkeley)]
+    format: Format,
+    /// Same as --format sysv
+    #[arg(short = 'A', conflicts_with = "format")]
+    sysv: bool,
+    /// Number base for sizes and addresses: 8, 10 or 16
+    #[arg(long, default_value_t = 10, value_parser = parse_radix)]
+    radix: u32,
+    /// Same as --radix 16
+    #[arg(short = 'x', conflicts_with = "radix")]
+    hex: bool,
+    /// Print a line with the sum of all files (berkeley format)
+    #[arg(short = 't', long)]
+    totals: bool,
+    /// List the N largest symbols of every file; with --diff, the N largest symbol changes
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+    /// Demangle Rust and C++ symbol names
+    #[arg(short = 'C', long)]
+    demangle: bool,
+    /// Attribute the sizes of a linked image to its input files using the link map written by ohlink-ld --Map
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+    /// Compare two files (OLD NEW) by segment class, section and symbol
+    #[arg(long)]
+    diff: bool,
+    /// With --diff, exit with an error when the total size grows by more than BYTES
+    #[arg(long, value_name = "BYTES", requires = "diff")]
+    max_growth: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Berkeley,
+    Sysv,
+}
+
+/// Berkeley 格式的三类：只读（代码与常量）、可写数据、零填充
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Class {
+    Text,
+    Data,
+    Bss,
+}
+
+/// 按 `段名,节名` 合并后的一个节（ld 为每个输入节输出一个同名节）
+struct SectionSize {
+    name: String,
+    class: Class,
+    addr: u64,
+    size: u64,
+}
+
+/// 一个文件（或归档成员）的大小汇总
+struct Summary {
+    label: String,
+    sections: Vec<SectionSize>,
+    /// (符号名, 所在节, 大小)
+    symbols: Vec<(String, String, u64)>,
+}
+
+impl Summary {
+    fn totals(&self) -> [u64; 3] {
+        let mut t = [0; 3];
+        for s in &self.sections {
+            t[s.class as usize] += s.size;
+        }
+        t
+    }
+}
+
+/// 节的类别；__PAGEZERO 与调试节不占用映像，返回 None。零填充节（包括公共符号区 __common）计入 bss
+fn classify(file: &OhlinkFile, seg: &SegmentCommand64, sec: &Section64) -> Option<Class> {
+    let segname = file.segment_name(seg);
+    if segname == "__PAGEZERO" || is_debug_section(&segname, &file.section_name(sec)) {
+        return None;
+    }
+    if sec.flags & SECTION_TYPE == S_ZEROFILL {
+        Some(Class::Bss)
+    } else if file.segment_protection(seg) & VM_PROT_WRITE != 0 {
+        Some(Class::Data)
+    } else {
+        Some(Class::Text)
+    }
+}
+
+fn summarize(file: &OhlinkFile, label: String) -> Summary {
+    let all = file.sections();
+    let mut sections: Vec<SectionSize> = Vec::new();
+    for (seg, sec) in &all {
+        let Some(class) = classify(file, seg, sec) else { continue };
+        let name = format!("{},{}", file.segment_name(seg), file.section_name(sec));
+        match sections.iter_mut().find(|s| s.name == name && s.class == class) {
+            Some(s) => {
+                s.addr = s.addr.min(sec.addr);
+                s.size += sec.size;
+            }
+            None => sections.push(SectionSize { name, class, addr: sec.addr, size: sec.size }),
+        }
+    }
+    let extents = file.symbol_extents();
+    let symbols = file
+        .symbols()
+        .into_iter()
+        .zip(extents)
+        .filter_map(|((name, n), size)| {
+            if name.is_empty() || name.starts_with('$') {
+                return None;
+            }
+            let section = if n.is_common() {
+                "*COM*".to_string()
+            } else {
+                let (seg, sec) = all.get((n.n_sect as usize).wrapping_sub(1))?;
+                format!("{},{}", file.segment_name(seg), file.section_name(sec))
+            };
+            Some((name, section, size?))
+        })
+        .collect();
+    Summary { label, sections, symbols }
+}
+
+/// 读取一个输入；归档的每个成员单独汇总，标签为 `成员 (ex 归档)`
+fn load(path: &Path) -> Result<Vec<(Summary, OhlinkFile)>> {
+    let data = fs::read(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+    let shown = path.display().to_string();
+    if data.get(0..4) == Some(&OHLIB_MAGIC[..]) {
+        let arch = OhlibArchive::parse(&data).with_context(|| format!("Failed to parse Ohlib archive: {:?}", path))?;
+        let mut out = Vec::new();
+        for (name, bytes) in arch.members() {
+            let file = OhlinkFile::parse(bytes).with_context(|| format!("Failed to parse member {:?}({})", path, name))?;
+            out.push((summarize(&file, format!("{} (ex {})", name, shown)), file));
+        }
+        Ok(out)
+    } else {
+        let file = OhlinkFile::parse(&data).with_context(|| format!("Failed to parse Ohlink file: {:?}", path))?;
+        Ok(vec![(summarize(&file, shown), file)])
+    }
+}
+
+fn parse_radix(s: &str) -> std::result::Result<u32, String> {
+    match s {
+        "8" | "10" | "16" => Ok(s.parse().unwrap()),
+        _ => Err("must be 8, 10 or 16".to_string()),
+    }
+}
+
+fn num(v: u64, radix: u32) -> String {
+    match radix {
+        8 => format!("{:#o}", v),
+        16 => format!("{:#x}", v),
+        _ => v.to_string(),
+    }
+}
+
+/// 带符号的差值
+fn delta(old: u64, new: u64) -> String {
+    let d = new as i128 - old as i128;
+    if d >= 0 { format!("+{}", d) } else { d.to_string() }
+}
+
+fn berkeley_header(last: &str) {
+    println!("{:>7}\t{:>7}\t{:>7}\t{:>7}\t{:>7}\t{}", "text", "data", "bss", "dec", "hex", last);
+}
+
+fn berkeley_row(t: [u64; 3], label: &str, radix: u32) {
+    let total: u64 = t.iter().sum();
+    println!(
+        "{:>7}\t{:>7}\t{:>7}\t{:>7}\t{:>7x}\t{}",
+        num(t[0], radix), num(t[1], radix), num(t[2], radix), total, total, label
+    );
+}
+
+fn print_sysv(s: &Summary, radix: u32) {
+    let width = s.sections.iter().map(|x| x.name.len()).chain(["section".len()]).max().unwrap_or(0);
+    let total: u64 = s.sections.iter().map(|x| x.size).sum();
+    let size_width = s.sections.iter().map(|x| num(x.size, radix).len()).chain([num(total, radix).len(), 4]).max().unwrap_or(0);
+    println!("{}  :", s.label);
+    println!("{:width$}   {:>size_width$}   addr", "section", "size");
+    for x in &s.sections {
+        println!("{:width$}   {:>size_width$}   {}", x.name, num(x.size, radix), num(x.addr, radix));
+    }
+    println!("{:width$}   {:>size_width$}", "Total", num(total, radix));
+    println!();
+}
+
+fn print_top(s: &Summary, n: usize, args: &Args, radix: u32) {
+    let mut symbols: Vec<&(String, String, u64)> = s.symbols.iter().collect();
+    symbols.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    println!("\nLargest symbols in {}:", s.label);
+    println!("{:>10}  {:24} symbol", "size", "section");
+    for (name, section, size) in symbols.into_iter().take(n) {
+        println!("{:>10}  {:24} {}", num(*size, radix), section, demangle::display_name(name, args.demangle));
+    }
+}
+
+/// 链接映射中的一行：(地址, 大小, 输入文件)
+fn parse_map(text: &str) -> Result<Vec<(u64, u64, String)>> {
+    let parse = |s: &str| -> Result<u64> {
+        let v = match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        v.with_context(|| format!("bad number in link map: {}", s))
+    };
+    let mut out = Vec::new();
+    for line in text.lines().filter(|l| !l.starts_with('#') && !l.trim().is_empty()) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 4 {
+            bail!("malformed link map line: {}", line);
+        }
+        out.push((parse(fields[0])?, parse(fields[1])?, fields[3].to_string()));
+    }
+    Ok(out)
+}
+
+/// 按链接映射把映像中每一类的大小归到输入文件，按总大小从大到小排列
+fn attribute(file: &OhlinkFile, text: &str) -> Result<Vec<(String, [u64; 3])>> {
+    let sections = file.sections();
+    let mut inputs: Vec<(String, [u64; 3])> = Vec::new();
+    for (addr, size, input) in parse_map(text)? {
+        // 按地址找到所在的输出节（跳过覆盖低地址的 __PAGEZERO），以其类别计数
+        let class = sections
+            .iter()
+            .filter_map(|(seg, sec)| Some((classify(file, seg, sec)?, sec)))
+            .find(|(_, sec)| sec.addr <= addr && addr < sec.addr + sec.size.max(1))
+            .map(|(class, _)| class);
+        let Some(class) = class else { continue };
+        match inputs.iter_mut().find(|(name, _)| *name == input) {
+            Some((_, t)) => t[class as usize] += size,
+            None => {
+                let mut t = [0; 3];
+                t[class as usize] = size;
+                inputs.push((input, t));
+            }
+        }
+    }
+    inputs.sort_by(|a, b| b.1.iter().sum::<u64>().cmp(&a.1.iter().sum::<u64>()).then_with(|| a.0.cmp(&b.0)));
+    Ok(inputs)
+}
+
+/// 打印各输入文件的大小；对齐填充等未被映射覆盖的部分单独列出
+fn print_attribution(s: &Summary, file: &OhlinkFile, map: &Path, radix: u32) -> Result<()> {
+    let text = fs::read_to_string(map).with_context(|| format!("Failed to read link map: {:?}", map))?;
+    let inputs = attribute(file, &text)?;
+    println!("\nInputs of {}:", s.label);
+    berkeley_header("input");
+    let mut rest = s.totals();
+    for (name, t) in &inputs {
+        berkeley_row(*t, name, radix);
+        for (r, v) in rest.iter_mut().zip(t) {
+            *r = r.saturating_sub(*v);
+        }
+    }
+    if rest.iter().any(|&v| v != 0) {
+        berkeley_row(rest, "(padding and linker-generated)", radix);
+    }
+    Ok(())
+}
+
+/// 两个文件逐类、逐节、逐符号比较；返回总大小的增长量
+fn print_diff(old: &Summary, new: &Summary, args: &Args, radix: u32) -> i128 {
+    let (ot, nt) = (old.totals(), new.totals());
+    berkeley_header("filename");
+    berkeley_row(ot, &old.label, radix);
+    berkeley_row(nt, &new.label, radix);
+    let (otot, ntot) = (ot.iter().sum::<u64>(), nt.iter().sum::<u64>());
+    println!(
+        "{:>7}\t{:>7}\t{:>7}\t{:>7}\t{:>7}\t(delta)",
+        delta(ot[0], nt[0]), delta(ot[1], nt[1]), delta(ot[2], nt[2]), delta(otot, ntot), ""
+    );
+
+    let mut sections: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for x in &old.sections {
+        sections.entry(&x.name).or_default().0 += x.size;
+    }
+    for x in &new.sections {
+        sections.entry(&x.name).or_default().1 += x.size;
+    }
+    let changed: Vec<_> = sections.iter().filter(|(_, (o, n))| o != n).collect();
+    if !changed.is_empty() {
+        println!("\nSections:");
+        println!("{:>10} {:>10} {:>10}  section", "delta", "old", "new");
+        for (name, (o, n)) in changed {
+            println!("{:>10} {:>10} {:>10}  {}", delta(*o, *n), num(*o, radix), num(*n, radix), name);
+        }
+    }
+
+    // 同名局部符号合并计算
+    let mut symbols: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for (name, _, size) in &old.symbols {
+        symbols.entry(name).or_default().0 += size;
+    }
+    for (name, _, size) in &new.symbols {
+        symbols.entry(name).or_default().1 += size;
+    }
+    let mut changed: Vec<_> = symbols.into_iter().filter(|(_, (o, n))| o != n).collect();
+    changed.sort_by_key(|(name, (o, n))| (std::cmp::Reverse((*n as i128 - *o as i128).abs()), *name));
+    if !changed.is_empty() {
+        println!("\nSymbols:");
+        println!("{:>10} {:>10} {:>10}  symbol", "delta", "old", "new");
+        for (name, (o, n)) in changed.into_iter().take(args.top.unwrap_or(usize::MAX)) {
+            let note = match (o, n) {
+                (0, _) => " (added)",
+                (_, 0) => " (removed)",
+                _ => "",
+            };
+            println!("{:>10} {:>10} {:>10}  {}{}", delta(o, n), num(o, radix), num(n, radix), demangle::display_name(name, args.demangle), note);
+        }
+    }
+    ntot as i128 - otot as i128
+}
+
+/// --max-growth：总大小增长超过上限时报错
+fn check_growth(growth: i128, limit: Option<u64>) -> Result<()> {
+    if let Some(limit) = limit {
+        if growth > limit as i128 {
+            bail!("total size grew by {} bytes, more than the allowed {}", growth, limit);
+        }
+    }
+    Ok(())
+}
+
+/// 把一个输入（可能是归档）的所有成员合并成一份汇总，用于 --diff
+fn merge(path: &Path, parts: Vec<(Summary, OhlinkFile)>) -> Summary {
+    let mut out = Summary { label: path.display().to_string(), sections: Vec::new(), symbols: Vec::new() };
+    for (s, _) in parts {
+        for x in s.sections {
+            match out.sections.iter_mut().find(|y| y.name == x.name && y.class == x.class) {
+                Some(y) => y.size += x.size,
+                None => out.sections.push(x),
+            }
+        }
+        out.symbols.extend(s.symbols);
+    }
+    out
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let radix = if args.hex { 16 } else { args.radix };
+    let format = if args.sysv { Format::Sysv } else { args.format };
+
+    if args.diff {
+        let [old, new] = &args.files[..] else { bail!("--diff takes exactly two files: OLD NEW") };
+        let old = merge(old, load(old)?);
+        let new = merge(new, load(new)?);
+        let growth = print_diff(&old, &new, &args, radix);
+        return check_growth(growth, args.max_growth);
+    }
+
+    let mut loaded = Vec::new();
+    for path in &args.files {
+        loaded.extend(load(path)?);
+    }
+    if args.map.is_some() && loaded.len() != 1 {
+        bail!("--map needs exactly one linked image");
+    }
+    match format {
+        Format::Berkeley => {
+            berkeley_header("filename");
+            let mut sum = [0; 3];
+            for (s, _) in &loaded {
+                let t = s.totals();
+                berkeley_row(t, &s.label, radix);
+                for (a, b) in sum.iter_mut().zip(t) {
+                    *a += b;
+                }
+            }
+            if args.totals {
+                berkeley_row(sum, "(TOTALS)", radix);
+            }
+        }
+        Format::Sysv => {
+            for (s, _) in &loaded {
+                print_sysv(s, radix);
+            }
+        }
+    }
+    if let Some(n) = args.top {
+        for (s, _) in &loaded {
+            print_top(s, n, &args, radix);
+        }
+    }
+    if let Some(map) = &args.map {
+        let (s, file) = &loaded[0];
+        print_attribution(s, file, map, radix)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 链接后的映像：给定大小的 __TEXT,__text 与 __DATA,__data，另有 8 字节零填充的 __DATA,__common
+    fn image(text: usize, data: usize) -> OhlinkFile {
+        let mut b = OhlinkBuilder::new(MH_EXECUTE);
+        b.add_segment("__PAGEZERO", 0).add_section_with("__pagezero", &[], 0, 0, 0x40_0000);
+        b.add_segment("__TEXT", 0x40_0000)
+            .set_protection(VM_PROT_READ | VM_PROT_EXECUTE, VM_PROT_READ | VM_PROT_EXECUTE)
+            .add_section("__text", &vec![0; text], 0);
+        b.add_segment("__DATA", 0x40_1000)
+            .set_protection(VM_PROT_READ | VM_PROT_WRITE, VM_PROT_READ | VM_PROT_WRITE)
+            .add_section("__data", &vec![0; data], 0)
+            .add_section_with("__common", &[], data as u64, 3, 8)
+            .set_section_flags(S_ZEROFILL, 0);
+        b.add_symbol("main", 0x40_0000, 1);
+        b.add_symbol("table", 0x40_1000, 2);
+        OhlinkFile::parse(&b.build()).expect("parse")
+    }
+
+    #[test]
+    fn attribution_from_link_map() {
+        // 与 ohlink-ld 的 link_map 输出相同的格式
+        let map = "# Link map of a.exe.ohlink\n\
+                   # address\tsize\toutput section\tinput\tinput section\n\
+                   0x0000000000400000\t0x10\t__TEXT,__text\ta.ohlink\t__text\n\
+                   0x0000000000400014\t0xc\t__TEXT,__text\tlibc.ohlib(puts.ohlink)\t__text\n\
+                   0x0000000000401000\t0x10\t__DATA,__data\ta.ohlink\t__data\n\
+                   0x0000000000401010\t0x8\t__DATA,__common\tlibc.ohlib(puts.ohlink)\t*COMMON* buf\n";
+        let file = image(0x20, 0x10);
+        assert_eq!(parse_map(map).expect("parse")[1], (0x40_0014, 0xc, "libc.ohlib(puts.ohlink)".to_string()));
+        let inputs = attribute(&file, map).expect("attribute");
+        assert_eq!(inputs, [("a.ohlink".to_string(), [0x10, 0x10, 0]), ("libc.ohlib(puts.ohlink)".to_string(), [0xc, 0, 8])]);
+        // 剩下的是 __text 中的对齐填充
+        assert_eq!(summarize(&file, String::new()).totals(), [0x20, 0x10, 8]);
+        assert!(parse_map("0x0\t0x4\t__TEXT,__text\n").is_err());
+        assert!(parse_map("zz\t0x4\t__TEXT,__text\ta.ohlink\t__text\n").is_err());
+    }
+
+    #[test]
+    fn diff_growth() {
+        let args = Args::parse_from(["ohlink-size", "--diff", "old", "new"]);
+        let old = summarize(&image(0x20, 0x10), "old".to_string());
+        let new = summarize(&image(0x28, 0x8), "new".to_string());
+        assert_eq!(print_diff(&old, &new, &args, 10), 0);
+        let new = summarize(&image(0x30, 0x10), "new".to_string());
+        let growth = print_diff(&old, &new, &args, 10);
+        assert_eq!(growth, 0x10);
+        assert_eq!(print_diff(&new, &old, &args, 10), -0x10);
+        assert!(check_growth(growth, None).is_ok());
+        assert!(check_growth(growth, Some(0x10)).is_ok());
+        let err = check_growth(growth, Some(0xf)).unwrap_err();
+        assert_eq!(err.to_string(), "total size grew by 16 bytes, more than the allowed 15");
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use ohlink_format::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Remove symbols and debug sections from Ohlink files",
+    long_about = None,
+    after_help = "Without any of -s, -g, --strip-unneeded, -x, -X or -N, all symbols are removed (-s).\n\
+                  Symbols named in relocations are always kept. Archives are stripped member by member."
+)]
+struct Args {
+    /// Ohlink files or .ohlib archives, rewritten in place unless -o is given
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Write the result to FILE (single input only)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Remove all symbols and debug sections
+    #[arg(short = 's', long)]
+    strip_all: bool,
+    /// Remove debug sections (__DWARF segment, __debug* sections) and the symbols defined in them
+    #[arg(short = 'g', visible_short_aliases = ['S', 'd'], long)]
+    strip_debug: bool,
+    /// Remove every symbol that is not external and not needed by a relocation
+    #[arg(long)]
+    strip_unneeded: bool,
+    /// Remove all local symbols
+    #[arg(short = 'x', long)]
+    discard_all: bool,
+    /// Remove compiler-generated local labels (.L*)
+    #[arg(short = 'X', long)]
+    discard_locals: bool,
+    /// Keep symbol NAME (may be repeated)
+    #[arg(short = 'K', long = "keep-symbol", value_name = "NAME")]
+    keep_symbol: Vec<String>,
+    /// Remove symbol NAME (may be repeated)
+    #[arg(short = 'N', long = "strip-symbol", value_name = "NAME")]
+    strip_symbol: Vec<String>,
+    /// Keep the symbols listed in FILE, one per line
+    #[arg(long = "keep-symbols", value_name = "FILE")]
+    keep_symbols: Vec<PathBuf>,
+    /// Remove the symbols listed in FILE, one per line
+    #[arg(long = "strip-symbols", value_name = "FILE")]
+    strip_symbols: Vec<PathBuf>,
+}
+
+/// 合并命令行与列表文件后的删除规则
+struct Rules {
+    strip_all: bool,
+    strip_debug: bool,
+    strip_unneeded: bool,
+    discard_all: bool,
+    discard_locals: bool,
+    keep: HashSet<String>,
+    strip: HashSet<String>,
+}
+
+/// 读取符号列表文件：每行一个名字，忽略空行与 `#` 之后的内容
+fn read_symbol_list(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read symbol list: {:?}", path))?;
+    Ok(text
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+impl Rules {
+    fn from_args(args: &Args) -> Result<Self> {
+        let mut keep: HashSet<String> = args.keep_symbol.iter().cloned().collect();
+        for path in &args.keep_symbols {
+            keep.extend(read_symbol_list(path)?);
+        }
+        let mut strip: HashSet<String> = args.strip_symbol.iter().cloned().collect();
+        for path in &args.strip_symbols {
+            strip.extend(read_symbol_list(path)?);
+        }
+        // 与 GNU strip 相同：没有指定删除什么时删除全部符号
+        let explicit = args.strip_debug || args.strip_unneeded || args.discard_all || args.discard_locals || !strip.is_empty();
+        Ok(Rules {
+            strip_all: args.strip_all || !explicit,
+            strip_debug: args.strip_debug,
+            strip_unneeded: args.strip_unneeded,
+            discard_all: args.discard_all,
+            discard_locals: args.discard_locals,
+            keep,
+            strip,
+        })
+    }
+
+    /// 不考虑重定位时是否保留该符号
+    fn keeps(&self, name: &str, n: &Nlist64) -> bool {
+        if self.keep.contains(name) {
+            return true;
+        }
+        if self.strip.contains(name) || self.strip_all {
+            return false;
+        }
+        // 外部符号（含未定义引用）之后只按列表删除
+        if n.is_external() {
+            return true;
+        }
+        !(self.strip_unneeded || self.discard_all || self.discard_locals && is_local_label(name))
+    }
+}
+
+/// 删除一个 Ohlink 文件的符号与调试节，返回重建后的内容
+fn strip_file(file: &OhlinkFile, rules: &Rules) -> Result<Vec<u8>> {
+    // 先删除调试节（连同只被它们引用的符号的重定位），再按剩下的重定位决定哪些符号必须保留
+    let debugless;
+    let file = if rules.strip_all || rules.strip_debug {
+        let mut b = OhlinkBuilder::from_file(file);
+        b.retain_sections(|seg, sect| !is_debug_section(seg, sect))
+            .map_err(|e| match e {
+                RetainError::Referenced(name) => anyhow!("symbol `{}' in a debug section is named in a relocation", name),
+                e => e.into(),
+            })?;
+        debugless = OhlinkFile::parse(&b.build()).context("Failed to parse rebuilt file")?;
+        &debugless
+    } else {
+        file
+    };
+    let needed: HashSet<usize> = file
+        .sections()
+        .iter()
+        .flat_map(|(_, sec)| file.relocations(sec))
+        .map(|r| r.r_symbol as usize)
+        .collect();
+    let mut refused = None;
+    let mut b = OhlinkBuilder::from_file(file);
+    b.retain_symbols(|i, name, n| {
+        if needed.contains(&i) {
+            if rules.strip.contains(name) && refused.is_none() {
+                refused = Some(name.to_string());
+            }
+            return true;
+        }
+        rules.keeps(name, n)
+    })
+    .map_err(|name| anyhow!("symbol `{}' is named in a relocation", name))?;
+    if let Some(name) = refused {
+        bail!("not stripping symbol `{}' because it is named in a relocation", name);
+    }
+    Ok(b.build())
+}
+
+fn strip_bytes(path: &Path, data: &[u8], rules: &Rules) -> Result<Vec<u8>> {
+    if data.get(0..4) == Some(&OHLIB_MAGIC[..]) {
+        let arch = OhlibArchive::parse(data).with_context(|| format!("Failed to parse Ohlib archive: {:?}", path))?;
+        let mut out = OhlibArchive::new();
+        for (name, bytes) in arch.members() {
+            let file = OhlinkFile::parse(bytes).with_context(|| format!("Failed to parse member {:?}({})", path, name))?;
+            let stripped = strip_file(&file, rules).with_context(|| format!("{}({})", path.display(), name))?;
+            out.append_member(&name, &stripped);
+        }
+        // 原来有符号索引的归档按剥离后的成员重新生成
+        Ok(out.to_bytes(arch.index.is_some()))
+    } else {
+        let file = OhlinkFile::parse(data).with_context(|| format!("Failed to parse Ohlink file: {:?}", path))?;
+        strip_file(&file, rules).with_context(|| path.display().to_string())
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.output.is_some() && args.inputs.len() > 1 {
+        bail!("-o can only be used with a single input");
+    }
+    let rules = Rules::from_args(&args)?;
+    for path in &args.inputs {
+        let data = fs::read(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+        let stripped = strip_bytes(path, &data, &rules)?;
+        let out = args.output.as_ref().unwrap_or(path);
+        fs::write(out, &stripped).with_context(|| format!("Failed to write output: {:?}", out))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// __text 引用 helper 与 puts，__DWARF 段中定义 dbg
+    fn sample() -> OhlinkFile {
+        let mut b = OhlinkBuilder::new(MH_OBJECT);
+        b.add_segment("__TEXT", 0).add_section("__text", &[0; 16], 0);
+        b.add_segment("__DWARF", 0x1000).add_section("__debug_info", &[0; 8], 0x1000);
+        b.add_symbol("main", 0, 0);
+        let helper = b.add_symbol_with("helper", 4, 0, N_SECT, 0);
+        b.add_symbol_with(".Ltmp", 8, 0, N_SECT, 0);
+        b.add_symbol_with("loc", 12, 0, N_SECT, 0);
+        b.add_symbol_with("dbg", 0x1000, 1, N_SECT, 0);
+        let puts = b.add_undefined_symbol("puts", 0);
+        b.add_relocations_by_ord(
+            0,
+            &[
+                Relocation64 { r_addr: 0, r_symbol: helper, r_type: RELOC_BRANCH26, r_addend: 0 },
+                Relocation64 { r_addr: 4, r_symbol: puts, r_type: RELOC_BRANCH26, r_addend: 0 },
+            ],
+        );
+        OhlinkFile::parse(&b.build()).expect("parse")
+    }
+
+    fn rules(flags: &[&str]) -> Rules {
+        let args = Args::parse_from(["ohlink-strip"].iter().chain(flags).chain(&["x.o"]));
+        Rules::from_args(&args).expect("rules")
+    }
+
+    /// 剥离后剩下的 (符号名, 节名)
+    fn strip(flags: &[&str]) -> Result<(Vec<String>, Vec<String>)> {
+        let out = OhlinkFile::parse(&strip_file(&sample(), &rules(flags))?)?;
+        let names = out.symbols().into_iter().map(|(name, _)| name).collect();
+        let sections = out.sections().iter().map(|(_, sec)| out.section_name(sec)).collect();
+        Ok((names, sections))
+    }
+
+    #[test]
+    fn keeps() {
+        let local = Nlist64 { n_strx: 0, n_type: N_SECT, n_sect: 1, n_desc: 0, n_value: 0 };
+        let external = Nlist64 { n_type: N_SECT | N_EXT, ..local };
+        let r = rules(&["-X"]);
+        assert!(!r.strip_all);
+        assert!(!r.keeps(".L1", &local));
+        assert!(r.keeps("loc", &local));
+        assert!(r.keeps(".L1", &external));
+        let r = rules(&["-x", "-K", "loc", "-N", "main"]);
+        assert!(r.keeps("loc", &local));
+        assert!(!r.keeps("other", &local));
+        assert!(!r.keeps("main", &external));
+        assert!(r.keeps("ext", &external));
+        // 没有指定删除什么时等同于 -s
+        assert!(rules(&[]).strip_all);
+        assert!(rules(&["-K", "loc"]).strip_all);
+        assert!(!rules(&["-N", "main"]).strip_all);
+    }
+
+    #[test]
+    fn modes() {
+        let text = vec!["__text".to_string()];
+        let all = vec!["__text".to_string(), "__debug_info".to_string()];
+        let check = |flags: &[&str], names: &[&str], sections: &[String]| {
+            assert_eq!(strip(flags).expect("strip"), (names.iter().map(|s| s.to_string()).collect(), sections.to_vec()), "{:?}", flags);
+        };
+        // 重定位引用的 helper/puts 总是保留
+        check(&[], &["helper", "puts"], &text);
+        check(&["-s"], &["helper", "puts"], &text);
+        check(&["-s", "-K", "loc"], &["helper", "loc", "puts"], &text);
+        check(&["-g"], &["main", "helper", ".Ltmp", "loc", "puts"], &text);
+        check(&["--strip-unneeded"], &["main", "helper", "puts"], &all);
+        check(&["-x"], &["main", "helper", "puts"], &all);
+        check(&["-X"], &["main", "helper", "loc", "dbg", "puts"], &all);
+        check(&["-N", "main"], &["helper", ".Ltmp", "loc", "dbg", "puts"], &all);
+    }
+
+    #[test]
+    fn refuses_relocation_symbols() {
+        let err = strip(&["-N", "helper"]).unwrap_err();
+        assert!(err.to_string().contains("not stripping symbol `helper'"), "{}", err);
+    }
+}
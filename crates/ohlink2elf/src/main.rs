@@ -0,0 +1,54 @@
+// crates/ohlink2elf/src/main.rs
+use anyhow::{Context, Result};
+use clap::Parser;
+use ohlink_elf::convert_ohlink_to_elf;
+use ohlink_format::*;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Convert Ohlink objects and executables back to ELF", long_about = None)]
+struct Args {
+    /// Input Ohlink file
+    input: PathBuf,
+
+    /// Output ELF file (default: input with .o for objects, .elf otherwise)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Entry symbol for executables (default: _start, else the __TEXT base)
+    #[arg(short = 'e', long)]
+    entry: Option<String>,
+
+    /// Verbose output
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let data = fs::read(&args.input)
+        .with_context(|| format!("Failed to read input file: {:?}", args.input))?;
+    let file = OhlinkFile::parse(&data)
+        .with_context(|| format!("Failed to parse Ohlink file: {:?}", args.input))?;
+
+    let elf_data = convert_ohlink_to_elf(&file, args.entry.as_deref())
+        .with_context(|| "Conversion failed")?;
+
+    let output_path = args.output.unwrap_or_else(|| {
+        let mut path = args.input.clone();
+        path.set_extension(if file.header.file_type == MH_OBJECT { "o" } else { "elf" });
+        path
+    });
+    fs::write(&output_path, &elf_data)
+        .with_context(|| format!("Failed to write output: {:?}", output_path))?;
+
+    if args.verbose {
+        println!("Input: {:?} (file type {:#x})", args.input, file.header.file_type);
+        println!("Sections: {}", file.sections().len());
+        println!("Symbols: {}", file.symbols().len());
+    }
+    println!("Converted: {:?} -> {:?} ({} bytes)", args.input, output_path, elf_data.len());
+    Ok(())
+}
@@ -9,6 +9,7 @@ fn main() {
             for (i, s) in us.segments.iter().enumerate() {
                 println!("  [{}] vmaddr={:#x} fileoff={:#x} size={:#x} prot={:#x}", i, s.vmaddr, s.fileoff, s.filesize, s.prot);
             }
+            println!("  init={:x?} fini={:x?}", us.init_funcs, us.fini_funcs);
         }
         Err(e) => {
             eprintln!("Load error: {:?}", e);
@@ -1,4 +1,4 @@
-use ohlink_format::{OhlinkFile, LoadCommand, SymtabCommand, Nlist64, LC_NOTE_ABI};
+use ohlink_format::{OhlinkFile, LoadCommand, SymtabCommand, InitFuncsCommand, Nlist64, LC_NOTE_ABI};
 use crate::{UserSpace, SegmentMap};
 
 pub fn ohlink_load(binary: &[u8]) -> Result<UserSpace, ohlink_format::OhlinkError> {
@@ -7,12 +7,15 @@ pub fn ohlink_load(binary: &[u8]) -> Result<UserSpace, ohlink_format::OhlinkErro
     let mut has_hnx_note = false;
     let mut segments: Vec<SegmentMap> = Vec::new();
     let mut symtab: Option<SymtabCommand> = None;
+    let mut init_cmd: Option<InitFuncsCommand> = None;
 
     for cmd in &oh.commands {
         match cmd {
-            LoadCommand::NoteAbi { abi_version, .. } => {
-                if *abi_version == ohlink_format::NOTE_ABI_VERSION { has_hnx_note = true; }
+            LoadCommand::NoteAbi { abi_version, .. } if *abi_version == ohlink_format::NOTE_ABI_VERSION => {
+                has_hnx_note = true;
             }
+            // 调试信息段不映射
+            LoadCommand::Segment64(seg, _secs) if oh.segment_name(seg) == "__DWARF" => {}
             LoadCommand::Segment64(seg, _secs) => {
                 segments.push(SegmentMap {
                     vmaddr: seg.vmaddr,
@@ -22,6 +25,7 @@ pub fn ohlink_load(binary: &[u8]) -> Result<UserSpace, ohlink_format::OhlinkErro
                 });
             }
             LoadCommand::Symtab(s) => { symtab = Some(*s); }
+            LoadCommand::InitFuncs(f) => { init_cmd = Some(*f); }
             _ => {}
         }
     }
@@ -60,9 +64,9 @@ pub fn ohlink_load(binary: &[u8]) -> Result<UserSpace, ohlink_format::OhlinkErro
         // do_mmap(vmaddr=seg.vmaddr, bytes=&binary[_file_start as usize.._file_end as usize], prot=_prot)
     }
 
-    // 解析入口：优先查找符号表中的 `_start`
-    let mut entry: u64 = 0;
-    if let Some(sym) = symtab {
+    // 解析入口：优先使用 LC_ENTRY_POINT，其次查找符号表中的 `_start`
+    let mut entry: u64 = oh.entry_point().unwrap_or(0);
+    if let (0, Some(sym)) = (entry, symtab) {
         let nsz = std::mem::size_of::<Nlist64>();
         let mut entries = Vec::new();
         for i in 0..(sym.nsyms as usize) {
@@ -81,13 +85,34 @@ pub fn ohlink_load(binary: &[u8]) -> Result<UserSpace, ohlink_format::OhlinkErro
         // 回退：选择 __TEXT 段的 vmaddr 作为入口
         for cmd in &oh.commands {
             if let LoadCommand::Segment64(seg, _) = cmd {
-                let name = std::str::from_utf8(&seg.segname).unwrap_or("").trim_end_matches('\0');
-                if name == "__TEXT" { entry = seg.vmaddr; break; }
+                if oh.segment_name(seg) == "__TEXT" { entry = seg.vmaddr; break; }
             }
         }
     }
 
-    Ok(UserSpace { entry, segments })
+    // 构造/析构函数表：preinit 先于 init 调用；.fini_array 从后往前执行，按调用顺序反转
+    let mut init_funcs = Vec::new();
+    let mut fini_funcs = Vec::new();
+    if let Some(f) = init_cmd {
+        init_funcs.extend(read_func_ptrs(binary, &segments, f.preinit_addr, f.preinit_size));
+        init_funcs.extend(read_func_ptrs(binary, &segments, f.init_addr, f.init_size));
+        fini_funcs.extend(read_func_ptrs(binary, &segments, f.fini_addr, f.fini_size));
+        fini_funcs.reverse();
+    }
+
+    Ok(UserSpace { entry, segments, init_funcs, fini_funcs })
+}
+
+/// 通过段映射把虚拟地址处的指针数组换算到文件内容读取
+fn read_func_ptrs(binary: &[u8], segments: &[SegmentMap], addr: u64, size: u64) -> Vec<u64> {
+    let seg = segments.iter().find(|s| addr >= s.vmaddr && addr + size <= s.vmaddr + s.filesize);
+    let Some(seg) = seg else { return Vec::new(); };
+    let start = (seg.fileoff + (addr - seg.vmaddr)) as usize;
+    let end = (start + size as usize).min(binary.len());
+    binary.get(start..end).unwrap_or(&[])
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect()
 }
 
 fn read_cstr(buf: &[u8], off: usize) -> String {
@@ -96,3 +121,24 @@ fn read_cstr(buf: &[u8], off: usize) -> String {
     while end < buf.len() && buf[end] != 0 { end += 1; }
     String::from_utf8_lossy(&buf[off..end]).to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ohlink_format::{OhlinkBuilder, MH_EXECUTE, S_INIT_FUNC_POINTERS, S_TERM_FUNC_POINTERS};
+
+    #[test]
+    fn fini_funcs_run_back_to_front() {
+        let ptrs = |a: u64, b: u64| [a.to_le_bytes(), b.to_le_bytes()].concat();
+        let mut b = OhlinkBuilder::new(MH_EXECUTE);
+        b.add_segment("__DATA", 0x8000)
+            .add_section_with("__init_array", &ptrs(0x4000_0000, 0x4000_0004), 0, 8, 16)
+            .set_section_flags(S_INIT_FUNC_POINTERS, 0)
+            .add_section_with("__fini_array", &ptrs(0x4000_0008, 0x4000_000c), 16, 8, 16)
+            .set_section_flags(S_TERM_FUNC_POINTERS, 0);
+        b.set_init_funcs((0, 0), (0x8000, 16), (0x8010, 16));
+        let us = ohlink_load(&b.build()).expect("load");
+        assert_eq!(us.init_funcs, [0x4000_0000, 0x4000_0004]);
+        assert_eq!(us.fini_funcs, [0x4000_000c, 0x4000_0008]);
+    }
+}
@@ -12,5 +12,9 @@ pub struct SegmentMap {
 pub struct UserSpace {
     pub entry: u64,
     pub segments: Vec<SegmentMap>,
+    /// 进入 entry 前依次调用（preinit 在前，init 在后）
+    pub init_funcs: Vec<u64>,
+    /// 进程退出时依次调用（已按 .fini_array 的逆序排列）
+    pub fini_funcs: Vec<u64>,
 }
 